@@ -77,7 +77,7 @@ pub enum SoladError {
     MathOverflow,
     #[msg("Invalid shard ID")]
     InvalidShardId,
-    #[msg("Insufficient nodes available")]
+    #[msg("Insufficient active nodes with the minimum stake")]
     InsufficientNodes,
     #[msg("Unauthorized access")]
     Unauthorized,
@@ -131,4 +131,128 @@ pub enum SoladError {
     TimeoutNotExpired,
     #[msg("Invalid replacement account data")]
     InvalidReplacementAccount,
+    #[msg("Invalid pending authority")]
+    InvalidPendingAuthority,
+    #[msg("No authority handover in progress")]
+    NoPendingAuthority,
+    #[msg("Invalid config timelock")]
+    InvalidTimelock,
+    #[msg("Config change timelock not expired")]
+    TimelockNotExpired,
+    #[msg("Invalid reporting window")]
+    InvalidReportingWindow,
+    #[msg("Invalid user upload limit")]
+    InvalidUploadLimit,
+    #[msg("Invalid oversized report threshold")]
+    InvalidReportThreshold,
+    #[msg("Invalid PoS submission limit")]
+    InvalidSubmissionLimit,
+    #[msg("Invalid SlotHashes sysvar account")]
+    InvalidSlotHashes,
+    #[msg("Challenge does not match this upload shard")]
+    InvalidChallenge,
+    #[msg("Challenge epoch is not the current epoch")]
+    InvalidChallengeEpoch,
+    #[msg("Challenge response deadline has passed")]
+    ChallengeExpired,
+    #[msg("Node already answered this challenge")]
+    ChallengeAlreadyAnswered,
+    #[msg("Proofs do not match the challenged leaf indices")]
+    ChallengeIndexMismatch,
+    #[msg("Upload has expired")]
+    UploadExpired,
+    #[msg("Invalid Merkle root")]
+    InvalidMerkleRoot,
+    #[msg("Invalid chunk size or leaf count")]
+    InvalidChunkLayout,
+    #[msg("PoS batch is empty or exceeds the submission limit")]
+    InvalidBatchSize,
+    #[msg("Invalid unbonding period")]
+    InvalidUnbondingPeriod,
+    #[msg("No unbonded stake to withdraw")]
+    NoUnbondingStake,
+    #[msg("Unbonding period not complete")]
+    UnbondingNotComplete,
+    #[msg("Node still has stake bonded or unbonding")]
+    NodeHasStake,
+    #[msg("Commission must be at most 100 percent")]
+    InvalidCommission,
+    #[msg("Not enough delegated shares")]
+    InsufficientShares,
+    #[msg("Node is not active")]
+    NodeInactive,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Node metadata is missing or exceeds its bounds")]
+    InvalidNodeMetadata,
+    #[msg("No staked node has enough free capacity for the shard")]
+    InsufficientNodeCapacity,
+    #[msg("Registry page does not match the node or the open page")]
+    InvalidRegistryPage,
+    #[msg("Registry page is full")]
+    RegistryPageFull,
+    #[msg("Replacement candidates do not match the sampled registry window")]
+    InvalidCandidateWindow,
+    #[msg("Payment mint is not accepted")]
+    MintNotAccepted,
+    #[msg("Token accounts are required for uploads paid in an SPL token")]
+    MissingTokenAccount,
+    #[msg("Token account has the wrong mint or owner")]
+    InvalidTokenAccount,
+    #[msg("USD pricing needs a price feed, a maximum price age and a confidence bound")]
+    InvalidPriceFeedConfig,
+    #[msg("Price feed account is missing or malformed")]
+    InvalidPriceFeed,
+    #[msg("Price feed is stale")]
+    StalePrice,
+    #[msg("Price feed confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("Shard claim account does not match the upload, shard and node")]
+    InvalidShardClaim,
+    #[msg("Replication factor range must satisfy 2 <= min <= max <= 8")]
+    InvalidReplicationRange,
+    #[msg("Replication factor is outside the configured range")]
+    InvalidReplicationFactor,
+    #[msg("Erasure layout needs data and parity fragments and a root for each, 15 at most")]
+    InvalidErasureLayout,
+    #[msg("New owner must be a key other than the current owner")]
+    InvalidUploadOwner,
+    #[msg("Delegate must be a key other than the owner")]
+    InvalidUploadDelegate,
+    #[msg("Sponsor account is missing or does not match the upload's sponsor")]
+    InvalidSponsor,
+    #[msg("Inactivity window must be between 1 and 16 epochs")]
+    InvalidInactivityWindow,
+    #[msg("Node answered or was not assigned one of the window's challenges")]
+    NodeNotInactive,
+    #[msg("Dispute window must be at least one epoch")]
+    InvalidDisputeWindow,
+    #[msg("Oversized reports on the shard can still be disputed")]
+    DisputeWindowOpen,
+    #[msg("Dispute window for the shard's oversized reports has closed")]
+    DisputeWindowClosed,
+    #[msg("Commitment disproves none of the shard's oversized reports")]
+    NoFalseReports,
+    #[msg("Stake escrow does not belong to the node")]
+    InvalidStakeEscrow,
+    #[msg("Challenge leaves have not been revealed yet")]
+    ChallengeNotRevealed,
+    #[msg("Challenge leaves were already revealed")]
+    ChallengeAlreadyRevealed,
+    #[msg("Challenge seed slot has not passed yet")]
+    ChallengeSeedPending,
+    #[msg("Challenge seed slot has aged out of SlotHashes; issue the challenge again")]
+    ChallengeSeedExpired,
+    #[msg("Challenge was already issued for this shard and epoch")]
+    ChallengeAlreadyIssued,
+    #[msg("Challenge can still be used as inactivity evidence")]
+    ChallengeStillOpen,
+    #[msg("Uploader was already slashed for this shard")]
+    ShardAlreadySlashed,
+    #[msg("Upload nodes have not been assigned yet")]
+    UploadNotAssigned,
+    #[msg("Upload nodes were already assigned")]
+    UploadAlreadyAssigned,
+    #[msg("Too little of the epoch is left to respond to a challenge")]
+    ChallengeWindowTooShort,
 }
@@ -1,39 +1,54 @@
 pub use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock::UnixTimestamp;
 
+use crate::states::{ConfigParams, HashAlgo};
+
 #[event]
 pub struct ConfigInitializedEvent {
-    pub treasury: Pubkey,
-    pub sol_per_gb: u64,
-    pub treasury_fee_percent: u64,
-    pub node_fee_percent: u64,
-    pub shard_min_mb: u64,
-    pub epochs_total: u64,
-    pub slash_penalty_percent: u64,
-    pub min_shard_count: u8,
-    pub max_shard_count: u8,
-    pub slots_per_epoch: u64,
-    pub min_node_stake: u64,
-    pub replacement_timeout_epochs: u64,
-    pub min_lamports_per_upload: u64,
-    pub user_slash_penalty_percent: u64,
-    pub reporting_window: u64,
-    pub oversized_report_threshold: f64,
+    pub authority: Pubkey,
+    pub params: ConfigParams,
+}
+
+#[event]
+pub struct ConfigChangeQueuedEvent {
+    pub proposer: Pubkey,
+    pub queued_epoch: u64,
+    pub activation_epoch: u64,
+    pub params: ConfigParams,
+}
+
+#[event]
+pub struct ConfigChangeCancelledEvent {
+    pub authority: Pubkey,
+    pub activation_epoch: u64,
 }
 
 #[event]
 pub struct ConfigUpdatedEvent {
-    pub sol_per_gb: u64,
-    pub treasury_fee_percent: u64,
-    pub node_fee_percent: u64,
-    pub shard_min_mb: u64,
-    pub epochs_total: u64,
-    pub slash_penalty_percent: u64,
-    pub min_shard_count: u8,
-    pub max_shard_count: u8,
-    pub slots_per_epoch: u64,
-    pub min_node_stake: u64,
-    pub replacement_timeout_epochs: u64,
+    pub authority: Pubkey,
+    pub params: ConfigParams,
+    pub applied_epoch: u64,
+}
+
+#[event]
+pub struct MintPricingUpdatedEvent {
+    pub mint: Pubkey,
+    pub price_per_gb: u64,
+    pub pending_price_per_gb: u64,
+    pub activation_epoch: u64,
+    pub is_enabled: bool,
+}
+
+#[event]
+pub struct AuthorityProposedEvent {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
 }
 
 #[event]
@@ -42,6 +57,69 @@ pub struct NodeRegisteredEvent {
     pub stake_amount: u64,
 }
 
+#[event]
+pub struct NodeMetadataUpdatedEvent {
+    pub node: Pubkey,
+    pub http_url: String,
+    pub multiaddr: String,
+    pub region: String,
+    pub capacity_mb: u64,
+    pub version: String,
+}
+
+#[event]
+pub struct StakeAddedEvent {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub stake_amount: u64,
+}
+
+#[event]
+pub struct UnstakeRequestedEvent {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub stake_amount: u64,
+    pub unbonding_amount: u64,
+    pub release_epoch: u64,
+}
+
+#[event]
+pub struct StakeWithdrawnEvent {
+    pub node: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CommissionUpdatedEvent {
+    pub node: Pubkey,
+    pub commission_percent: u8,
+    pub effective_slot: u64, // Slot from which the rate applies; the current slot for a cut
+}
+
+#[event]
+pub struct DelegatedEvent {
+    pub node: Pubkey,
+    pub delegator: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+}
+
+#[event]
+pub struct UndelegatedEvent {
+    pub node: Pubkey,
+    pub delegator: Pubkey,
+    pub shares: u64,
+    pub release_epoch: u64,
+}
+
+#[event]
+pub struct DelegatorRewardsClaimedEvent {
+    pub node: Pubkey,
+    pub delegator: Pubkey,
+    pub rewards: u64,
+    pub stake_withdrawn: u64,
+}
+
 #[event]
 pub struct NodeExitedEvent {
     pub node: Pubkey,
@@ -77,6 +155,19 @@ pub struct TimeoutSlashedEvent {
     pub caller_amount: u64,
 }
 
+#[event]
+pub struct InactivitySlashedEvent {
+    pub node: Pubkey,
+    pub data_hash: String,
+    pub shard_id: u8,
+    pub missed_epochs: u64,
+    pub last_pos_time: i64, // 0 if the node never proved any shard
+    pub slash_amount: u64,
+    pub treasury_amount: u64,
+    pub caller_amount: u64,
+    pub replacement_node: Pubkey,
+}
+
 #[event]
 pub struct NodeDeregisteredEvent {
     pub node: Pubkey,
@@ -87,14 +178,109 @@ pub struct NodeDeregisteredEvent {
 pub struct UploadEvent {
     pub upload_pda: Pubkey,
     pub data_hash: String,
+    pub hash_algo: HashAlgo,
     pub size_bytes: u64,
     pub shard_count: u8,
+    pub replication_factor: u8,
+    pub data_shards: u8, // 0 unless the upload is erasure coded
+    pub parity_shards: u8,
+    pub merkle_root: [u8; 32],
+    pub chunk_size: u32,
+    pub leaf_count: u32,
     pub payer: Pubkey,
+    pub sponsor: Pubkey, // Pubkey::default() unless a sponsor funded the upload
+    pub payment_mint: Pubkey,
     pub nodes: Vec<Pubkey>,
     pub storage_duration_days: u64,
     pub timestamp: UnixTimestamp,
 }
 
+#[event]
+pub struct NodeSelectionPendingEvent {
+    pub upload_pda: Pubkey,
+    pub data_hash: String,
+    pub selection_slot: u64, // `assign_upload` can run once this slot has passed
+    pub held_amount: u64,    // Fee held in escrow until the nodes are assigned
+}
+
+#[event]
+pub struct UploadExtendedEvent {
+    pub upload_pda: Pubkey,
+    pub data_hash: String,
+    pub payer: Pubkey,
+    pub additional_days: u64,
+    pub storage_duration_days: u64,
+    pub expiry_time: UnixTimestamp,
+    pub treasury_lamports: u64,
+    pub node_lamports: u64,
+    pub timestamp: UnixTimestamp,
+}
+
+#[event]
+pub struct UploadTransferredEvent {
+    pub upload_pda: Pubkey,
+    pub data_hash: String,
+    pub previous_owner: Pubkey,
+    pub new_owner: Pubkey,
+}
+
+#[event]
+pub struct UploadDelegateSetEvent {
+    pub upload_pda: Pubkey,
+    pub data_hash: String,
+    pub owner: Pubkey,
+    pub delegate: Pubkey, // Pubkey::default() when removed
+}
+
+// Settlement of an upload closed by its payer. Amounts are in units of `payment_mint`.
+// `nodes` and `node_amounts` are parallel: what each node was paid at close, on top of
+// what it had already claimed.
+#[event]
+pub struct UploadClosedEvent {
+    pub upload_pda: Pubkey,
+    pub data_hash: String,
+    pub payer: Pubkey,
+    pub owner: Pubkey,
+    pub payment_mint: Pubkey,
+    pub escrow_balance: u64, // Node share left in escrow before settlement
+    pub settled_amount: u64,
+    pub refund_amount: u64,
+    pub refund_recipient: Pubkey,
+    pub served_secs: i64,
+    pub term_secs: i64,
+    pub nodes: Vec<Pubkey>,
+    pub node_amounts: Vec<u64>,
+    pub timestamp: UnixTimestamp,
+}
+
+#[event]
+pub struct ChallengeIssuedEvent {
+    pub upload_pda: Pubkey,
+    pub data_hash: String,
+    pub shard_id: u8,
+    pub epoch: u64,
+    pub seed_slot: u64,
+    pub deadline_slot: u64,
+    pub issuer: Pubkey,
+}
+
+#[event]
+pub struct ChallengeRevealedEvent {
+    pub upload_pda: Pubkey,
+    pub shard_id: u8,
+    pub epoch: u64,
+    pub seed_slot: u64,
+    pub leaf_indices: Vec<u32>,
+}
+
+#[event]
+pub struct ChallengeClosedEvent {
+    pub upload_pda: Pubkey,
+    pub shard_id: u8,
+    pub epoch: u64,
+    pub issuer: Pubkey,
+}
+
 #[event]
 pub struct PoSEvent {
     pub data_hash: String,
@@ -105,12 +291,25 @@ pub struct PoSEvent {
     pub timestamp: UnixTimestamp,
 }
 
+#[event]
+pub struct PoSBatchItemEvent {
+    pub index: u16,
+    pub data_hash: String,
+    pub shard_id: u8,
+    pub node: Pubkey,
+    pub verified: bool,
+    pub error_code: u32, // 0 when verified
+    pub timestamp: UnixTimestamp,
+}
+
 #[event]
 pub struct RewardEvent {
     pub data_hash: String,
     pub shard_id: u8,
     pub node: Pubkey,
     pub amount: u64,
+    pub delegator_amount: u64, // Part of `amount` credited to delegators
+    pub commission: u64,       // Operator commission taken from the delegators' part
 }
 
 #[event]
@@ -123,6 +322,19 @@ pub struct OversizedDataReportedEvent {
     pub timestamp: UnixTimestamp,
 }
 
+#[event]
+pub struct OversizedReportDisputedEvent {
+    pub data_hash: String,
+    pub shard_id: u8,
+    pub disputer: Pubkey,
+    pub committed_size_mb: u64,
+    pub slashed_reporters: Vec<Pubkey>,
+    pub slash_amount: u64, // Total across `slashed_reporters`
+    pub treasury_amount: u64,
+    pub disputer_amount: u64,
+    pub shard_restored: bool, // Whether the shard is valid again
+}
+
 #[event]
 pub struct UserSlashedEvent {
     pub payer: Pubkey,
@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::AuthorityTransferredEvent,
+    states::{StorageConfig, STORAGE_CONFIG_SEED},
+};
+
+// Completes a config authority handover started by `propose_authority`.
+// The pending authority must sign, proving it controls the key before it replaces
+// the current authority. The pending slot is cleared once the handover completes.
+/// Accepts a config authority handover.
+/// # Arguments
+/// * `ctx` - Context containing the config and pending authority accounts.
+/// # Errors
+/// Returns errors if no handover is in progress or the signer is not the pending authority.
+pub fn process_accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require!(
+        config.pending_authority != Pubkey::default(),
+        SoladError::NoPendingAuthority
+    );
+    require_keys_eq!(
+        config.pending_authority,
+        ctx.accounts.pending_authority.key(),
+        SoladError::Unauthorized
+    );
+
+    let previous_authority = config.authority;
+    config.authority = config.pending_authority;
+    config.pending_authority = Pubkey::default();
+
+    emit!(AuthorityTransferredEvent {
+        previous_authority,
+        new_authority: config.authority,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    pub pending_authority: Signer<'info>,
+}
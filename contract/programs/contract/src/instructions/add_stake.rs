@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::{
+    errors::SoladError,
+    events::StakeAddedEvent,
+    states::{
+        Escrow, Node, NodeRegistry, RegistryPage, StorageConfig, NODE_REGISTRY_SEED, NODE_SEED,
+        REGISTRY_PAGE_SEED, STAKE_ESCROW_SEED, STORAGE_CONFIG_SEED,
+    },
+};
+
+// Tops up the bonded stake of a registered node.
+// The lamports are transferred from the owner into the node's stake escrow and counted
+// in `stake_amount` straight away, so the node's selection weight in `assign_upload` and
+// `request_replacement` grows from the next upload onwards. An inactive node whose bonded
+// stake is back at `min_node_stake`, such as one `request_unstake` took below it, is marked
+// active again, in its registry page as well, and is eligible for new shards.
+// # Arguments
+// * `ctx` - Context containing node, stake escrow, node registry, registry page, owner, config,
+//   and system program accounts.
+// * `amount` - Lamports to add to the node's stake (must be > 0).
+// # Errors
+// Returns `SoladError` variants if the program is not initialized, the caller is not the node owner,
+// or the amount is zero.
+pub fn process_add_stake(ctx: Context<AddStake>, amount: u64) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require!(amount > 0, SoladError::InvalidStake);
+
+    let node = &mut ctx.accounts.node;
+    require!(
+        node.owner == ctx.accounts.owner.key(),
+        SoladError::Unauthorized
+    );
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.owner.to_account_info(),
+                to: ctx.accounts.stake_escrow.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    node.stake_amount = node
+        .stake_amount
+        .checked_add(amount)
+        .ok_or(SoladError::MathOverflow)?;
+    if !node.is_active && node.stake_amount >= config.min_node_stake {
+        node.is_active = true;
+        ctx.accounts
+            .node_registry
+            .activate_node(&mut ctx.accounts.registry_page, &node.key())?;
+    }
+
+    emit!(StakeAddedEvent {
+        node: node.key(),
+        amount,
+        stake_amount: node.stake_amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AddStake<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+    #[account(
+        mut,
+        seeds = [STAKE_ESCROW_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub stake_escrow: Account<'info, Escrow>,
+    #[account(mut, seeds = [NODE_REGISTRY_SEED], bump)]
+    pub node_registry: Account<'info, NodeRegistry>,
+    #[account(
+        mut,
+        seeds = [REGISTRY_PAGE_SEED, &node.registry_page.to_le_bytes()],
+        bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    pub system_program: Program<'info, System>,
+}
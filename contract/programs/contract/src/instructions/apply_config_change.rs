@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::ConfigUpdatedEvent,
+    states::{PendingConfigChange, StorageConfig, PENDING_CONFIG_SEED, STORAGE_CONFIG_SEED},
+};
+
+// Applies a queued configuration change once its timelock has expired.
+// This is a permissionless crank: anyone may call it at or after the activation epoch.
+// Values were validated when the change was queued, so they are copied onto the live
+// config as-is. The pending account is closed and its rent returned to the proposer.
+/// Applies a pending configuration change.
+/// # Arguments
+/// * `ctx` - Context containing the config, pending change, proposer, and caller accounts.
+/// # Errors
+/// Returns errors if the program is not initialized or the activation epoch has not been reached.
+pub fn process_apply_config_change(ctx: Context<ApplyConfigChange>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let pending = &ctx.accounts.pending_config;
+    let current_epoch = Clock::get()?.slot / config.slots_per_epoch;
+    require!(
+        current_epoch >= pending.activation_epoch,
+        SoladError::TimelockNotExpired
+    );
+
+    config.apply_params(&pending.params);
+
+    emit!(ConfigUpdatedEvent {
+        authority: config.authority,
+        params: pending.params.clone(),
+        applied_epoch: current_epoch,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApplyConfigChange<'info> {
+    #[account(
+        mut,
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    #[account(
+        mut,
+        seeds = [PENDING_CONFIG_SEED],
+        bump,
+        close = proposer
+    )]
+    pub pending_config: Account<'info, PendingConfigChange>,
+    /// CHECK: Rent recipient, validated against pending_config.proposer
+    #[account(mut, address = pending_config.proposer)]
+    pub proposer: AccountInfo<'info>,
+    pub caller: Signer<'info>,
+}
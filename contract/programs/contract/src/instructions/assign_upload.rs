@@ -0,0 +1,321 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+
+use crate::{
+    errors::SoladError,
+    events::{NodeSelectionPendingEvent, UploadEvent},
+    selection::{
+        challenge_seed, challenge_seed_expired, registry_window, select_weighted, upload_seed,
+        SelectionRng,
+    },
+    states::{
+        Escrow, Node, NodeRegistry, RegistryPage, StorageConfig, Upload, ESCROW_SEED,
+        MAX_UPLOAD_CANDIDATES, NODE_REGISTRY_SEED, REGISTRY_PAGE_SEED, SELECTION_SEED_DELAY,
+        UPLOAD_SEED,
+    },
+    token::{self, TOKEN_PROGRAM_ID},
+};
+
+// Assigns a paid upload's shards to nodes. Anyone may crank this once the upload's
+// selection slot has passed; the hash of that slot, which did not exist when the upload was
+// paid for, seeds the upload's `selection_seed`. The seed fixes the candidate window, a
+// slice of one registry page, and the draw within it. The caller passes the window's node
+// accounts in any order, but cannot add or leave out a node.
+// Each shard only draws from nodes whose free capacity fits it, and the assigned megabytes
+// are committed on the node until the shard is closed or expires. Each shard is assigned up
+// to `replication_factor` nodes and at least the configured minimum; a fragment of an
+// erasure-coded upload goes to one node, and no node holds two fragments of the upload.
+// The fee held in escrow was charged for every requested replica. Only the share for the
+// replicas actually assigned is kept: the treasury share is paid to the treasury, the node
+// share stays in escrow, and the rest is refunded to the sponsor or owner.
+// If the selection slot aged out of SlotHashes before anyone cranked, a new selection slot
+// is set `SELECTION_SEED_DELAY` slots ahead instead.
+// Remaining accounts: the node accounts of the candidate window, writable.
+/// Assigns an upload's shards to nodes.
+/// # Arguments
+/// * `ctx` - Context containing upload, escrow, config, registry, SlotHashes, treasury, and
+///   refund accounts.
+/// * `data_hash` - Hash of the uploaded data.
+/// # Errors
+/// Returns errors if the nodes were already assigned, the selection slot has not passed,
+/// the candidate window is wrong, there are too few staked nodes or too little node
+/// capacity, or for payment issues.
+pub fn process_assign_upload<'info>(
+    ctx: Context<'_, '_, 'info, 'info, AssignUpload<'info>>,
+    data_hash: String,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let upload = &mut ctx.accounts.upload;
+    require!(upload.data_hash == data_hash, SoladError::InvalidHash);
+    require!(!upload.nodes_assigned, SoladError::UploadAlreadyAssigned);
+
+    let slot = Clock::get()?.slot;
+    if challenge_seed_expired(&ctx.accounts.slot_hashes, upload.selection_slot)? {
+        upload.selection_slot = slot
+            .checked_add(SELECTION_SEED_DELAY)
+            .ok_or(SoladError::MathOverflow)?;
+        emit!(NodeSelectionPendingEvent {
+            upload_pda: upload.key(),
+            data_hash,
+            selection_slot: upload.selection_slot,
+            held_amount: ctx.accounts.escrow.lamports,
+        });
+        return Ok(());
+    }
+
+    // The selection seed fixes the candidate window: a slice of one registry page
+    let upload_key = upload.key();
+    let entropy = challenge_seed(&ctx.accounts.slot_hashes, upload.selection_slot)?;
+    let selection_seed = upload_seed(&entropy, &upload_key);
+    let (page_index, start_seed) =
+        registry_window(&selection_seed, ctx.accounts.node_registry.page_count);
+    let candidate_page = &ctx.accounts.candidate_page;
+    require!(
+        candidate_page.index == page_index,
+        SoladError::InvalidCandidateWindow
+    );
+    let window = candidate_page.window(start_seed, MAX_UPLOAD_CANDIDATES);
+    require!(
+        ctx.remaining_accounts.len() == window.len(),
+        SoladError::InvalidCandidateWindow
+    );
+
+    // Collect and validate nodes
+    let mut node_stakes = Vec::new();
+    let mut free_capacity_mb = Vec::new();
+    let mut processed_keys = Vec::new();
+
+    // Distinct accounts, each in the window and as many as it holds, are exactly the window
+    for node_info in ctx.remaining_accounts.iter() {
+        let node_key = node_info.key();
+        require!(
+            !processed_keys.contains(&node_key),
+            SoladError::DuplicateNodeAccount
+        );
+        let entry = window
+            .iter()
+            .find(|entry| entry.node == node_key)
+            .ok_or(SoladError::InvalidCandidateWindow)?;
+        require!(node_info.is_writable, SoladError::AccountNotWritable);
+        processed_keys.push(node_key);
+        if !entry.is_active {
+            continue;
+        }
+        let node_account: Account<Node> = Account::try_from(node_info)?;
+        if node_account.is_active && node_account.stake_amount >= config.min_node_stake {
+            node_stakes.push((node_key, node_account.selection_weight()?));
+            free_capacity_mb.push(node_account.free_capacity_mb());
+        }
+    }
+
+    require!(!node_stakes.is_empty(), SoladError::InsufficientNodes);
+    require!(
+        upload.shard_count as usize <= node_stakes.len(),
+        SoladError::InsufficientNodes
+    );
+
+    // Assign nodes to shards, skipping nodes that cannot fit the shard
+    let erasure = upload.data_shards > 0;
+    let mut assigned_mb = vec![0u64; node_stakes.len()];
+    let mut assigned_slots = vec![0u64; node_stakes.len()];
+    let mut updated_nodes: Vec<Pubkey> = Vec::new();
+
+    let min_nodes = if erasure {
+        1
+    } else {
+        config.min_replication_factor as usize
+    };
+    let replication_factor = upload.replication_factor as usize;
+    for (i, shard) in upload.shards.iter_mut().enumerate() {
+        // A node holding one fragment is never given another
+        let candidates: Vec<(Pubkey, u64)> = node_stakes
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| free_capacity_mb[*j] - assigned_mb[*j] >= shard.size_mb)
+            .filter(|(_, (key, _))| !erasure || !updated_nodes.contains(key))
+            .map(|(_, candidate)| *candidate)
+            .collect();
+        require!(!candidates.is_empty(), SoladError::InsufficientNodeCapacity);
+
+        let mut rng = SelectionRng::new(&selection_seed, &[&[i as u8]]);
+        let nodes = select_weighted(&candidates, replication_factor, &mut rng)?;
+        require!(nodes.len() >= min_nodes, SoladError::InsufficientNodes);
+        for key in nodes.iter() {
+            let j = node_stakes
+                .iter()
+                .position(|(candidate, _)| candidate == key)
+                .ok_or(SoladError::InvalidNodeAccount)?;
+            assigned_mb[j] += shard.size_mb;
+            assigned_slots[j] += 1;
+            if !updated_nodes.contains(key) {
+                updated_nodes.push(*key);
+            }
+        }
+        shard.proven_epochs = vec![0; nodes.len()];
+        shard.node_keys = nodes;
+    }
+
+    // Count one upload slot per shard assigned and commit the assigned capacity; each slot
+    // is given back once through `ShardInfo::mark_upload_released`
+    for node_key in updated_nodes.iter() {
+        let node_info = ctx
+            .remaining_accounts
+            .iter()
+            .find(|acc| acc.key() == *node_key)
+            .ok_or(SoladError::InvalidNodeAccount)?;
+        let mut node_account: Account<Node> = Account::try_from(node_info)?;
+        let j = node_stakes
+            .iter()
+            .position(|(candidate, _)| candidate == node_key)
+            .ok_or(SoladError::InvalidNodeAccount)?;
+        node_account.upload_count = node_account
+            .upload_count
+            .checked_add(assigned_slots[j])
+            .ok_or(SoladError::MathOverflow)?;
+        node_account.commit_capacity(assigned_mb[j])?;
+        node_account.exit(ctx.program_id)?;
+    }
+
+    // Keep the fee for the replicas actually assigned and refund the rest
+    let held_amount = ctx.accounts.escrow.lamports;
+    let stored_bytes = upload.stored_bytes()? as u128;
+    let requested_bytes = (upload.requested_bytes()? as u128).max(1);
+    let node_lamports = (upload.node_lamports as u128 * stored_bytes / requested_bytes) as u64;
+    let treasury_lamports = (held_amount
+        .checked_sub(upload.node_lamports)
+        .ok_or(SoladError::MathOverflow)? as u128
+        * stored_bytes
+        / requested_bytes) as u64;
+    let refund_amount = held_amount
+        .checked_sub(node_lamports)
+        .and_then(|rest| rest.checked_sub(treasury_lamports))
+        .ok_or(SoladError::MathOverflow)?;
+
+    let payment_mint = upload.payment_mint;
+    let escrow_info = ctx.accounts.escrow.to_account_info();
+    if payment_mint == Pubkey::default() {
+        // The escrow is program-owned, so lamports are moved directly
+        **escrow_info.try_borrow_mut_lamports()? -= treasury_lamports + refund_amount;
+        **ctx.accounts.treasury.try_borrow_mut_lamports()? += treasury_lamports;
+        **ctx.accounts.refund_recipient.try_borrow_mut_lamports()? += refund_amount;
+    } else {
+        let escrow_seeds: &[&[u8]] = &[
+            ESCROW_SEED,
+            data_hash.as_bytes(),
+            upload.payer.as_ref(),
+            &[ctx.accounts.escrow.bump],
+        ];
+        let token_program = token::token_program(&ctx.accounts.token_program)?;
+        let (escrow_token, _) = token::checked_token_account(
+            &ctx.accounts.escrow_token,
+            &payment_mint,
+            escrow_info.key,
+        )?;
+        let (treasury_token, _) = token::checked_token_account(
+            &ctx.accounts.treasury_token,
+            &payment_mint,
+            &config.treasury,
+        )?;
+        let (refund_token, _) = token::checked_token_account(
+            &ctx.accounts.refund_token,
+            &payment_mint,
+            ctx.accounts.refund_recipient.key,
+        )?;
+        token::transfer(
+            token_program,
+            escrow_token,
+            treasury_token,
+            &escrow_info,
+            treasury_lamports,
+            &[escrow_seeds],
+        )?;
+        token::transfer(
+            token_program,
+            escrow_token,
+            refund_token,
+            &escrow_info,
+            refund_amount,
+            &[escrow_seeds],
+        )?;
+    }
+
+    ctx.accounts.escrow.lamports = node_lamports;
+    upload.node_lamports = node_lamports;
+    upload.selection_seed = selection_seed;
+    upload.nodes_assigned = true;
+
+    emit!(UploadEvent {
+        upload_pda: upload_key,
+        data_hash,
+        hash_algo: upload.hash_algo,
+        size_bytes: upload.size_bytes,
+        shard_count: upload.shard_count,
+        replication_factor: upload.replication_factor,
+        data_shards: upload.data_shards,
+        parity_shards: upload.parity_shards,
+        merkle_root: upload.merkle_root,
+        chunk_size: upload.chunk_size,
+        leaf_count: upload.leaf_count,
+        payer: upload.payer,
+        sponsor: upload.sponsor,
+        payment_mint,
+        nodes: updated_nodes,
+        storage_duration_days: upload.storage_duration_days,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(data_hash: String)]
+pub struct AssignUpload<'info> {
+    #[account(
+        mut,
+        seeds = [UPLOAD_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
+        bump
+    )]
+    pub upload: Box<Account<'info, Upload>>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    pub config: Account<'info, StorageConfig>,
+    #[account(
+        seeds = [NODE_REGISTRY_SEED],
+        bump
+    )]
+    pub node_registry: Account<'info, NodeRegistry>,
+    // Page holding the upload's candidate window
+    #[account(
+        seeds = [REGISTRY_PAGE_SEED, &candidate_page.index.to_le_bytes()],
+        bump
+    )]
+    pub candidate_page: Box<Account<'info, RegistryPage>>,
+    /// CHECK: SlotHashes sysvar, validated by address and read in `selection`
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+    /// CHECK: Safe
+    #[account(mut, address = config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    /// CHECK: Upload sponsor, or owner if unsponsored; receives the unassigned replicas' fee
+    #[account(mut, address = upload.refund_recipient())]
+    pub refund_recipient: AccountInfo<'info>,
+    // Token accounts, required when the upload was paid in an SPL token
+    /// CHECK: Token account owned by the escrow PDA that holds the fee
+    #[account(mut)]
+    pub escrow_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Treasury's token account for the payment mint
+    #[account(mut)]
+    pub treasury_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Refund recipient's token account for the payment mint
+    #[account(mut)]
+    pub refund_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL Token program, validated by address
+    #[account(address = TOKEN_PROGRAM_ID)]
+    pub token_program: Option<UncheckedAccount<'info>>,
+}
@@ -9,7 +9,8 @@ use anchor_lang::prelude::*;
 
 /// Processes batch requests to replace an exiting node with a replacement node for specified shards.
 /// This function validates the replacement node's stake, verifies the exiting node's involvement in the shards,
-/// checks for existing replacement accounts, and decrements the exiting node's upload count via CPI.
+/// checks for existing replacement accounts, and gives back the exiting node's upload slot on each
+/// replaced shard, at most once per shard; the upload accounts must be writable.
 /// It emits events for each successful replacement request.
 /// # Arguments
 /// * `ctx` - Context containing exiting node, replacement node, config, and remaining accounts for uploads and replacements.
@@ -39,12 +40,13 @@ pub fn process_batch_request_replacement<'info>(
 
     // Initialize vector to track replacements that need processing
     let mut replacements_to_process = Vec::new();
+    let mut released_slots: u64 = 0;
 
     // Iterate over each shard replacement request
     for (i, shard_replacement) in shard_replacements.iter().enumerate() {
         // Retrieve the upload account from remaining accounts (4 accounts per shard: 1 upload + up to 3 replacements)
         let upload_account = &ctx.remaining_accounts[i * 4];
-        let upload: Account<Upload> = Account::try_from(upload_account)?;
+        let mut upload: Account<Upload> = Account::try_from(upload_account)?;
 
         // Derive and validate the upload PDA
         let (upload_pda, _) = Pubkey::find_program_address(
@@ -101,8 +103,17 @@ pub fn process_batch_request_replacement<'info>(
             }
         }
 
-        // If a valid replacement exists, queue it for processing
+        // If a valid replacement exists, release the slot and queue it for processing
         if has_replacement {
+            let shard = upload
+                .shards
+                .iter_mut()
+                .find(|s| s.shard_id == shard_replacement.shard_id)
+                .ok_or(SoladError::InvalidShardId)?;
+            if shard.mark_upload_released(&exiting_node.key()) {
+                released_slots += 1;
+            }
+            upload.exit(ctx.program_id)?;
             replacements_to_process.push((
                 shard_replacement.data_hash.clone(),
                 shard_replacement.shard_id,
@@ -111,14 +122,12 @@ pub fn process_batch_request_replacement<'info>(
         }
     }
 
-    // If there are replacements to process, decrement the exiting node's upload count
+    // If there are replacements to process, drop the released slots from the upload count
     if !replacements_to_process.is_empty() {
-        require!(exiting_node.upload_count > 0, SoladError::InvalidState);
-
         exiting_node.upload_count = exiting_node
             .upload_count
-            .checked_sub(1)
-            .ok_or(SoladError::MathOverflow)?;
+            .checked_sub(released_slots)
+            .ok_or(SoladError::InvalidState)?;
 
         // Emit an event for each replacement request
         for (data_hash, shard_id, storage_fee) in replacements_to_process {
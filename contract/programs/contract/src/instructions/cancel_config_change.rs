@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::ConfigChangeCancelledEvent,
+    states::{PendingConfigChange, StorageConfig, PENDING_CONFIG_SEED, STORAGE_CONFIG_SEED},
+};
+
+// Cancels a queued configuration change before it is applied.
+// Only the config authority may cancel. The pending account is closed and its rent
+// returned to the authority, freeing the slot for a new `update_config` call.
+/// Cancels a pending configuration change.
+/// # Arguments
+/// * `ctx` - Context containing the config, pending change, and authority accounts.
+/// # Errors
+/// Returns errors if the program is not initialized or the signer is not the config authority.
+pub fn process_cancel_config_change(ctx: Context<CancelConfigChange>) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require_keys_eq!(
+        config.authority,
+        ctx.accounts.authority.key(),
+        SoladError::Unauthorized
+    );
+
+    emit!(ConfigChangeCancelledEvent {
+        authority: ctx.accounts.authority.key(),
+        activation_epoch: ctx.accounts.pending_config.activation_epoch,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelConfigChange<'info> {
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    #[account(
+        mut,
+        seeds = [PENDING_CONFIG_SEED],
+        bump,
+        close = authority
+    )]
+    pub pending_config: Account<'info, PendingConfigChange>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::DelegatorRewardsClaimedEvent,
+    states::{
+        Delegation, Escrow, Node, StorageConfig, DELEGATION_SEED, NODE_SEED, STAKE_ESCROW_SEED,
+        STORAGE_CONFIG_SEED,
+    },
+};
+
+// Pays a delegator their accrued rewards and, once the unbonding window has ended, the
+// value of their undelegated shares.
+// Rewards sit in the node account, where `claim_rewards` deposits them; stake sits in the
+// node's stake escrow. Both are program-owned, so lamports are moved directly. Unbonding
+// shares are valued at the pool's current price, so any slash during the window is borne
+// by them too. Claiming before the window ends pays rewards only.
+// # Arguments
+// * `ctx` - Context containing node, delegation, stake escrow, delegator, and config accounts.
+// # Errors
+// Returns `SoladError` variants if the program is not initialized or there is nothing to claim.
+pub fn process_claim_delegator_rewards(ctx: Context<ClaimDelegatorRewards>) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let node = &mut ctx.accounts.node;
+    let delegation = &mut ctx.accounts.delegation;
+    delegation.settle(node.reward_per_share)?;
+    let rewards = delegation.pending_rewards;
+    delegation.pending_rewards = 0;
+
+    let current_epoch = Clock::get()?.slot / config.slots_per_epoch;
+    let mut stake_withdrawn = 0;
+    let unbonded_shares = delegation.unbonding_shares;
+    if unbonded_shares > 0 && current_epoch >= delegation.unbonding_release_epoch {
+        stake_withdrawn = node.shares_to_lamports(unbonded_shares)?;
+        node.delegated_stake -= stake_withdrawn;
+        node.delegator_shares -= unbonded_shares;
+        node.unbonding_shares -= unbonded_shares;
+        delegation.shares -= unbonded_shares;
+        delegation.unbonding_shares = 0;
+        delegation.unbonding_release_epoch = 0;
+    }
+    delegation.reset_debt(node.reward_per_share)?;
+    require!(
+        rewards > 0 || stake_withdrawn > 0,
+        SoladError::NothingToClaim
+    );
+
+    let delegator_info = ctx.accounts.delegator.to_account_info();
+    if rewards > 0 {
+        let node_info = node.to_account_info();
+        let node_lamports = node_info
+            .lamports()
+            .checked_sub(rewards)
+            .ok_or(SoladError::InsufficientFunds)?;
+        **node_info.try_borrow_mut_lamports()? = node_lamports;
+        **delegator_info.try_borrow_mut_lamports()? += rewards;
+    }
+    if stake_withdrawn > 0 {
+        let stake_escrow_info = ctx.accounts.stake_escrow.to_account_info();
+        let escrow_lamports = stake_escrow_info
+            .lamports()
+            .checked_sub(stake_withdrawn)
+            .ok_or(SoladError::InsufficientFunds)?;
+        **stake_escrow_info.try_borrow_mut_lamports()? = escrow_lamports;
+        **delegator_info.try_borrow_mut_lamports()? += stake_withdrawn;
+    }
+
+    emit!(DelegatorRewardsClaimedEvent {
+        node: node.key(),
+        delegator: delegation.delegator,
+        rewards,
+        stake_withdrawn,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimDelegatorRewards<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, node.owner.as_ref()],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+    #[account(
+        mut,
+        seeds = [DELEGATION_SEED, node.key().as_ref(), delegator.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(
+        mut,
+        seeds = [STAKE_ESCROW_SEED, node.owner.as_ref()],
+        bump
+    )]
+    pub stake_escrow: Account<'info, Escrow>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+}
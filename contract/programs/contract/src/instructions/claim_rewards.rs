@@ -1,20 +1,32 @@
-use crate::{errors::SoladError, events::RewardEvent, states::*};
+use crate::{
+    errors::SoladError,
+    events::RewardEvent,
+    states::*,
+    token::{self, TOKEN_PROGRAM_ID},
+};
 pub use anchor_lang::prelude::*;
-use anchor_lang::system_program;
+use std::mem::size_of;
 
 // Allows nodes to claim their storage rewards after submitting Proof of Storage (PoS).
 // Nodes must submit PoS before claiming any rewards, including an initial 25% reward,
 // to prevent abuse. The remaining 75% is distributed as an endowment per epoch after
-// continued PoS submissions. Rewards are calculated based on shard size and node count.
-// Nodes can claim once per epoch, and uploads are finalized after the total epochs are reached.
+// continued PoS submissions. Rewards are calculated based on shard size and replica count.
+// Each (upload, shard, node) keeps its own `ShardClaim` ledger, so claiming one shard never
+// blocks another in the same epoch. A claim pays one endowment epoch per challenge the node
+// has answered for the shard (`ShardInfo.proven_epochs`), never more than have elapsed since
+// the upload epoch and at most `epochs_total`, less those already paid. Epochs the node did
+// not prove are never paid. The node's upload slot and the shard's committed capacity are
+// released once all `epochs_total` epochs are paid.
+// For uploads paid in an SPL token the reward is paid from the token escrow to the node
+// owner's token account and is not shared with delegators.
 /// Claims rewards.
 /// # Arguments
-/// * `ctx` - Context containing upload, node, escrow, and config accounts.
+/// * `ctx` - Context containing upload, node, shard claim, escrow, and config accounts.
 /// * `data_hash` - Hash of the data.
 /// * `shard_id` - ID of the shard.
 /// # Errors
 /// Returns errors for invalid claims, unauthorized nodes, missing PoS submissions, invalid shards,
-// or insufficient rewards.
+/// or insufficient rewards.
 pub fn process_claim_rewards(
     ctx: Context<ClaimRewards>,
     data_hash: String,
@@ -22,7 +34,6 @@ pub fn process_claim_rewards(
 ) -> Result<()> {
     let upload = &ctx.accounts.upload;
     let node = &mut ctx.accounts.node;
-    let escrow = &ctx.accounts.escrow;
     let config = &ctx.accounts.config;
     require!(config.is_initialized, SoladError::NotInitialized);
 
@@ -35,87 +46,122 @@ pub fn process_claim_rewards(
         SoladError::Unauthorized
     );
     require!(shard.verified_count != u8::MAX, SoladError::InvalidShard);
-    require!(shard.verified_count > 0, SoladError::NoPoSSubmitted);
+    let proven_epochs = shard.proven_epochs_of(&node.key());
+    require!(proven_epochs > 0, SoladError::NoPoSSubmitted);
 
-    let current_epoch = Clock::get()?.slot / config.slots_per_epoch;
-    require!(
-        node.last_claimed_epoch < current_epoch,
-        SoladError::AlreadyClaimed
-    );
+    let claim = &mut ctx.accounts.shard_claim;
+    if claim.upload == Pubkey::default() {
+        claim.upload = upload.key();
+        claim.shard_id = shard_id;
+        claim.node = node.key();
+    }
 
-    let size_bytes = upload.size_bytes;
-    let size_mb = (size_bytes + (1024 * 1024 - 1)) / (1024 * 1024); // Ceiling to MB
-    let shard_lamports = upload
-        .node_lamports
-        .checked_mul(shard.size_mb)
-        .ok_or(SoladError::MathOverflow)?
-        .checked_div(size_mb)
-        .ok_or(SoladError::MathOverflow)?;
-    let node_count = shard
-        .node_keys
-        .iter()
-        .filter(|&&k| k != Pubkey::default())
-        .count();
-    let node_lamports = shard_lamports
-        .checked_div(node_count as u64)
-        .ok_or(SoladError::MathOverflow)?;
+    let node_lamports = upload.node_share(shard)?;
 
-    let reward = if node.last_claimed_epoch == 0 {
-        // Initial 25% reward, requires PoS
+    // Initial 25% reward, requires PoS
+    let initial_reward = if claim.initial_claimed {
+        0
+    } else {
         node_lamports
             .checked_mul(25)
             .ok_or(SoladError::MathOverflow)?
             / 100
-    } else {
-        // Epoch-based endowment (75% over epochs_total)
-        let endowment_lamports = node_lamports
-            .checked_mul(75)
-            .ok_or(SoladError::MathOverflow)?
-            / 100;
-        let epoch_lamports = endowment_lamports
-            .checked_div(config.epochs_total)
-            .ok_or(SoladError::MathOverflow)?;
-        if node_count == 1 || upload.shard_count == 1 {
-            epoch_lamports
-        } else {
-            epoch_lamports // PoS ensures verified_count > 0
-        }
     };
 
-    require!(reward >= 1000, SoladError::InsufficientReward);
+    // Epoch-based endowment (75% over epochs_total), for the elapsed epochs the node proved
+    let current_epoch = Clock::get()?.slot / config.slots_per_epoch;
+    let upload_epoch = upload.current_slot / config.slots_per_epoch;
+    let payable_epochs = current_epoch
+        .saturating_sub(upload_epoch)
+        .min(proven_epochs)
+        .min(config.epochs_total);
+    let unpaid_epochs = payable_epochs.saturating_sub(claim.epochs_claimed);
+    let epoch_lamports = node_lamports
+        .checked_mul(75)
+        .ok_or(SoladError::MathOverflow)?
+        / 100
+        / config.epochs_total;
+    let endowment_reward = epoch_lamports
+        .checked_mul(unpaid_epochs)
+        .ok_or(SoladError::MathOverflow)?;
 
-    let seeds = &[
-        ESCROW_SEED,
-        upload.data_hash.as_bytes(),
-        upload.payer.as_ref(),
-        &[ctx.accounts.escrow.bump],
-    ];
-    system_program::transfer(
-        CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            system_program::Transfer {
-                from: escrow.to_account_info(),
-                to: node.to_account_info(),
-            },
-            &[&seeds[..]],
-        ),
-        reward,
-    )?;
+    let reward = initial_reward
+        .checked_add(endowment_reward)
+        .ok_or(SoladError::MathOverflow)?;
+    require!(reward > 0, SoladError::AlreadyClaimed);
+    require!(reward >= 1000, SoladError::InsufficientReward);
 
-    if current_epoch >= config.epochs_total {
-        node.upload_count = node
-            .upload_count
-            .checked_sub(1)
-            .ok_or(SoladError::MathOverflow)?;
+    let escrow = &mut ctx.accounts.escrow;
+    escrow.lamports = escrow
+        .lamports
+        .checked_sub(reward)
+        .ok_or(SoladError::InsufficientFunds)?;
+    let payment_mint = upload.payment_mint;
+    if payment_mint == Pubkey::default() {
+        // The escrow is program-owned, so lamports are moved directly rather than via CPI
+        **escrow.to_account_info().try_borrow_mut_lamports()? -= reward;
+        **node.to_account_info().try_borrow_mut_lamports()? += reward;
+    } else {
+        // Token rewards go straight to the operator's token account
+        let token_program = token::token_program(&ctx.accounts.token_program)?;
+        let (escrow_token, _) =
+            token::checked_token_account(&ctx.accounts.escrow_token, &payment_mint, &escrow.key())?;
+        let (owner_token, _) =
+            token::checked_token_account(&ctx.accounts.owner_token, &payment_mint, &node.owner)?;
+        let escrow_seeds: &[&[u8]] = &[
+            ESCROW_SEED,
+            data_hash.as_bytes(),
+            upload.payer.as_ref(),
+            &[ctx.bumps.escrow],
+        ];
+        token::transfer(
+            token_program,
+            escrow_token,
+            owner_token,
+            &escrow.to_account_info(),
+            reward,
+            &[escrow_seeds],
+        )?;
     }
 
-    node.last_claimed_epoch = current_epoch;
+    let was_complete = claim.epochs_claimed >= config.epochs_total;
+    claim.initial_claimed = true;
+    claim.epochs_claimed = payable_epochs.max(claim.epochs_claimed);
+    claim.last_claimed_epoch = current_epoch;
+    claim.lamports_paid = claim
+        .lamports_paid
+        .checked_add(reward)
+        .ok_or(SoladError::MathOverflow)?;
+
+    // Delegators earn the share of the reward backed by their stake, less commission.
+    // Delegator pools are lamport-denominated, so token rewards are not shared.
+    let (delegator_amount, commission) = if payment_mint == Pubkey::default() {
+        node.apply_pending_commission(Clock::get()?.slot);
+        node.distribute_reward(reward)?
+    } else {
+        (0, 0)
+    };
+
+    if !was_complete && claim.epochs_claimed >= config.epochs_total {
+        let shard = &mut ctx.accounts.upload.shards[shard_id as usize];
+        if shard.mark_upload_released(&node.key()) {
+            node.upload_count = node
+                .upload_count
+                .checked_sub(1)
+                .ok_or(SoladError::MathOverflow)?;
+        }
+        if shard.mark_capacity_released(&node.key()) {
+            node.release_capacity(shard.size_mb);
+        }
+    }
 
     emit!(RewardEvent {
         data_hash,
         shard_id,
         node: node.key(),
         amount: reward,
+        delegator_amount,
+        commission,
     });
 
     Ok(())
@@ -125,16 +171,25 @@ pub fn process_claim_rewards(
 #[instruction(data_hash: String, shard_id: u8)]
 pub struct ClaimRewards<'info> {
     #[account(
+        mut,
         seeds = [UPLOAD_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
         bump
     )]
     pub upload: Account<'info, Upload>,
     #[account(
         mut,
-        seeds = [NODE_SEED, node.key().as_ref()],
+        seeds = [NODE_SEED, owner.key().as_ref()],
         bump
     )]
     pub node: Account<'info, Node>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + size_of::<ShardClaim>(),
+        seeds = [SHARD_CLAIM_SEED, upload.key().as_ref(), &[shard_id], node.key().as_ref()],
+        bump
+    )]
+    pub shard_claim: Account<'info, ShardClaim>,
     #[account(
         mut,
         seeds = [ESCROW_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
@@ -152,5 +207,17 @@ pub struct ClaimRewards<'info> {
         bump
     )]
     pub stake_escrow: Account<'info, Escrow>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
     pub system_program: Program<'info, System>,
+    // Token payout accounts, required when the upload was paid in an SPL token
+    /// CHECK: Token account owned by the escrow PDA that holds the node share
+    #[account(mut)]
+    pub escrow_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Node owner's token account for the payment mint
+    #[account(mut)]
+    pub owner_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL Token program, validated by address
+    #[account(address = TOKEN_PROGRAM_ID)]
+    pub token_program: Option<UncheckedAccount<'info>>,
 }
@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::ChallengeClosedEvent,
+    states::{Challenge, StorageConfig, MAX_INACTIVITY_EPOCHS, STORAGE_CONFIG_SEED},
+};
+
+// Closes a challenge that can no longer matter and returns its rent to the issuer.
+// Anyone may crank this. A challenge stays open while `slash_inactive` could still count
+// it: that looks back `inactivity_epochs` epochs, at most MAX_INACTIVITY_EPOCHS, so the
+// challenge closes once more than that many epochs have passed since it was issued.
+/// Closes an expired PoS challenge.
+/// # Arguments
+/// * `ctx` - Context containing challenge, issuer, and config accounts.
+/// # Errors
+/// Returns errors if the challenge could still serve as inactivity evidence.
+pub fn process_close_challenge(ctx: Context<CloseChallenge>) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let challenge = &ctx.accounts.challenge;
+    let current_epoch = Clock::get()?.slot / config.slots_per_epoch;
+    let closable_epoch = challenge
+        .epoch
+        .checked_add(MAX_INACTIVITY_EPOCHS)
+        .ok_or(SoladError::MathOverflow)?;
+    require!(
+        current_epoch > closable_epoch,
+        SoladError::ChallengeStillOpen
+    );
+
+    emit!(ChallengeClosedEvent {
+        upload_pda: challenge.upload,
+        shard_id: challenge.shard_id,
+        epoch: challenge.epoch,
+        issuer: challenge.issuer,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseChallenge<'info> {
+    #[account(
+        mut,
+        has_one = issuer @ SoladError::Unauthorized,
+        close = issuer
+    )]
+    pub challenge: Account<'info, Challenge>,
+    /// CHECK: Receives the rent; must be the challenge's issuer
+    #[account(mut)]
+    pub issuer: AccountInfo<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+}
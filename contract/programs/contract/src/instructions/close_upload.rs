@@ -2,36 +2,78 @@ use anchor_lang::prelude::*;
 
 use crate::{
     errors::SoladError,
-    states::{Escrow, Node, Replacement, Upload, REPLACEMENT_SEED},
+    events::UploadClosedEvent,
+    states::{
+        Escrow, Node, Replacement, ShardClaim, StorageConfig, Upload, ESCROW_SEED,
+        REPLACEMENT_SEED, SHARD_CLAIM_SEED, STORAGE_CONFIG_SEED,
+    },
+    token::{self, TOKEN_PROGRAM_ID},
 };
 
+// Closes an upload before or after expiry, settling its escrow between nodes and payer.
+// Each node that proved a shard has earned the initial 25% of its share plus the 75%
+// endowment prorated by the time served, `(now - term_start) / (expiry_time - term_start)`,
+// capped like `claim_rewards` at the per-epoch rate times the epochs that node proved.
+// `term_start` is moved past any lapse an expired upload was renewed after, so the term
+// only counts paid storage time.
+// Whatever `claim_rewards` already paid it (its `ShardClaim.lamports_paid`) is deducted, and
+// the accrued rest is paid to the node now. Nodes that proved nothing earn nothing. The
+// unearned remainder of the escrow is refunded to the upload's sponsor if it was
+// sponsored, or else to its owner; the escrow and upload rent always return to the owner. The
+// owner or its delegate may close.
+// Remaining accounts: any pending `Replacement` accounts for the upload first, then for each
+// shard in order and each node assigned to it: the node account and its `ShardClaim` PDA
+// (empty if the node never claimed), followed by the node owner's token account for uploads
+// paid in an SPL token. Sponsored lamport uploads also need the `sponsor` account.
+/// Closes an upload and refunds the unearned escrow.
+/// # Arguments
+/// * `ctx` - Context containing the upload, escrow, config, owner, signer, and optional token accounts.
+/// * `data_hash` - Hash of the data.
+/// # Errors
+/// Returns errors for a signer that is neither owner nor delegate, pending replacements, missing or mismatched node
+/// and shard claim accounts, invalid token accounts, or mathematical overflows.
 pub fn process_close_upload<'info>(
     ctx: Context<'_, '_, 'info, 'info, CloseUpload<'info>>,
     data_hash: String,
-    shard_id: u8,
 ) -> Result<()> {
     let upload = &ctx.accounts.upload;
-    let payer = &ctx.accounts.payer;
+    let config = &ctx.accounts.config;
+    let owner = &ctx.accounts.owner;
+    let refund_recipient = upload.refund_recipient();
 
-    // Verify payer owns the upload
-    require!(upload.payer == payer.key(), SoladError::Unauthorized);
+    // Verify the signer owns the upload or manages it for the owner
+    require!(
+        upload.can_manage(&ctx.accounts.authority.key()),
+        SoladError::Unauthorized
+    );
 
-    // Find the specified shard
-    let shard = upload
-        .shards
-        .iter()
-        .find(|s| s.shard_id == shard_id)
-        .ok_or(SoladError::InvalidShardId)?;
+    let payment_mint = upload.payment_mint;
+    let paid_in_token = payment_mint != Pubkey::default();
+    let group_len = if paid_in_token { 3 } else { 2 };
+    let slot_count: usize = upload.shards.iter().map(|s| s.node_count()).sum();
+    let node_accounts_len = slot_count * group_len;
+    require!(
+        ctx.remaining_accounts.len() >= node_accounts_len,
+        SoladError::InvalidNodeAccount
+    );
+    let (replacement_infos, node_infos) = ctx
+        .remaining_accounts
+        .split_at(ctx.remaining_accounts.len() - node_accounts_len);
 
-    // Check for pending replacements in remaining_accounts (up to 3)
-    for replacement_info in ctx.remaining_accounts.iter().take(3) {
+    // Check for pending replacements
+    for replacement_info in replacement_infos.iter() {
         let replacement: Account<Replacement> = Account::try_from(replacement_info)?;
+        let shard = upload
+            .shards
+            .iter()
+            .find(|s| s.shard_id == replacement.shard_id)
+            .ok_or(SoladError::InvalidShardId)?;
         let (replacement_pda, _) = Pubkey::find_program_address(
             &[
                 REPLACEMENT_SEED,
                 replacement.exiting_node.as_ref(),
                 data_hash.as_bytes(),
-                &[shard_id],
+                &[replacement.shard_id],
             ],
             ctx.program_id,
         );
@@ -42,82 +84,251 @@ pub fn process_close_upload<'info>(
         }
     }
 
-    // Collect unique node keys and their corresponding AccountInfo with upload_count > 0
-    let mut unique_nodes: Vec<(Pubkey, &AccountInfo)> = Vec::new();
-    for (i, &node_key) in shard
-        .node_keys
-        .iter()
-        .filter(|&&k| k != Pubkey::default())
-        .enumerate()
-    {
-        if !unique_nodes.iter().any(|(key, _)| key == &node_key) {
-            // Get node account from remaining_accounts (indices 3-5 for nodes)
-            let node_index = i + 3; // Nodes start after replacements
-            if node_index >= ctx.remaining_accounts.len() {
-                return Err(SoladError::InvalidNodeAccount.into());
+    let now = Clock::get()?.unix_timestamp;
+    let term_secs = upload
+        .expiry_time
+        .checked_sub(upload.term_start)
+        .ok_or(SoladError::MathOverflow)?
+        .max(1);
+    let served_secs = now.saturating_sub(upload.term_start).clamp(0, term_secs);
+
+    let escrow_key = ctx.accounts.escrow.key();
+    let escrow_info = ctx.accounts.escrow.to_account_info();
+    let escrow_seeds: &[&[u8]] = &[
+        ESCROW_SEED,
+        data_hash.as_bytes(),
+        upload.payer.as_ref(),
+        &[ctx.bumps.escrow],
+    ];
+    let token_accounts = if paid_in_token {
+        let token_program = token::token_program(&ctx.accounts.token_program)?;
+        let (escrow_token, _) =
+            token::checked_token_account(&ctx.accounts.escrow_token, &payment_mint, &escrow_key)?;
+        Some((token_program, escrow_token))
+    } else {
+        None
+    };
+
+    // Settle what each node has accrued and release its upload slot and committed capacity
+    let escrow_balance = ctx.accounts.escrow.lamports;
+    let mut remaining = escrow_balance;
+    let mut nodes: Vec<Account<Node>> = Vec::new();
+    let mut node_amounts: Vec<u64> = Vec::new();
+    let mut groups = node_infos.chunks(group_len);
+    let mut shards = upload.shards.clone();
+    for shard in shards.iter_mut() {
+        let node_keys = shard.node_keys.clone();
+        for node_key in node_keys.iter().filter(|&&k| k != Pubkey::default()) {
+            // Each node earns only for the epochs it proved, as in `claim_rewards`
+            let proven_epochs = shard.proven_epochs_of(node_key).min(config.epochs_total);
+            let earned = if proven_epochs > 0 && shard.verified_count != u8::MAX {
+                let node_share = upload.node_share(shard)?;
+                let initial = node_share.checked_mul(25).ok_or(SoladError::MathOverflow)? / 100;
+                let accrued =
+                    (node_share - initial) as u128 * served_secs as u128 / term_secs as u128;
+                let epoch_lamports = node_share.checked_mul(75).ok_or(SoladError::MathOverflow)?
+                    / 100
+                    / config.epochs_total;
+                let proven_lamports = epoch_lamports
+                    .checked_mul(proven_epochs)
+                    .ok_or(SoladError::MathOverflow)?;
+                initial + (accrued as u64).min(proven_lamports)
+            } else {
+                0
+            };
+
+            let group = groups.next().ok_or(SoladError::InvalidNodeAccount)?;
+            require_keys_eq!(group[0].key(), *node_key, SoladError::InvalidNodeAccount);
+            let paid = claimed_amount(
+                &group[1],
+                &upload.key(),
+                shard.shard_id,
+                node_key,
+                ctx.program_id,
+            )?;
+
+            let i = match nodes.iter().position(|node| node.key() == *node_key) {
+                Some(i) => i,
+                None => {
+                    let node_account: Account<Node> = Account::try_from(&group[0])?;
+                    nodes.push(node_account);
+                    node_amounts.push(0);
+                    nodes.len() - 1
+                }
+            };
+            let node_account = &mut nodes[i];
+            // Nodes that finished claiming or proving the shard already released it
+            if shard.mark_upload_released(node_key) {
+                node_account.upload_count = node_account
+                    .upload_count
+                    .checked_sub(1)
+                    .ok_or(SoladError::MathOverflow)?;
+            }
+            if shard.mark_capacity_released(node_key) {
+                node_account.release_capacity(shard.size_mb);
             }
-            let node_info = &ctx.remaining_accounts[node_index];
-            if node_info.key() != node_key {
-                return Err(SoladError::InvalidNodeAccount.into());
+
+            let amount = earned.saturating_sub(paid).min(remaining);
+            if amount == 0 {
+                continue;
             }
-            // Deserialize to check upload_count
-            let node_account: Account<Node> = Account::try_from(node_info)?;
-            if node_account.upload_count > 0 {
-                unique_nodes.push((node_key, node_info));
+            remaining -= amount;
+            node_amounts[i] += amount;
+            match token_accounts {
+                Some((token_program, escrow_token)) => {
+                    // Token rewards go straight to the operator's token account
+                    let owner_token = &group[2];
+                    token::token_balance(owner_token, &payment_mint, &node_account.owner)?;
+                    token::transfer(
+                        token_program,
+                        escrow_token,
+                        owner_token,
+                        &escrow_info,
+                        amount,
+                        &[escrow_seeds],
+                    )?;
+                }
+                None => {
+                    // The escrow is program-owned, so lamports are moved directly
+                    **escrow_info.try_borrow_mut_lamports()? -= amount;
+                    **group[0].try_borrow_mut_lamports()? += amount;
+                    node_account.apply_pending_commission(Clock::get()?.slot);
+                    node_account.distribute_reward(amount)?;
+                }
             }
         }
     }
-
-    // Decrement upload_count for nodes with upload_count > 0
-    for (_, node_info) in unique_nodes.iter() {
-        let mut node_account: Account<Node> = Account::try_from(*node_info)?; // Deserialize here
-
-        require!(node_account.upload_count > 0, SoladError::InvalidState);
-
-        node_account.upload_count = node_account
-            .upload_count
-            .checked_sub(1)
-            .ok_or(SoladError::MathOverflow)?;
+    for node_account in nodes.iter() {
+        node_account.exit(ctx.program_id)?;
     }
 
-    // Close escrow and refund lamports if this is the last shard
-    if upload.shards.iter().all(|s| {
-        s.verified_count
-            >= s.node_keys
-                .iter()
-                .filter(|&&k| k != Pubkey::default())
-                .count() as u8
-    }) {
-        let escrow = &mut ctx.accounts.escrow;
-        let lamports = escrow.to_account_info().lamports();
-        **escrow.to_account_info().lamports.borrow_mut() = 0;
-        **payer.to_account_info().lamports.borrow_mut() += lamports;
+    // The escrow PDA is closed with the upload, which returns the lamport remainder and rent
+    // to the owner; a sponsor's remainder is moved out first. A token escrow is emptied to
+    // the refund recipient and closed here; nothing could move its balance afterwards
+    if let Some((token_program, escrow_token)) = token_accounts {
+        let balance = token::token_balance(escrow_token, &payment_mint, &escrow_key)?;
+        let (refund_token, _) = token::checked_token_account(
+            &ctx.accounts.refund_token,
+            &payment_mint,
+            &refund_recipient,
+        )?;
+        token::transfer(
+            token_program,
+            escrow_token,
+            refund_token,
+            &escrow_info,
+            balance,
+            &[escrow_seeds],
+        )?;
+        token::close_account(
+            token_program,
+            escrow_token,
+            &owner.to_account_info(),
+            &escrow_info,
+            &[escrow_seeds],
+        )?;
+    } else if refund_recipient != owner.key() && remaining > 0 {
+        let sponsor = ctx
+            .accounts
+            .sponsor
+            .as_ref()
+            .ok_or(SoladError::InvalidSponsor)?;
+        require_keys_eq!(sponsor.key(), refund_recipient, SoladError::InvalidSponsor);
+        **escrow_info.try_borrow_mut_lamports()? -= remaining;
+        **sponsor.try_borrow_mut_lamports()? += remaining;
     }
 
-    // Upload account closed by Anchor if all shards are processed
+    emit!(UploadClosedEvent {
+        upload_pda: upload.key(),
+        data_hash,
+        payer: upload.payer,
+        owner: owner.key(),
+        payment_mint,
+        escrow_balance,
+        settled_amount: escrow_balance - remaining,
+        refund_amount: remaining,
+        refund_recipient,
+        served_secs,
+        term_secs,
+        nodes: nodes.iter().map(|node| node.key()).collect(),
+        node_amounts,
+        timestamp: now,
+    });
+
     Ok(())
 }
 
+// Amount `claim_rewards` has already paid `node` for the shard. A node that never claimed
+// has no ledger yet, so the account must then be the empty `ShardClaim` PDA.
+fn claimed_amount<'info>(
+    info: &'info AccountInfo<'info>,
+    upload: &Pubkey,
+    shard_id: u8,
+    node: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<u64> {
+    if info.data_is_empty() {
+        let (claim_pda, _) = Pubkey::find_program_address(
+            &[
+                SHARD_CLAIM_SEED,
+                upload.as_ref(),
+                &[shard_id],
+                node.as_ref(),
+            ],
+            program_id,
+        );
+        require_keys_eq!(info.key(), claim_pda, SoladError::InvalidShardClaim);
+        return Ok(0);
+    }
+    let claim: Account<ShardClaim> = Account::try_from(info)?;
+    require!(
+        claim.upload == *upload && claim.shard_id == shard_id && claim.node == *node,
+        SoladError::InvalidShardClaim
+    );
+    Ok(claim.lamports_paid)
+}
+
 #[derive(Accounts)]
-#[instruction(data_hash: String, shard_id: u8)]
+#[instruction(data_hash: String)]
 pub struct CloseUpload<'info> {
     #[account(
         mut,
-        close = payer,
-        seeds = [b"upload", data_hash.as_bytes(), payer.key().as_ref()],
+        close = owner,
+        seeds = [b"upload", data_hash.as_bytes(), upload.payer.as_ref()],
         bump
     )]
     pub upload: Account<'info, Upload>,
     #[account(
         mut,
-        close = payer,
-        seeds = [b"escrow", data_hash.as_bytes(), payer.key().as_ref()],
+        close = owner,
+        seeds = [b"escrow", data_hash.as_bytes(), upload.payer.as_ref()],
         bump
     )]
     pub escrow: Account<'info, Escrow>,
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    /// CHECK: Upload owner, receives the refund and rent
+    #[account(mut, address = upload.owner)]
+    pub owner: AccountInfo<'info>,
+    /// The owner or its delegate
+    pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
     /// CHECK: Program itself for CPI
     pub program: AccountInfo<'info>,
+    // Token accounts, required when the upload was paid in an SPL token
+    /// CHECK: Token account owned by the escrow PDA that holds the node share
+    #[account(mut)]
+    pub escrow_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Refund recipient's token account for the payment mint
+    #[account(mut)]
+    pub refund_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL Token program, validated by address
+    #[account(address = TOKEN_PROGRAM_ID)]
+    pub token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: Upload sponsor, receives the refund of a sponsored lamport upload
+    #[account(mut)]
+    pub sponsor: Option<UncheckedAccount<'info>>,
 }
@@ -0,0 +1,108 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use std::mem::size_of;
+
+use crate::{
+    errors::SoladError,
+    events::DelegatedEvent,
+    states::{
+        Delegation, Escrow, Node, StorageConfig, DELEGATION_SEED, NODE_SEED, STAKE_ESCROW_SEED,
+        STORAGE_CONFIG_SEED,
+    },
+};
+
+// Delegates lamports to a storage node's pool.
+// The lamports join the node's stake escrow and are converted into pool shares at the
+// pool's current value, so earlier slashes are reflected in the price. Delegated stake adds
+// to the node's selection weight and earns a pro-rata part of its rewards, less the
+// operator's commission. It is slashed by the same percentage as the operator's stake.
+// # Arguments
+// * `ctx` - Context containing node, delegation, stake escrow, delegator, config, and system program accounts.
+// * `amount` - Lamports to delegate (must be > 0).
+// # Errors
+// Returns `SoladError` variants if the program is not initialized, the node is inactive,
+// or the amount is zero.
+pub fn process_delegate(ctx: Context<Delegate>, amount: u64) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require!(amount > 0, SoladError::InvalidStake);
+
+    let node = &mut ctx.accounts.node;
+    require!(node.is_active, SoladError::NodeInactive);
+
+    let delegation = &mut ctx.accounts.delegation;
+    if delegation.delegator == Pubkey::default() {
+        delegation.node = node.key();
+        delegation.delegator = ctx.accounts.delegator.key();
+    }
+    delegation.settle(node.reward_per_share)?;
+
+    let shares = node.lamports_to_shares(amount)?;
+    require!(shares > 0, SoladError::InvalidStake);
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.delegator.to_account_info(),
+                to: ctx.accounts.stake_escrow.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    node.delegated_stake = node
+        .delegated_stake
+        .checked_add(amount)
+        .ok_or(SoladError::MathOverflow)?;
+    node.delegator_shares = node
+        .delegator_shares
+        .checked_add(shares)
+        .ok_or(SoladError::MathOverflow)?;
+    delegation.shares = delegation
+        .shares
+        .checked_add(shares)
+        .ok_or(SoladError::MathOverflow)?;
+    delegation.reset_debt(node.reward_per_share)?;
+
+    emit!(DelegatedEvent {
+        node: node.key(),
+        delegator: delegation.delegator,
+        amount,
+        shares,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Delegate<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, node.owner.as_ref()],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+    #[account(
+        init_if_needed,
+        payer = delegator,
+        space = 8 + size_of::<Delegation>(),
+        seeds = [DELEGATION_SEED, node.key().as_ref(), delegator.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(
+        mut,
+        seeds = [STAKE_ESCROW_SEED, node.owner.as_ref()],
+        bump
+    )]
+    pub stake_escrow: Account<'info, Escrow>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    pub system_program: Program<'info, System>,
+}
@@ -3,19 +3,24 @@ use anchor_lang::prelude::*;
 use crate::{
     errors::SoladError,
     events::NodeDeregisteredEvent,
-    states::{Escrow, Node, NodeRegistry, StorageConfig, NODE_SEED, STAKE_ESCROW_SEED},
+    states::{
+        Escrow, Node, NodeMetadata, NodeRegistry, RegistryPage, StorageConfig, NODE_METADATA_SEED,
+        NODE_REGISTRY_SEED, NODE_SEED, REGISTRY_PAGE_SEED, STAKE_ESCROW_SEED,
+    },
 };
 
-// Deregisters a storage node from the Solad network, closing its accounts and returning the staked lamports.
+// Deregisters a storage node from the Solad network, closing its accounts.
 // This function ensures the program is initialized, the caller is the node owner, and the node has no active
-// uploads to prevent data loss. It removes the node from the node registry, transfers the stake from the escrow
-// account back to the owner, and closes the node and escrow accounts. Upon success, it emits a
-// `NodeDeregisteredEvent` for transparency.
+// uploads to prevent data loss. Stake must already have left through `request_unstake` and
+// `withdraw_unstaked`, and every delegator must have undelegated, so deregistering can never
+// skip the unbonding period. It removes the node from its
+// registry page and closes the node, metadata and escrow accounts, returning their rent to the owner. Upon success,
+// it emits a `NodeDeregisteredEvent` for transparency.
 // # Arguments
 // * `ctx` - Context containing node, stake escrow, node registry, owner, config, and system program accounts.
 // # Errors
 // Returns `SoladError` variants if the program is not initialized, the caller is not the node owner,
-// or the node has active uploads.
+// the node has active uploads, or own or delegated stake is still bonded or unbonding.
 pub fn process_deregister_node(ctx: Context<DeregisterNode>) -> Result<()> {
     let config = &ctx.accounts.config;
     require!(config.is_initialized, SoladError::NotInitialized);
@@ -26,9 +31,13 @@ pub fn process_deregister_node(ctx: Context<DeregisterNode>) -> Result<()> {
         SoladError::Unauthorized
     );
     require!(node.upload_count == 0, SoladError::NodeHasActiveUploads);
+    require!(
+        node.stake_amount == 0 && node.unbonding_amount == 0 && node.delegator_shares == 0,
+        SoladError::NodeHasStake
+    );
 
     let node_registry = &mut ctx.accounts.node_registry;
-    node_registry.nodes.retain(|key| *key != node.key());
+    node_registry.remove_node(&mut ctx.accounts.registry_page, &node.key())?;
 
     let stake_amount = node.stake_amount;
 
@@ -56,8 +65,21 @@ pub struct DeregisterNode<'info> {
         close = owner
     )]
     pub stake_escrow: Account<'info, Escrow>,
-    #[account(mut, seeds = [b"node_registry"], bump)]
+    #[account(
+        mut,
+        seeds = [NODE_METADATA_SEED, node.key().as_ref()],
+        bump,
+        close = owner
+    )]
+    pub node_metadata: Account<'info, NodeMetadata>,
+    #[account(mut, seeds = [NODE_REGISTRY_SEED], bump)]
     pub node_registry: Account<'info, NodeRegistry>,
+    #[account(
+        mut,
+        seeds = [REGISTRY_PAGE_SEED, &node.registry_page.to_le_bytes()],
+        bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
     #[account(mut)]
     pub owner: Signer<'info>,
     #[account(mut)]
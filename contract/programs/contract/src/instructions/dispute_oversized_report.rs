@@ -0,0 +1,157 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::OversizedReportDisputedEvent,
+    states::{Node, StorageConfig, Upload, STAKE_ESCROW_SEED, STORAGE_CONFIG_SEED, UPLOAD_SEED},
+};
+
+// Disputes the oversized reports that invalidated a shard. Within `dispute_window_epochs` of
+// the shard being invalidated, the uploader or anyone else can check its reports against the
+// shard's stored `DataCommitment`. The commitment holds at most `leaf_count * chunk_size`
+// bytes, and PoS only ever checks those chunks, so every report claiming a larger
+// `actual_size_mb` is false.
+// False reporters lose `slash_penalty_percent` of their stake, split 90/10 between the
+// treasury and the disputer as in `slash_timeout`, and their reports are dropped. If the
+// reports left fall below the threshold the shard is valid again: `slash_user` no longer
+// applies and its nodes resume earning once they prove it again.
+// Remaining accounts: each false reporter's node account followed by its stake escrow, in
+// report order.
+/// Disputes a shard's oversized reports against its data commitment.
+/// # Arguments
+/// * `ctx` - Context containing upload, config, disputer, and treasury accounts.
+/// * `data_hash` - Hash of the uploaded data.
+/// * `shard_id` - ID of the invalidated shard.
+/// # Errors
+/// Returns errors if the shard is not invalid, the dispute window has closed, no report
+/// claims more than the commitment covers, reporter accounts are missing, or on mathematical
+/// overflows.
+pub fn process_dispute_oversized_report<'info>(
+    ctx: Context<'_, '_, 'info, 'info, DisputeOversizedReport<'info>>,
+    data_hash: String,
+    shard_id: u8,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let upload = &mut ctx.accounts.upload;
+    require!(upload.data_hash == data_hash, SoladError::InvalidHash);
+    require!(shard_id < upload.shard_count, SoladError::InvalidShardId);
+
+    let shard = &mut upload.shards[shard_id as usize];
+    require!(shard.verified_count == u8::MAX, SoladError::ShardNotInvalid);
+    let dispute_window_end = shard
+        .invalidated_epoch
+        .checked_add(config.dispute_window_epochs)
+        .ok_or(SoladError::MathOverflow)?;
+    require!(
+        Clock::get()?.slot / config.slots_per_epoch < dispute_window_end,
+        SoladError::DisputeWindowClosed
+    );
+
+    // Reports claiming more than the commitment covers are false
+    let committed_size_mb = shard.commitment.committed_mb();
+    let (false_reports, upheld_reports): (Vec<_>, Vec<_>) = shard
+        .oversized_reports
+        .iter()
+        .cloned()
+        .partition(|report| report.actual_size_mb > committed_size_mb);
+    require!(!false_reports.is_empty(), SoladError::NoFalseReports);
+    require!(
+        ctx.remaining_accounts.len() == false_reports.len() * 2,
+        SoladError::InsufficientAccounts
+    );
+
+    // Slash each false reporter; its stake escrow is program-owned, so lamports move directly
+    let mut slash_amount: u64 = 0;
+    let mut slashed_reporters = Vec::with_capacity(false_reports.len());
+    for (report, accounts) in false_reports
+        .iter()
+        .zip(ctx.remaining_accounts.chunks_exact(2))
+    {
+        let (node_info, stake_escrow_info) = (&accounts[0], &accounts[1]);
+        require_keys_eq!(node_info.key(), report.node, SoladError::InvalidNodeAccount);
+        require!(
+            node_info.is_writable && stake_escrow_info.is_writable,
+            SoladError::AccountNotWritable
+        );
+        let mut node: Account<Node> = Account::try_from(node_info)?;
+        let (stake_escrow_pda, _) =
+            Pubkey::find_program_address(&[STAKE_ESCROW_SEED, node.owner.as_ref()], ctx.program_id);
+        require_keys_eq!(
+            stake_escrow_info.key(),
+            stake_escrow_pda,
+            SoladError::InvalidStakeEscrow
+        );
+
+        let node_slash = node.slash(config.slash_penalty_percent)?;
+        node.exit(ctx.program_id)?;
+        let escrow_lamports = stake_escrow_info
+            .lamports()
+            .checked_sub(node_slash)
+            .ok_or(SoladError::InsufficientFunds)?;
+        **stake_escrow_info.try_borrow_mut_lamports()? = escrow_lamports;
+
+        slash_amount = slash_amount
+            .checked_add(node_slash)
+            .ok_or(SoladError::MathOverflow)?;
+        slashed_reporters.push(report.node);
+    }
+
+    let treasury_amount = slash_amount
+        .checked_mul(90)
+        .ok_or(SoladError::MathOverflow)?
+        / 100;
+    let disputer_amount = slash_amount
+        .checked_sub(treasury_amount)
+        .ok_or(SoladError::MathOverflow)?;
+    **ctx.accounts.treasury.try_borrow_mut_lamports()? += treasury_amount;
+    **ctx
+        .accounts
+        .disputer
+        .to_account_info()
+        .try_borrow_mut_lamports()? += disputer_amount;
+
+    // Without enough reports left the shard is valid again and must be re-proven
+    shard.oversized_reports = upheld_reports;
+    let shard_restored = (shard.oversized_reports.len() as u64)
+        < shard.required_reports(config.oversized_report_threshold);
+    if shard_restored {
+        shard.verified_count = 0;
+    }
+
+    emit!(OversizedReportDisputedEvent {
+        data_hash,
+        shard_id,
+        disputer: ctx.accounts.disputer.key(),
+        committed_size_mb,
+        slashed_reporters,
+        slash_amount,
+        treasury_amount,
+        disputer_amount,
+        shard_restored,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(data_hash: String)]
+pub struct DisputeOversizedReport<'info> {
+    #[account(
+        mut,
+        seeds = [UPLOAD_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
+        bump
+    )]
+    pub upload: Box<Account<'info, Upload>>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    #[account(mut)]
+    pub disputer: Signer<'info>,
+    /// CHECK: Receives the treasury share, validated against the config
+    #[account(mut, address = config.treasury)]
+    pub treasury: AccountInfo<'info>,
+}
@@ -0,0 +1,220 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::{
+    errors::SoladError,
+    events::UploadExtendedEvent,
+    states::{
+        Escrow, MintPricing, StorageConfig, Upload, ESCROW_SEED, MAX_STORAGE_DURATION_DAYS,
+        MINT_PRICING_SEED, UPLOAD_SEED,
+    },
+    token::{self, TOKEN_PROGRAM_ID},
+    utils::{calculate_storage_fee, lamports_per_gb},
+};
+
+// Extends or renews the storage duration of an existing upload.
+// The upload's owner or its delegate may extend it. The signer is charged for the additional
+// days using the same per-GB-day formula as `upload_data`, priced on the upload's stored
+// footprint. The treasury share is paid to the treasury and the node share is added to the
+// upload's existing escrow so nodes keep earning through the longer term. If the upload has
+// already expired the new term starts from the current time, otherwise `expiry_time` is
+// pushed forward.
+// Uploads paid in an SPL token are extended in the same mint at its current price; lamport
+// uploads are priced like `upload_data`, from the USD price feed when USD pricing is on.
+/// Extends an upload.
+/// # Arguments
+/// * `ctx` - Context containing upload, escrow, config, payer, treasury, and system program accounts.
+/// * `data_hash` - Hash of the uploaded data.
+/// * `additional_days` - Number of days to add to the storage duration.
+/// # Errors
+/// Returns errors for an invalid duration, an unassigned upload, a payer that is neither
+/// owner nor delegate, or mathematical overflows.
+pub fn process_extend_upload(
+    ctx: Context<ExtendUpload>,
+    data_hash: String,
+    additional_days: u64,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let upload = &mut ctx.accounts.upload;
+    require!(upload.data_hash == data_hash, SoladError::InvalidHash);
+    require!(upload.nodes_assigned, SoladError::UploadNotAssigned);
+    require!(
+        upload.can_manage(&ctx.accounts.payer.key()),
+        SoladError::Unauthorized
+    );
+
+    let storage_duration_days = upload
+        .storage_duration_days
+        .checked_add(additional_days)
+        .ok_or(SoladError::MathOverflow)?;
+    require!(
+        additional_days >= 1 && storage_duration_days <= MAX_STORAGE_DURATION_DAYS,
+        SoladError::InvalidStorageDuration
+    );
+
+    // Extensions are paid in the mint the upload was paid in
+    let price_per_gb = if upload.payment_mint == Pubkey::default() {
+        lamports_per_gb(
+            config,
+            ctx.accounts.price_feed.as_deref(),
+            Clock::get()?.unix_timestamp,
+        )?
+    } else {
+        let pricing = ctx
+            .accounts
+            .mint_pricing
+            .as_ref()
+            .ok_or(SoladError::MissingTokenAccount)?;
+        require_keys_eq!(
+            pricing.mint,
+            upload.payment_mint,
+            SoladError::MintNotAccepted
+        );
+        require!(pricing.is_enabled, SoladError::MintNotAccepted);
+        pricing.price_at(Clock::get()?.slot / config.slots_per_epoch)
+    };
+    let (treasury_lamports, node_lamports) = calculate_storage_fee(
+        config,
+        price_per_gb,
+        upload.stored_bytes()?,
+        additional_days,
+    )?;
+
+    if upload.payment_mint == Pubkey::default() {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            treasury_lamports,
+        )?;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.escrow.to_account_info(),
+                },
+            ),
+            node_lamports,
+        )?;
+    } else {
+        let token_program = token::token_program(&ctx.accounts.token_program)?;
+        let (payer_token, _) = token::checked_token_account(
+            &ctx.accounts.payer_token,
+            &upload.payment_mint,
+            &ctx.accounts.payer.key(),
+        )?;
+        let (treasury_token, _) = token::checked_token_account(
+            &ctx.accounts.treasury_token,
+            &upload.payment_mint,
+            &config.treasury,
+        )?;
+        let (escrow_token, _) = token::checked_token_account(
+            &ctx.accounts.escrow_token,
+            &upload.payment_mint,
+            &ctx.accounts.escrow.key(),
+        )?;
+        let payer_info = ctx.accounts.payer.to_account_info();
+        token::transfer(
+            token_program,
+            payer_token,
+            treasury_token,
+            &payer_info,
+            treasury_lamports,
+            &[],
+        )?;
+        token::transfer(
+            token_program,
+            payer_token,
+            escrow_token,
+            &payer_info,
+            node_lamports,
+            &[],
+        )?;
+    }
+
+    let escrow = &mut ctx.accounts.escrow;
+    escrow.lamports = escrow
+        .lamports
+        .checked_add(node_lamports)
+        .ok_or(SoladError::MathOverflow)?;
+
+    // A renewal after expiry leaves the lapse unpaid, so the term start moves past it
+    let now = Clock::get()?.unix_timestamp;
+    let lapsed_secs = now.saturating_sub(upload.expiry_time).max(0);
+    upload.term_start = upload
+        .term_start
+        .checked_add(lapsed_secs)
+        .ok_or(SoladError::MathOverflow)?;
+    let term_start = upload.expiry_time.max(now);
+    upload.expiry_time = term_start
+        .checked_add((additional_days as i64) * 86400)
+        .ok_or(SoladError::MathOverflow)?;
+    upload.storage_duration_days = storage_duration_days;
+    upload.node_lamports = upload
+        .node_lamports
+        .checked_add(node_lamports)
+        .ok_or(SoladError::MathOverflow)?;
+
+    emit!(UploadExtendedEvent {
+        upload_pda: upload.key(),
+        data_hash,
+        payer: ctx.accounts.payer.key(),
+        additional_days,
+        storage_duration_days,
+        expiry_time: upload.expiry_time,
+        treasury_lamports,
+        node_lamports,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(data_hash: String)]
+pub struct ExtendUpload<'info> {
+    #[account(
+        mut,
+        seeds = [UPLOAD_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
+        bump
+    )]
+    pub upload: Account<'info, Upload>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    pub config: Account<'info, StorageConfig>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Safe
+    #[account(mut, address = config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    // Token payment accounts, required when the upload was paid in an SPL token
+    #[account(seeds = [MINT_PRICING_SEED, mint_pricing.mint.as_ref()], bump)]
+    pub mint_pricing: Option<Account<'info, MintPricing>>,
+    /// CHECK: Payer's token account for the payment mint
+    #[account(mut)]
+    pub payer_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Treasury's token account for the payment mint
+    #[account(mut)]
+    pub treasury_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Token account owned by the escrow PDA that holds the node share
+    #[account(mut)]
+    pub escrow_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL Token program, validated by address
+    #[account(address = TOKEN_PROGRAM_ID)]
+    pub token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: SOL/USD price feed, required while USD pricing is on; checked against
+    /// `config.price_feed` and parsed as a `PriceFeed`
+    pub price_feed: Option<UncheckedAccount<'info>>,
+}
@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use std::mem::size_of;
+
+use crate::{
+    errors::SoladError,
+    events::ChallengeIssuedEvent,
+    selection::challenge_seed_expired,
+    states::{
+        Challenge, StorageConfig, Upload, CHALLENGE_LEAF_COUNT, CHALLENGE_SEED,
+        CHALLENGE_SEED_DELAY, MAX_REPLICATION_FACTOR, STORAGE_CONFIG_SEED, UPLOAD_SEED,
+    },
+};
+
+// Issues a Proof of Storage challenge for one shard in the current epoch.
+// Anyone may crank this; the caller pays rent for the `Challenge` account. The leaves are
+// not picked here: the challenge only fixes a seed slot `CHALLENGE_SEED_DELAY` slots ahead,
+// whose hash nobody knows when the crank lands. Once that slot has passed, anyone can
+// `reveal_challenge` to draw the leaves in `0..leaf_count` of the shard's commitment (the
+// upload's chunks, or the fragment's for an erasure-coded shard), and nodes must then
+// answer through `submit_pos` before the epoch ends. The seed slot must leave at least
+// `challenge_response_slots` before the deadline, so a challenge is never issued too late
+// in the epoch for nodes to answer. If the seed slot ages out of the SlotHashes sysvar
+// unrevealed, issuing again re-arms the challenge with a fresh seed slot, under the same
+// rule.
+// The shard's nodes at issue time are recorded, so `slash_inactive` can tell a missed
+// challenge from one issued before a node joined.
+/// Issues a PoS challenge.
+/// # Arguments
+/// * `ctx` - Context containing upload, challenge, config, caller, and SlotHashes accounts.
+/// * `data_hash` - Hash of the uploaded data.
+/// * `uploader` - Payer of the upload.
+/// * `shard_id` - Shard being challenged.
+/// * `epoch` - Current epoch; part of the challenge PDA seeds.
+/// # Errors
+/// Returns errors for an expired or unassigned upload, an invalid shard, a stale epoch, too
+/// little of the epoch left to respond, or a challenge already issued whose seed slot can
+/// still be revealed.
+pub fn process_issue_challenge(
+    ctx: Context<IssueChallenge>,
+    data_hash: String,
+    uploader: Pubkey,
+    shard_id: u8,
+    epoch: u64,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let upload = &ctx.accounts.upload;
+    require!(upload.data_hash == data_hash, SoladError::InvalidHash);
+    require!(upload.payer == uploader, SoladError::InvalidUploader);
+    require!(upload.nodes_assigned, SoladError::UploadNotAssigned);
+    require!(shard_id < upload.shard_count, SoladError::InvalidShardId);
+
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp < upload.expiry_time,
+        SoladError::UploadExpired
+    );
+    let current_epoch = clock.slot / config.slots_per_epoch;
+    require_eq!(epoch, current_epoch, SoladError::InvalidChallengeEpoch);
+
+    let seed_slot = clock
+        .slot
+        .checked_add(CHALLENGE_SEED_DELAY)
+        .ok_or(SoladError::MathOverflow)?;
+    let deadline_slot = current_epoch
+        .checked_add(1)
+        .and_then(|next| next.checked_mul(config.slots_per_epoch))
+        .ok_or(SoladError::MathOverflow)?;
+    // The nodes need a full response window after the earliest possible reveal
+    require!(
+        seed_slot
+            .checked_add(config.challenge_response_slots())
+            .ok_or(SoladError::MathOverflow)?
+            <= deadline_slot,
+        SoladError::ChallengeWindowTooShort
+    );
+    let upload_key = upload.key();
+    let challenge = &mut ctx.accounts.challenge;
+
+    // An issued challenge is only re-armed once its seed can no longer be revealed
+    if challenge.upload != Pubkey::default() {
+        require!(
+            !challenge.revealed
+                && challenge_seed_expired(&ctx.accounts.slot_hashes, challenge.seed_slot)?,
+            SoladError::ChallengeAlreadyIssued
+        );
+        challenge.seed_slot = seed_slot;
+    } else {
+        let shard = &upload.shards[shard_id as usize];
+        let mut assigned = [Pubkey::default(); MAX_REPLICATION_FACTOR as usize];
+        for (slot, key) in assigned.iter_mut().zip(shard.node_keys.iter()) {
+            *slot = *key;
+        }
+        challenge.upload = upload_key;
+        challenge.shard_id = shard_id;
+        challenge.epoch = epoch;
+        challenge.seed_slot = seed_slot;
+        challenge.revealed = false;
+        challenge.revealed_slot = 0;
+        challenge.leaf_indices = [0u32; CHALLENGE_LEAF_COUNT];
+        challenge.deadline_slot = deadline_slot;
+        challenge.issuer = ctx.accounts.caller.key();
+        challenge.assigned = assigned;
+        challenge.responders = [Pubkey::default(); MAX_REPLICATION_FACTOR as usize];
+    }
+
+    emit!(ChallengeIssuedEvent {
+        upload_pda: upload_key,
+        data_hash,
+        shard_id,
+        epoch,
+        seed_slot,
+        deadline_slot: challenge.deadline_slot,
+        issuer: challenge.issuer,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(data_hash: String, uploader: Pubkey, shard_id: u8, epoch: u64)]
+pub struct IssueChallenge<'info> {
+    #[account(
+        seeds = [UPLOAD_SEED, data_hash.as_bytes(), uploader.as_ref()],
+        bump
+    )]
+    pub upload: Box<Account<'info, Upload>>,
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = 8 + size_of::<Challenge>(),
+        seeds = [CHALLENGE_SEED, upload.key().as_ref(), &[shard_id], &epoch.to_le_bytes()],
+        bump
+    )]
+    pub challenge: Account<'info, Challenge>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    /// CHECK: SlotHashes sysvar, validated by address and read in `selection`
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -1,25 +1,71 @@
+pub mod accept_authority;
+pub mod add_stake;
+pub mod apply_config_change;
+pub mod assign_upload;
 pub mod batch_request_replacement;
+pub mod cancel_config_change;
+pub mod claim_delegator_rewards;
 pub mod claim_rewards;
+pub mod close_challenge;
 pub mod close_upload;
+pub mod delegate;
 pub mod deregister_node;
+pub mod dispute_oversized_report;
+pub mod extend_upload;
 pub mod initialize;
+pub mod issue_challenge;
+pub mod propose_authority;
 pub mod register_node;
 pub mod request_replacement;
+pub mod request_unstake;
+pub mod reveal_challenge;
+pub mod set_commission;
+pub mod set_mint_pricing;
+pub mod set_upload_delegate;
+pub mod slash_inactive;
 pub mod slash_timeout;
 pub mod slash_user;
 pub mod submit_pos;
+pub mod submit_pos_batch;
+pub mod transfer_upload;
+pub mod undelegate;
 pub mod update_config;
+pub mod update_node_metadata;
 pub mod upload_data;
+pub mod withdraw_unstaked;
 
+pub use accept_authority::*;
+pub use add_stake::*;
+pub use apply_config_change::*;
+pub use assign_upload::*;
 pub use batch_request_replacement::*;
+pub use cancel_config_change::*;
+pub use claim_delegator_rewards::*;
 pub use claim_rewards::*;
+pub use close_challenge::*;
 pub use close_upload::*;
+pub use delegate::*;
 pub use deregister_node::*;
+pub use dispute_oversized_report::*;
+pub use extend_upload::*;
 pub use initialize::*;
+pub use issue_challenge::*;
+pub use propose_authority::*;
 pub use register_node::*;
 pub use request_replacement::*;
+pub use request_unstake::*;
+pub use reveal_challenge::*;
+pub use set_commission::*;
+pub use set_mint_pricing::*;
+pub use set_upload_delegate::*;
+pub use slash_inactive::*;
 pub use slash_timeout::*;
 pub use slash_user::*;
 pub use submit_pos::*;
+pub use submit_pos_batch::*;
+pub use transfer_upload::*;
+pub use undelegate::*;
 pub use update_config::*;
+pub use update_node_metadata::*;
 pub use upload_data::*;
+pub use withdraw_unstaked::*;
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::AuthorityProposedEvent,
+    states::{StorageConfig, STORAGE_CONFIG_SEED},
+};
+
+// Proposes a new authority for the storage configuration.
+// This is the first step of a two-step handover: the current authority nominates a
+// successor, which only takes control once it signs `accept_authority`. This prevents
+// the config from being locked by a mistyped or unowned key. Proposing
+// `Pubkey::default()` cancels a pending handover.
+/// Proposes a config authority handover.
+/// # Arguments
+/// * `ctx` - Context containing the config and current authority accounts.
+/// * `new_authority` - Public key of the proposed authority.
+/// # Errors
+/// Returns errors if the signer is not the current authority or the proposed key is the current authority.
+pub fn process_propose_authority(
+    ctx: Context<ProposeAuthority>,
+    new_authority: Pubkey,
+) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require_keys_eq!(
+        config.authority,
+        ctx.accounts.authority.key(),
+        SoladError::Unauthorized
+    );
+    require_keys_neq!(
+        new_authority,
+        config.authority,
+        SoladError::InvalidPendingAuthority
+    );
+
+    config.pending_authority = new_authority;
+
+    emit!(AuthorityProposedEvent {
+        authority: config.authority,
+        pending_authority: new_authority,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    pub authority: Signer<'info>,
+}
@@ -3,46 +3,70 @@ use anchor_lang::system_program;
 
 use crate::{
     errors::SoladError,
-    events::NodeRegisteredEvent,
-    states::{Escrow, Node, NodeRegistry, StorageConfig, NODE_SEED, STAKE_ESCROW_SEED},
+    events::{NodeMetadataUpdatedEvent, NodeRegisteredEvent},
+    states::{
+        Escrow, Node, NodeMetadata, NodeMetadataParams, NodeRegistry, RegistryPage,
+        StorageConfig, NODE_METADATA_SEED, NODE_REGISTRY_SEED, NODE_SEED, REGISTRY_PAGE_SEED,
+        STAKE_ESCROW_SEED,
+    },
 };
 
 // Registers a new storage node in the Solad network.
 // This function allows a node operator to join the network by staking a minimum amount
 // of lamports, as defined in the storage configuration. The stake is transferred to an
-// escrow account, and the node is added to the node registry. The function initializes
+// escrow account, and the node is added to the registry's open page, which is created
+// here when the previous one has filled up. The function initializes
 // node metadata, including owner, stake amount, upload count, and activity status. It
 // ensures the program is initialized, the stake meets the minimum requirement, and the
 // node is not already registered. Upon success, it emits a `NodeRegisteredEvent` for
-// transparency.
+// transparency. The node's endpoints, region and capacity are stored in a `NodeMetadata`
+// account so clients and peers can discover it from chain.
 // # Arguments
 // * `ctx` - Context containing node, stake escrow, node registry, owner, config, and system program accounts.
 // * `stake_amount` - Amount of lamports to stake (must be ≥ config.min_node_stake).
+// * `metadata` - Endpoints, region, capacity and software version the node advertises.
 // # Errors
 // Returns `SoladError` variants for cases such as uninitialized program, insufficient stake,
-// or if the node is already registered.
-pub fn process_register_node(ctx: Context<RegisterNode>, stake_amount: u64) -> Result<()> {
+// invalid metadata, or if the node is already registered.
+pub fn process_register_node(
+    ctx: Context<RegisterNode>,
+    stake_amount: u64,
+    metadata: NodeMetadataParams,
+) -> Result<()> {
     let config = &ctx.accounts.config;
     require!(config.is_initialized, SoladError::NotInitialized);
     require!(
         stake_amount >= config.min_node_stake,
         SoladError::InvalidStake
     );
+    metadata.validate()?;
 
     let node = &mut ctx.accounts.node;
     node.owner = ctx.accounts.owner.key();
     node.stake_amount = stake_amount;
     node.upload_count = 0;
     node.last_pos_time = 0;
-    node.last_claimed_epoch = 0;
     node.is_active = true; // Set node as active
+    node.unbonding_amount = 0;
+    node.unbonding_release_epoch = 0;
+    node.delegated_stake = 0;
+    node.delegator_shares = 0;
+    node.unbonding_shares = 0;
+    node.commission_percent = 0;
+    node.pending_commission_percent = 0;
+    node.commission_effective_slot = 0;
+    node.reward_per_share = 0;
+    node.capacity_mb = metadata.capacity_mb;
+    node.committed_mb = 0;
 
+    // The node PDA is `init`, so a second registration fails before reaching this point
     let node_registry = &mut ctx.accounts.node_registry;
-    require!(
-        !node_registry.nodes.contains(&ctx.accounts.node.key()),
-        SoladError::NodeAlreadyRegistered
-    );
-    node_registry.nodes.push(ctx.accounts.node.key());
+    let registry_page = &mut ctx.accounts.registry_page;
+    if node_registry.open_page == node_registry.page_count {
+        registry_page.index = node_registry.open_page;
+    }
+    node.registry_page = registry_page.index;
+    node_registry.add_node(registry_page, node.key())?;
 
     system_program::transfer(
         CpiContext::new(
@@ -60,6 +84,20 @@ pub fn process_register_node(ctx: Context<RegisterNode>, stake_amount: u64) -> R
         stake_amount,
     });
 
+    let node_metadata = &mut ctx.accounts.node_metadata;
+    node_metadata.node = ctx.accounts.node.key();
+    node_metadata.owner = ctx.accounts.owner.key();
+    node_metadata.apply_params(&metadata, Clock::get()?.unix_timestamp);
+
+    emit!(NodeMetadataUpdatedEvent {
+        node: node_metadata.node,
+        http_url: metadata.http_url,
+        multiaddr: metadata.multiaddr,
+        region: metadata.region,
+        capacity_mb: metadata.capacity_mb,
+        version: metadata.version,
+    });
+
     Ok(())
 }
 
@@ -68,7 +106,7 @@ pub struct RegisterNode<'info> {
     #[account(
         init,
         payer = owner,
-        space = 8 + 32 + 8 + 8 + 8 + 8 + 1,
+        space = 8 + 32 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 16 + 8 + 8 + 4 + 1 + 8,
         seeds = [NODE_SEED, owner.key().as_ref()],
         bump
     )]
@@ -81,8 +119,24 @@ pub struct RegisterNode<'info> {
         bump
     )]
     pub stake_escrow: Account<'info, Escrow>,
-    #[account(mut, seeds = [b"node_registry"], bump)]
+    #[account(
+        init,
+        payer = owner,
+        space = NodeMetadata::SPACE,
+        seeds = [NODE_METADATA_SEED, node.key().as_ref()],
+        bump
+    )]
+    pub node_metadata: Account<'info, NodeMetadata>,
+    #[account(mut, seeds = [NODE_REGISTRY_SEED], bump)]
     pub node_registry: Account<'info, NodeRegistry>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = RegistryPage::SPACE,
+        seeds = [REGISTRY_PAGE_SEED, &node_registry.open_page.to_le_bytes()],
+        bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
     #[account(mut)]
     pub owner: Signer<'info>,
     #[account(mut)]
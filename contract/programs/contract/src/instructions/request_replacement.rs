@@ -1,28 +1,26 @@
 use anchor_lang::prelude::*;
-use anchor_lang::system_program;
-use sha2::{Digest, Sha256};
 
 use crate::{
     errors::SoladError,
     events::{NodeExitedEvent, ReplacementRequestedEvent},
+    selection::{registry_window, replacement_seed, select_weighted, SelectionRng},
     states::{
-        Escrow, Node, NodeRegistry, Replacement, StorageConfig, Upload, NODE_SEED,
-        REPLACEMENT_SEED, STAKE_ESCROW_SEED, UPLOAD_SEED,
+        Escrow, Node, NodeRegistry, RegistryPage, Replacement, ShardRole, StorageConfig, Upload,
+        MAX_REPLACEMENT_CANDIDATES, MAX_REPLACEMENT_WINDOWS, NODE_REGISTRY_SEED, NODE_SEED,
+        REGISTRY_PAGE_SEED, REPLACEMENT_SEED, STAKE_ESCROW_SEED, UPLOAD_SEED,
     },
 };
 
+// Hands the exiting node's slot on a shard to a successor, or vacates it when the node is the
+// shard's last holder. Remaining accounts hold the successor's candidate windows; see
+// `draw_successor`.
+
 pub fn process_request_replacement(
     ctx: Context<RequestReplacement>,
     data_hash: String,
     shard_id: u8,
     uploader: Pubkey,
 ) -> Result<()> {
-    let node = &mut ctx.accounts.node;
-    msg!("Node account key: {}", node.key());
-    msg!("Node account owner: {}", node.owner);
-    msg!("Node account is_active: {}", node.is_active);
-    msg!("Node account upload_count: {}", node.upload_count);
-
     let config = &ctx.accounts.config;
     require!(config.is_initialized, SoladError::NotInitialized);
     require!(
@@ -41,19 +39,10 @@ pub fn process_request_replacement(
     require!(shard_id < upload.shard_count, SoladError::InvalidShardId);
     require!(upload.payer == uploader, SoladError::InvalidUploader);
 
-    let is_last_shard = upload.shard_count == 1
-        && upload.shards[shard_id as usize]
-            .node_keys
-            .iter()
-            .filter(|&&k| k != Pubkey::default())
-            .count()
-            == 1;
-
-    let node_count = upload.shards[shard_id as usize]
-        .node_keys
-        .iter()
-        .filter(|&&k| k != Pubkey::default())
-        .count();
+    let node_count = upload.shards[shard_id as usize].node_count();
+    let is_last_shard = upload.shard_count == 1 && node_count == 1;
+    // A fragment is always handed to a successor, which must not hold another fragment
+    let is_fragment = upload.shards[shard_id as usize].role != ShardRole::Replica;
 
     let upload = &mut ctx.accounts.upload;
     let shard = &mut upload.shards[shard_id as usize];
@@ -63,12 +52,20 @@ pub fn process_request_replacement(
     );
 
     node.is_active = false;
-    node.upload_count = node
-        .upload_count
-        .checked_sub(1)
-        .ok_or(SoladError::MathOverflow)?;
+    ctx.accounts
+        .node_registry
+        .deactivate_node(&mut ctx.accounts.node_page, &node.key())?;
+    if shard.mark_upload_released(&node.key()) {
+        node.upload_count = node
+            .upload_count
+            .checked_sub(1)
+            .ok_or(SoladError::MathOverflow)?;
+    }
+    if shard.mark_capacity_released(&node.key()) {
+        node.release_capacity(shard.size_mb);
+    }
 
-    if node_count == 1 || is_last_shard {
+    if !is_fragment && (node_count == 1 || is_last_shard) {
         for key in shard.node_keys.iter_mut() {
             if *key == node.key() {
                 *key = Pubkey::default();
@@ -76,22 +73,12 @@ pub fn process_request_replacement(
             }
         }
 
-        let stake_escrow_seeds = &[
-            STAKE_ESCROW_SEED,
-            node.owner.as_ref(),
-            &[ctx.accounts.stake_escrow.bump],
-        ];
-        system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: ctx.accounts.stake_escrow.to_account_info(),
-                    to: ctx.accounts.owner.to_account_info(),
-                },
-                &[&stake_escrow_seeds[..]],
-            ),
-            node.stake_amount,
-        )?;
+        // The exiting stake unbonds rather than being paid out, so it stays slashable
+        let release_epoch = (Clock::get()?.slot / config.slots_per_epoch)
+            .checked_add(config.unbonding_epochs)
+            .ok_or(SoladError::MathOverflow)?;
+        let stake_amount = node.stake_amount;
+        node.begin_unbonding(stake_amount, release_epoch)?;
 
         emit!(NodeExitedEvent {
             node: node.key(),
@@ -106,52 +93,19 @@ pub fn process_request_replacement(
             .replacement
             .as_mut()
             .ok_or(SoladError::InvalidNodeAccount)?;
-        let node_registry = ctx.accounts.node_registry.clone();
-        let mut node_stakes = Vec::new();
-        let mut total_stake = 0u64;
-        for (i, node_key) in node_registry.nodes.iter().enumerate() {
-            let node_account = &ctx.remaining_accounts[i];
-            require!(
-                node_account.key() == *node_key,
-                SoladError::InvalidNodeAccount
-            );
-            let node_data = node_account.data.borrow();
-            let candidate: &Node = &Node::try_deserialize(&mut node_data.as_ref())
-                .map_err(|_| SoladError::InvalidNodeAccount)?;
-            if candidate.is_active
-                && node_key != &node.key()
-                && !shard.node_keys.contains(node_key)
-                && candidate.stake_amount >= config.min_node_stake
-            {
-                node_stakes.push((node_key, candidate.stake_amount));
-                total_stake = total_stake
-                    .checked_add(candidate.stake_amount)
-                    .ok_or(SoladError::MathOverflow)?;
-            }
-        }
-
-        require!(!node_stakes.is_empty(), SoladError::NoReplacementAvailable);
 
+        let exiting_key = node.key();
+        let replacement_info = draw_successor(
+            ctx.remaining_accounts,
+            upload,
+            shard_id,
+            &exiting_key,
+            config,
+            ctx.accounts.node_registry.page_count,
+            ctx.program_id,
+        )?;
+        let replacement_key = replacement_info.key();
         let current_slot = Clock::get()?.slot;
-        let seed = format!("{}:{}:{}", data_hash, shard_id, current_slot);
-        let mut rng_state =
-            u64::from_le_bytes(Sha256::digest(seed.as_bytes())[..8].try_into().unwrap());
-        rng_state ^= rng_state << 13;
-        rng_state ^= rng_state >> 7;
-        rng_state ^= rng_state << 17;
-        let target = rng_state % total_stake;
-        let mut cumulative = 0u64;
-        let mut replacement_key = *node_stakes[0].0;
-
-        for (key, stake) in node_stakes.iter() {
-            cumulative = cumulative
-                .checked_add(*stake)
-                .ok_or(SoladError::MathOverflow)?;
-            if target < cumulative {
-                replacement_key = **key;
-                break;
-            }
-        }
 
         replacement.exiting_node = node.key();
         replacement.replacement_node = replacement_key;
@@ -160,12 +114,21 @@ pub fn process_request_replacement(
         replacement.pos_submitted = false;
         replacement.request_epoch = current_slot / config.slots_per_epoch;
 
-        for key in shard.node_keys.iter_mut() {
-            if *key == node.key() {
-                *key = replacement_key;
-                break;
-            }
-        }
+        // The successor takes over the shard's upload slot and committed capacity
+        let shard = &mut upload.shards[shard_id as usize];
+        require!(replacement_info.is_writable, SoladError::AccountNotWritable);
+        let mut replacement_data = replacement_info.data.borrow_mut();
+        let mut replacement_node: Node = Node::try_deserialize(&mut replacement_data.as_ref())
+            .map_err(|_| SoladError::InvalidNodeAccount)?;
+        replacement_node.upload_count = replacement_node
+            .upload_count
+            .checked_add(1)
+            .ok_or(SoladError::MathOverflow)?;
+        replacement_node.commit_capacity(shard.size_mb)?;
+        let mut serialized = Vec::new();
+        replacement_node.try_serialize(&mut serialized)?;
+        replacement_data[..serialized.len()].copy_from_slice(&serialized);
+        shard.replace_node(&node.key(), replacement_key);
 
         emit!(ReplacementRequestedEvent {
             data_hash,
@@ -179,6 +142,90 @@ pub fn process_request_replacement(
     }
 }
 
+// Draws a successor for `exiting_node`'s slot on `shard_id` and returns its node account.
+// Candidates come from up to MAX_REPLACEMENT_WINDOWS registry windows, each fixed by
+// `replacement_seed` for its attempt number; `window_infos` holds each window's registry
+// page followed by its node accounts, in order. The first window with an eligible candidate
+// is drawn from, weighted by stake, so callers pass windows up to and including that one.
+// A window without one, say because its nodes are full or inactive, moves the search on
+// rather than blocking the exit. Shared by `request_replacement` and `slash_inactive`.
+pub(crate) fn draw_successor<'a, 'info>(
+    window_infos: &'a [AccountInfo<'info>],
+    upload: &Upload,
+    shard_id: u8,
+    exiting_node: &Pubkey,
+    config: &StorageConfig,
+    page_count: u32,
+    program_id: &Pubkey,
+) -> Result<&'a AccountInfo<'info>> {
+    let shard = &upload.shards[shard_id as usize];
+    let peers = upload.shard_peers(shard);
+    let mut remaining = window_infos;
+    for attempt in 0..MAX_REPLACEMENT_WINDOWS {
+        let seed = replacement_seed(
+            &upload.selection_seed,
+            &upload.data_hash,
+            shard_id,
+            exiting_node,
+            attempt,
+        );
+        let (page_index, start_seed) = registry_window(&seed, page_count);
+        let (page_info, rest) = remaining
+            .split_first()
+            .ok_or(SoladError::InsufficientAccounts)?;
+        let (page_pda, _) = Pubkey::find_program_address(
+            &[REGISTRY_PAGE_SEED, &page_index.to_le_bytes()],
+            program_id,
+        );
+        require_keys_eq!(
+            page_info.key(),
+            page_pda,
+            SoladError::InvalidCandidateWindow
+        );
+        let page = RegistryPage::try_deserialize(&mut &page_info.data.borrow()[..])?;
+        let window = page.window(start_seed, MAX_REPLACEMENT_CANDIDATES);
+        require!(
+            rest.len() >= window.len(),
+            SoladError::InvalidCandidateWindow
+        );
+        let (candidate_infos, next) = rest.split_at(window.len());
+        remaining = next;
+
+        let mut node_stakes = Vec::new();
+        for (entry, candidate_info) in window.iter().zip(candidate_infos) {
+            require_keys_eq!(
+                candidate_info.key(),
+                entry.node,
+                SoladError::InvalidCandidateWindow
+            );
+            if !entry.is_active || entry.node == *exiting_node || peers.contains(&entry.node) {
+                continue;
+            }
+            let candidate = Node::try_deserialize(&mut &candidate_info.data.borrow()[..])?;
+            if candidate.is_active
+                && candidate.stake_amount >= config.min_node_stake
+                && candidate.free_capacity_mb() >= shard.size_mb
+            {
+                node_stakes.push((entry.node, candidate.selection_weight()?));
+            }
+        }
+        if node_stakes.is_empty() {
+            continue;
+        }
+
+        let mut rng = SelectionRng::new(&seed, &[]);
+        let successor = select_weighted(&node_stakes, 1, &mut rng)?
+            .first()
+            .copied()
+            .ok_or(SoladError::NoReplacementAvailable)?;
+        return candidate_infos
+            .iter()
+            .find(|info| info.key() == successor)
+            .ok_or(error!(SoladError::InvalidNodeAccount));
+    }
+    err!(SoladError::NoReplacementAvailable)
+}
+
 #[derive(Accounts)]
 #[instruction(data_hash: String, shard_id: u8, uploader: Pubkey)]
 pub struct RequestReplacement<'info> {
@@ -210,10 +257,16 @@ pub struct RequestReplacement<'info> {
     pub stake_escrow: Account<'info, Escrow>,
     #[account(
         mut,
-        seeds = [b"node_registry"],
+        seeds = [NODE_REGISTRY_SEED],
         bump,
     )]
     pub node_registry: Account<'info, NodeRegistry>,
+    #[account(
+        mut,
+        seeds = [REGISTRY_PAGE_SEED, &node.registry_page.to_le_bytes()],
+        bump
+    )]
+    pub node_page: Account<'info, RegistryPage>,
     #[account(mut)]
     pub owner: Signer<'info>,
     #[account(mut)]
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::UnstakeRequestedEvent,
+    states::{
+        Node, NodeRegistry, RegistryPage, StorageConfig, NODE_REGISTRY_SEED, NODE_SEED,
+        REGISTRY_PAGE_SEED, STORAGE_CONFIG_SEED,
+    },
+};
+
+// Starts unbonding part of a node's stake.
+// The amount leaves `stake_amount` immediately, so it no longer counts as selection weight,
+// but stays in the stake escrow as `unbonding_amount` until `config.unbonding_epochs` have
+// passed. Slashing applies to unbonding lamports as well, so a node cannot dodge a pending
+// penalty by unstaking. A node with active uploads must keep at least `min_node_stake`
+// bonded; once it drops below that it is marked inactive, in its registry page as well, and
+// receives no new shards.
+// # Arguments
+// * `ctx` - Context containing node, node registry, registry page, owner, and config accounts.
+// * `amount` - Lamports of bonded stake to unbond (must be > 0).
+// # Errors
+// Returns `SoladError` variants if the program is not initialized, the caller is not the node owner,
+// the amount is zero or exceeds the bonded stake, or the remaining stake would fall below the
+// minimum while the node still stores data.
+pub fn process_request_unstake(ctx: Context<RequestUnstake>, amount: u64) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require!(amount > 0, SoladError::InvalidStake);
+
+    let node = &mut ctx.accounts.node;
+    require!(
+        node.owner == ctx.accounts.owner.key(),
+        SoladError::Unauthorized
+    );
+
+    let remaining_stake = node
+        .stake_amount
+        .checked_sub(amount)
+        .ok_or(SoladError::InvalidStake)?;
+    if remaining_stake < config.min_node_stake {
+        require!(node.upload_count == 0, SoladError::NodeHasActiveUploads);
+        node.is_active = false;
+        ctx.accounts
+            .node_registry
+            .deactivate_node(&mut ctx.accounts.registry_page, &node.key())?;
+    }
+
+    let release_epoch = (Clock::get()?.slot / config.slots_per_epoch)
+        .checked_add(config.unbonding_epochs)
+        .ok_or(SoladError::MathOverflow)?;
+    node.begin_unbonding(amount, release_epoch)?;
+
+    emit!(UnstakeRequestedEvent {
+        node: node.key(),
+        amount,
+        stake_amount: node.stake_amount,
+        unbonding_amount: node.unbonding_amount,
+        release_epoch,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+    #[account(mut, seeds = [NODE_REGISTRY_SEED], bump)]
+    pub node_registry: Account<'info, NodeRegistry>,
+    #[account(
+        mut,
+        seeds = [REGISTRY_PAGE_SEED, &node.registry_page.to_le_bytes()],
+        bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+}
@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+
+use crate::{
+    errors::SoladError,
+    events::ChallengeRevealedEvent,
+    selection::{challenge_seed, SelectionRng},
+    states::{Challenge, Upload},
+};
+
+// Draws a challenge's leaves from the hash of its seed slot. Anyone may crank this once the
+// seed slot has passed, usually the node about to answer, in the same transaction as its
+// `submit_pos`. The seed slot was still ahead when the challenge was issued, so neither the
+// issuer nor the storing nodes knew which chunks would be proven. The draw only depends on
+// the seed slot's hash, so it is the same whoever reveals it and whenever they do. It must be
+// revealed before the challenge's deadline, and the reveal slot is recorded, so
+// `slash_inactive` only counts challenges that left nodes a full response window.
+/// Reveals a PoS challenge's leaf indices.
+/// # Arguments
+/// * `ctx` - Context containing challenge, upload, and SlotHashes accounts.
+/// # Errors
+/// Returns errors if the challenge was already revealed, its deadline has passed, its seed
+/// slot has not passed or has aged out of SlotHashes, or the upload does not match.
+pub fn process_reveal_challenge(ctx: Context<RevealChallenge>) -> Result<()> {
+    let challenge = &mut ctx.accounts.challenge;
+    require!(!challenge.revealed, SoladError::ChallengeAlreadyRevealed);
+    let slot = Clock::get()?.slot;
+    require!(slot < challenge.deadline_slot, SoladError::ChallengeExpired);
+
+    let upload = &ctx.accounts.upload;
+    let shard = upload
+        .shards
+        .get(challenge.shard_id as usize)
+        .ok_or(SoladError::InvalidShardId)?;
+    let leaf_count = shard.commitment.leaf_count.max(1) as u64;
+
+    let entropy = challenge_seed(&ctx.accounts.slot_hashes, challenge.seed_slot)?;
+    let upload_key = upload.key();
+    let mut rng = SelectionRng::new(
+        &entropy,
+        &[
+            upload_key.as_ref(),
+            &[challenge.shard_id],
+            &challenge.epoch.to_le_bytes(),
+        ],
+    );
+    for index in challenge.leaf_indices.iter_mut() {
+        *index = (rng.next_u64() % leaf_count) as u32;
+    }
+    challenge.revealed = true;
+    challenge.revealed_slot = slot;
+
+    emit!(ChallengeRevealedEvent {
+        upload_pda: upload_key,
+        shard_id: challenge.shard_id,
+        epoch: challenge.epoch,
+        seed_slot: challenge.seed_slot,
+        leaf_indices: challenge.leaf_indices.to_vec(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RevealChallenge<'info> {
+    #[account(mut)]
+    pub challenge: Account<'info, Challenge>,
+    #[account(address = challenge.upload @ SoladError::InvalidChallenge)]
+    pub upload: Box<Account<'info, Upload>>,
+    /// CHECK: SlotHashes sysvar, validated by address and read in `selection`
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+}
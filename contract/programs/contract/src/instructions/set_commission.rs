@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::CommissionUpdatedEvent,
+    states::{Node, StorageConfig, NODE_SEED, STORAGE_CONFIG_SEED},
+};
+
+// Sets the commission a node operator takes from the rewards earned by delegated stake.
+// A cut applies to rewards distributed from the next `claim_rewards` onwards, and cancels
+// any queued raise. A raise is queued for `unbonding_epochs`: delegators who undelegate as
+// soon as it is announced stop earning at once and have their stake back by the time it
+// applies, so an operator cannot raise the rate on delegators who cannot leave first.
+// Rewards already credited to delegators are unaffected.
+// # Arguments
+// * `ctx` - Context containing node, owner, and config accounts.
+// * `commission_percent` - Operator cut of delegator rewards (must be ≤ 100).
+// # Errors
+// Returns `SoladError` variants if the program is not initialized, the caller is not the node owner,
+// or the commission exceeds 100 percent.
+pub fn process_set_commission(ctx: Context<SetCommission>, commission_percent: u8) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require!(commission_percent <= 100, SoladError::InvalidCommission);
+
+    let node = &mut ctx.accounts.node;
+    require!(
+        node.owner == ctx.accounts.owner.key(),
+        SoladError::Unauthorized
+    );
+
+    let slot = Clock::get()?.slot;
+    node.apply_pending_commission(slot);
+    let effective_slot = if commission_percent <= node.commission_percent {
+        node.commission_percent = commission_percent;
+        slot
+    } else {
+        let current_epoch = slot / config.slots_per_epoch;
+        current_epoch
+            .checked_add(config.unbonding_epochs)
+            .and_then(|epoch| epoch.checked_mul(config.slots_per_epoch))
+            .ok_or(SoladError::MathOverflow)?
+    };
+    node.pending_commission_percent = commission_percent;
+    node.commission_effective_slot = effective_slot;
+
+    emit!(CommissionUpdatedEvent {
+        node: node.key(),
+        commission_percent,
+        effective_slot,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetCommission<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::MintPricingUpdatedEvent,
+    states::{MintPricing, StorageConfig, MINT_PRICING_SEED, STORAGE_CONFIG_SEED},
+    token::{MINT_LEN, TOKEN_PROGRAM_ID},
+};
+
+// Lists an SPL token mint as a payment option, or reprices or delists one.
+// Only the config authority may call this. A newly listed mint is priced immediately since
+// no upload depends on it yet. Repricing follows the config timelock: the price in force
+// now keeps applying until `config_timelock_epochs` have passed, so users are never
+// charged a rate they could not see coming. Disabling a mint takes effect at once and only
+// blocks new uploads and extensions; uploads already paid in the mint are unaffected.
+/// Sets the storage price for a payment mint.
+/// # Arguments
+/// * `ctx` - Context containing the mint pricing, mint, config, and authority accounts.
+/// * `price_per_gb` - Price in the mint's base units per GB (must be > 0).
+/// * `is_enabled` - Whether new uploads may be paid in this mint.
+/// # Errors
+/// Returns errors for an unauthorized signer, a zero price, or mathematical overflows.
+pub fn process_set_mint_pricing(
+    ctx: Context<SetMintPricing>,
+    price_per_gb: u64,
+    is_enabled: bool,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require_keys_eq!(
+        config.authority,
+        ctx.accounts.authority.key(),
+        SoladError::Unauthorized
+    );
+    require!(price_per_gb > 0, SoladError::InvalidPaymentRate);
+
+    let current_epoch = Clock::get()?.slot / config.slots_per_epoch;
+    let pricing = &mut ctx.accounts.mint_pricing;
+    if pricing.mint == Pubkey::default() {
+        pricing.mint = ctx.accounts.mint.key();
+        pricing.price_per_gb = price_per_gb;
+        pricing.activation_epoch = current_epoch;
+    } else if price_per_gb != pricing.price_at(current_epoch) {
+        pricing.price_per_gb = pricing.price_at(current_epoch);
+        pricing.activation_epoch = current_epoch
+            .checked_add(config.config_timelock_epochs)
+            .ok_or(SoladError::MathOverflow)?;
+    }
+    pricing.pending_price_per_gb = price_per_gb;
+    pricing.is_enabled = is_enabled;
+
+    emit!(MintPricingUpdatedEvent {
+        mint: pricing.mint,
+        price_per_gb: pricing.price_per_gb,
+        pending_price_per_gb: pricing.pending_price_per_gb,
+        activation_epoch: pricing.activation_epoch,
+        is_enabled,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetMintPricing<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = MintPricing::SPACE,
+        seeds = [MINT_PRICING_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub mint_pricing: Account<'info, MintPricing>,
+    /// CHECK: A mint of the token program, checked by owner and size; only its address is stored
+    #[account(
+        owner = TOKEN_PROGRAM_ID,
+        constraint = mint.data_len() == MINT_LEN @ SoladError::InvalidTokenAccount
+    )]
+    pub mint: UncheckedAccount<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::UploadDelegateSetEvent,
+    states::{Upload, UPLOAD_SEED},
+};
+
+// Sets the delegate of an upload.
+// The delegate may extend or close the upload on the owner's behalf; refunds still go to the
+// owner. Only the owner can set or change it, and `Pubkey::default()` removes it.
+/// Sets an upload delegate.
+/// # Arguments
+/// * `ctx` - Context containing the upload and owner accounts.
+/// * `data_hash` - Hash of the uploaded data.
+/// * `delegate` - Public key of the delegate, or the default key to remove it.
+/// # Errors
+/// Returns errors if the signer is not the owner or the delegate is the owner.
+pub fn process_set_upload_delegate(
+    ctx: Context<SetUploadDelegate>,
+    data_hash: String,
+    delegate: Pubkey,
+) -> Result<()> {
+    let upload = &mut ctx.accounts.upload;
+    require_keys_eq!(
+        upload.owner,
+        ctx.accounts.owner.key(),
+        SoladError::Unauthorized
+    );
+    require_keys_neq!(delegate, upload.owner, SoladError::InvalidUploadDelegate);
+
+    upload.delegate = delegate;
+
+    emit!(UploadDelegateSetEvent {
+        upload_pda: upload.key(),
+        data_hash,
+        owner: upload.owner,
+        delegate,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(data_hash: String)]
+pub struct SetUploadDelegate<'info> {
+    #[account(
+        mut,
+        seeds = [UPLOAD_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
+        bump
+    )]
+    pub upload: Account<'info, Upload>,
+    pub owner: Signer<'info>,
+}
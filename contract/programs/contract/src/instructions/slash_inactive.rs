@@ -0,0 +1,212 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::InactivitySlashedEvent,
+    instructions::request_replacement::draw_successor,
+    states::{
+        Challenge, Escrow, Node, NodeRegistry, StorageConfig, Upload, CHALLENGE_SEED,
+        NODE_REGISTRY_SEED, NODE_SEED, STAKE_ESCROW_SEED, STORAGE_CONFIG_SEED, UPLOAD_SEED,
+    },
+};
+
+// Slashes a node that has stopped proving a shard and hands its slot to a successor.
+// Anyone may crank this. The evidence is the shard's `Challenge` accounts for each of the
+// last `inactivity_epochs` completed epochs: the node must have been assigned to the shard
+// when every one of them was issued and answered none. A challenge only counts once its
+// deadline has passed and if it was revealed with a full `challenge_response_slots` window
+// left before that deadline, so the node always had time to answer. An epoch without such a
+// challenge cannot be counted, so callers issue and reveal one each epoch before relying
+// on it. The slash takes
+// `slash_penalty_percent` of bonded, unbonding and delegated stake and splits it 90/10
+// between the treasury and the caller, as `slash_timeout` does.
+// The successor is drawn from the registry windows fixed by the upload's `selection_seed`,
+// the same way `request_replacement` draws one, and takes over the shard's committed
+// capacity. It then proves the shard through the regular challenges; there is no separate
+// replacement timeout.
+// Remaining accounts: the `inactivity_epochs` challenge PDAs in epoch order, oldest first,
+// then the candidate windows, each a registry page followed by its node accounts.
+/// Slashes a node for missed PoS challenges.
+/// # Arguments
+/// * `ctx` - Context containing upload, node, stake escrow, registry, config, and caller accounts.
+/// * `data_hash` - Hash of the uploaded data.
+/// * `shard_id` - ID of the shard the node stopped proving.
+/// # Errors
+/// Returns errors if the node is not on the shard, a challenge in the window is missing,
+/// answered by the node, issued before it joined, revealed too late to answer or still open,
+/// the candidate windows are wrong or hold no
+/// eligible successor, or on mathematical overflows.
+pub fn process_slash_inactive<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SlashInactive<'info>>,
+    data_hash: String,
+    shard_id: u8,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let upload = &mut ctx.accounts.upload;
+    require!(upload.data_hash == data_hash, SoladError::InvalidHash);
+    require!(shard_id < upload.shard_count, SoladError::InvalidShardId);
+
+    let node = &mut ctx.accounts.node;
+    let node_key = node.key();
+    require!(
+        upload.shards[shard_id as usize]
+            .node_keys
+            .contains(&node_key),
+        SoladError::InvalidNodeAccount
+    );
+
+    // Every challenge of the window must have been missed by the node
+    let window = config.inactivity_epochs as usize;
+    require!(
+        ctx.remaining_accounts.len() >= window,
+        SoladError::InsufficientAccounts
+    );
+    let (challenge_infos, candidate_infos) = ctx.remaining_accounts.split_at(window);
+    let now_slot = Clock::get()?.slot;
+    let current_epoch = now_slot / config.slots_per_epoch;
+    let response_slots = config.challenge_response_slots();
+    let first_epoch = current_epoch
+        .checked_sub(config.inactivity_epochs)
+        .ok_or(SoladError::NodeNotInactive)?;
+    let upload_key = upload.key();
+    for (epoch, challenge_info) in (first_epoch..current_epoch).zip(challenge_infos) {
+        let (challenge_pda, _) = Pubkey::find_program_address(
+            &[
+                CHALLENGE_SEED,
+                upload_key.as_ref(),
+                &[shard_id],
+                &epoch.to_le_bytes(),
+            ],
+            ctx.program_id,
+        );
+        require_keys_eq!(
+            challenge_info.key(),
+            challenge_pda,
+            SoladError::InvalidChallenge
+        );
+        let challenge: Account<Challenge> = Account::try_from(challenge_info)?;
+        // Only a missed deadline that left the node a full response window counts
+        let answerable_until = challenge
+            .revealed_slot
+            .checked_add(response_slots)
+            .ok_or(SoladError::MathOverflow)?;
+        require!(
+            challenge.revealed
+                && answerable_until <= challenge.deadline_slot
+                && challenge.deadline_slot <= now_slot
+                && challenge.assigned.contains(&node_key)
+                && !challenge.responders.contains(&node_key),
+            SoladError::NodeNotInactive
+        );
+    }
+
+    // Unbonding and delegated stake are slashed alongside bonded stake
+    let slash_amount = node.slash(config.slash_penalty_percent)?;
+    let treasury_amount = slash_amount
+        .checked_mul(90)
+        .ok_or(SoladError::MathOverflow)?
+        / 100;
+    let caller_amount = slash_amount
+        .checked_sub(treasury_amount)
+        .ok_or(SoladError::MathOverflow)?;
+
+    // The stake escrow is program-owned, so lamports are moved directly
+    let stake_escrow_info = ctx.accounts.stake_escrow.to_account_info();
+    let escrow_lamports = stake_escrow_info
+        .lamports()
+        .checked_sub(slash_amount)
+        .ok_or(SoladError::InsufficientFunds)?;
+    **stake_escrow_info.try_borrow_mut_lamports()? = escrow_lamports;
+    **ctx.accounts.treasury.try_borrow_mut_lamports()? += treasury_amount;
+    **ctx
+        .accounts
+        .caller
+        .to_account_info()
+        .try_borrow_mut_lamports()? += caller_amount;
+
+    // Draw a successor from the upload's replacement windows, excluding the shard's peers
+    let shard_size_mb = upload.shards[shard_id as usize].size_mb;
+    let replacement_info = draw_successor(
+        candidate_infos,
+        upload,
+        shard_id,
+        &node_key,
+        config,
+        ctx.accounts.node_registry.page_count,
+        ctx.program_id,
+    )?;
+    let replacement_key = replacement_info.key();
+
+    // The successor takes over the slot and its committed capacity
+    require!(replacement_info.is_writable, SoladError::AccountNotWritable);
+    let mut replacement_node: Account<Node> = Account::try_from(replacement_info)?;
+    replacement_node.upload_count = replacement_node
+        .upload_count
+        .checked_add(1)
+        .ok_or(SoladError::MathOverflow)?;
+    replacement_node.commit_capacity(shard_size_mb)?;
+    replacement_node.exit(ctx.program_id)?;
+
+    let shard = &mut upload.shards[shard_id as usize];
+    if shard.mark_capacity_released(&node_key) {
+        node.release_capacity(shard.size_mb);
+    }
+    if shard.mark_upload_released(&node_key) {
+        node.upload_count = node.upload_count.saturating_sub(1);
+    }
+    shard.replace_node(&node_key, replacement_key);
+
+    emit!(InactivitySlashedEvent {
+        node: node_key,
+        data_hash,
+        shard_id,
+        missed_epochs: config.inactivity_epochs,
+        last_pos_time: node.last_pos_time,
+        slash_amount,
+        treasury_amount,
+        caller_amount,
+        replacement_node: replacement_key,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(data_hash: String)]
+pub struct SlashInactive<'info> {
+    #[account(
+        mut,
+        seeds = [UPLOAD_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
+        bump
+    )]
+    pub upload: Box<Account<'info, Upload>>,
+    #[account(
+        mut,
+        seeds = [NODE_SEED, node.owner.as_ref()],
+        bump
+    )]
+    pub node: Box<Account<'info, Node>>,
+    #[account(
+        mut,
+        seeds = [STAKE_ESCROW_SEED, node.owner.as_ref()],
+        bump
+    )]
+    pub stake_escrow: Account<'info, Escrow>,
+    #[account(
+        seeds = [NODE_REGISTRY_SEED],
+        bump
+    )]
+    pub node_registry: Account<'info, NodeRegistry>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    /// CHECK: Receives the treasury share, validated against the config
+    #[account(mut, address = config.treasury)]
+    pub treasury: AccountInfo<'info>,
+}
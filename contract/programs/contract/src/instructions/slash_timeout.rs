@@ -1,5 +1,4 @@
 pub use anchor_lang::prelude::*;
-use anchor_lang::system_program;
 
 use crate::{
     errors::SoladError,
@@ -52,25 +51,25 @@ pub fn process_slash_timeout(
 
     let exiting_node = &mut ctx.accounts.exiting_node;
     let exiting_stake_escrow = &ctx.accounts.exiting_stake_escrow;
-    require!(
-        exiting_node.key() == exiting_node.owner.key(),
+    let (exiting_node_pda, _) =
+        Pubkey::find_program_address(&[NODE_SEED, exiting_node.owner.as_ref()], ctx.program_id);
+    require_keys_eq!(
+        exiting_node.key(),
+        exiting_node_pda,
         SoladError::InvalidNodeAccount
     );
-    require!(
-        exiting_stake_escrow.key()
-            == Pubkey::find_program_address(
-                &[STAKE_ESCROW_SEED, exiting_node.owner.as_ref()],
-                &ctx.program_id
-            )
-            .0,
-        SoladError::InvalidNodeAccount
+    let (stake_escrow_pda, _) = Pubkey::find_program_address(
+        &[STAKE_ESCROW_SEED, exiting_node.owner.as_ref()],
+        ctx.program_id,
+    );
+    require_keys_eq!(
+        exiting_stake_escrow.key(),
+        stake_escrow_pda,
+        SoladError::InvalidStakeEscrow
     );
 
-    let slash_amount = exiting_node
-        .stake_amount
-        .checked_mul(config.slash_penalty_percent)
-        .ok_or(SoladError::MathOverflow)?
-        / 100;
+    // Unbonding stake is slashed alongside bonded stake
+    let slash_amount = exiting_node.slash(config.slash_penalty_percent)?;
     let treasury_amount = slash_amount
         .checked_mul(90)
         .ok_or(SoladError::MathOverflow)?
@@ -79,44 +78,19 @@ pub fn process_slash_timeout(
         .checked_sub(treasury_amount)
         .ok_or(SoladError::MathOverflow)?;
 
-    let stake_escrow_seeds = &[
-        STAKE_ESCROW_SEED,
-        exiting_node.owner.as_ref(),
-        &[exiting_stake_escrow.bump],
-    ];
-
-    if treasury_amount > 0 {
-        system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: exiting_stake_escrow.to_account_info(),
-                    to: ctx.accounts.treasury.to_account_info(),
-                },
-                &[&stake_escrow_seeds[..]],
-            ),
-            treasury_amount,
-        )?;
-    }
-
-    if caller_amount > 0 {
-        system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: exiting_stake_escrow.to_account_info(),
-                    to: ctx.accounts.caller.to_account_info(),
-                },
-                &[&stake_escrow_seeds[..]],
-            ),
-            caller_amount,
-        )?;
-    }
-
-    exiting_node.stake_amount = exiting_node
-        .stake_amount
+    // The stake escrow is program-owned, so lamports are moved directly
+    let stake_escrow_info = exiting_stake_escrow.to_account_info();
+    let escrow_lamports = stake_escrow_info
+        .lamports()
         .checked_sub(slash_amount)
-        .ok_or(SoladError::MathOverflow)?;
+        .ok_or(SoladError::InsufficientFunds)?;
+    **stake_escrow_info.try_borrow_mut_lamports()? = escrow_lamports;
+    **ctx.accounts.treasury.try_borrow_mut_lamports()? += treasury_amount;
+    **ctx
+        .accounts
+        .caller
+        .to_account_info()
+        .try_borrow_mut_lamports()? += caller_amount;
 
     replacement.pos_submitted = true;
 
@@ -148,7 +122,7 @@ pub struct SlashTimeout<'info> {
     pub exiting_node: Account<'info, Node>,
     #[account(
         mut,
-        seeds = [REPLACEMENT_SEED, exiting_node.owner.as_ref(), data_hash.as_bytes(), &[shard_id]],
+        seeds = [REPLACEMENT_SEED, exiting_node.key().as_ref(), data_hash.as_bytes(), &[shard_id]],
         bump,
         close = caller
     )]
@@ -163,8 +137,7 @@ pub struct SlashTimeout<'info> {
     pub caller: Signer<'info>,
     #[account(mut)]
     pub config: Account<'info, StorageConfig>,
-    /// CHECK: safe
-    #[account(mut)]
+    /// CHECK: Receives the treasury share, validated against the config
+    #[account(mut, address = config.treasury)]
     pub treasury: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
 }
@@ -1,17 +1,20 @@
 pub use anchor_lang::prelude::*;
-use anchor_lang::system_program;
 
 use crate::{
     errors::SoladError,
     events::UserSlashedEvent,
     states::{Escrow, Node, StorageConfig, Upload, ESCROW_SEED, NODE_SEED, UPLOAD_SEED},
+    token::{self, TOKEN_PROGRAM_ID},
 };
 
 // Slashes a user by penalizing their escrow funds for a specific shard of an upload.
 // This function is called when a shard is deemed invalid due to sufficient oversized data reports
-// from nodes (2/3 of assigned nodes). It calculates a penalty based on the configured percentage,
-// transfers the penalty to the treasury, refunds the remaining escrow funds to the payer, and updates
+// from nodes (`oversized_report_threshold` percent of the shard's nodes, rounded up), once its
+// `dispute_window_epochs` have passed without a successful `dispute_oversized_report`. It calculates a penalty based on the configured percentage,
+// transfers the penalty to the treasury, refunds the remaining escrow funds to the sponsor or owner, and updates
 // the shard and node states. The function emits an event for transparency and ensures atomic execution.
+// Uploads paid in an SPL token are slashed and refunded in that token from the token escrow.
+// Remaining accounts: the shard's other node accounts, whose upload slots are given back.
 /// Slashes user escrow for invalid data size.
 /// # Arguments
 /// * `ctx` - Context containing upload, node, escrow, payer, config, treasury, and system program accounts.
@@ -19,52 +22,48 @@ use crate::{
 /// * `shard_id` - The ID of the shard to slash.
 /// # Errors
 /// Returns errors for uninitialized config, invalid hash, invalid shard ID, unauthorized node,
-// insufficient reports, or mathematical overflows. The transaction reverts if any validation fails.
-pub fn process_slash_user(ctx: Context<SlashUser>, data_hash: String, shard_id: u8) -> Result<()> {
+// insufficient reports, an open dispute window, an already slashed shard, or mathematical overflows. The transaction reverts if any validation fails.
+pub fn process_slash_user<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SlashUser<'info>>,
+    data_hash: String,
+    shard_id: u8,
+) -> Result<()> {
     let config = &ctx.accounts.config;
     require!(config.is_initialized, SoladError::NotInitialized);
 
-    // Collect all immutable data from upload upfront
-    let upload = &ctx.accounts.upload;
+    let upload = &mut ctx.accounts.upload;
     require!(upload.data_hash == data_hash, SoladError::InvalidHash);
     require!(shard_id < upload.shard_count, SoladError::InvalidShardId);
 
-    // Store data needed for escrow_seeds and calculations
-    let data_hash_bytes = upload.data_hash.as_bytes();
-    let payer_ref = upload.payer.as_ref();
-    let node_lamports = upload.node_lamports;
-    let size_bytes = upload.size_bytes;
-    let size_mb = (size_bytes + (1024 * 1024 - 1)) / (1024 * 1024); // Ceiling to MB
+    let shard_lamports = upload.shard_lamports(&upload.shards[shard_id as usize])?;
     let payer = upload.payer;
+    let refund_recipient = upload.refund_recipient();
+    let payment_mint = upload.payment_mint;
 
-    // Collect event data (payer) upfront
-    let event_payer = payer;
-
-    // Now create mutable borrow
-    let upload = &mut ctx.accounts.upload.clone();
     let shard = &mut upload.shards[shard_id as usize];
     require!(
         shard.node_keys.contains(&ctx.accounts.node.key()),
         SoladError::Unauthorized
     );
     require!(shard.verified_count == u8::MAX, SoladError::ShardNotInvalid);
+    require!(!shard.user_slashed, SoladError::ShardAlreadySlashed);
+
+    // The reports stand only once nobody disputed them in time
+    let dispute_window_end = shard
+        .invalidated_epoch
+        .checked_add(config.dispute_window_epochs)
+        .ok_or(SoladError::MathOverflow)?;
+    require!(
+        Clock::get()?.slot / config.slots_per_epoch >= dispute_window_end,
+        SoladError::DisputeWindowOpen
+    );
 
-    let node_count = shard
-        .node_keys
-        .iter()
-        .filter(|&&k| k != Pubkey::default())
-        .count();
-    let required_reports = ((node_count as u64 * 2) / 3).max(1);
+    let required_reports = shard.required_reports(config.oversized_report_threshold);
     require!(
         shard.oversized_reports.len() as u64 >= required_reports,
         SoladError::InsufficientReports
     );
 
-    let shard_lamports = node_lamports
-        .checked_mul(shard.size_mb)
-        .ok_or(SoladError::MathOverflow)?
-        .checked_div(size_mb)
-        .ok_or(SoladError::MathOverflow)?;
     let slash_amount = shard_lamports
         .checked_mul(config.user_slash_penalty_percent)
         .ok_or(SoladError::MathOverflow)?
@@ -72,76 +71,99 @@ pub fn process_slash_user(ctx: Context<SlashUser>, data_hash: String, shard_id:
     let refund_amount = shard_lamports
         .checked_sub(slash_amount)
         .ok_or(SoladError::MathOverflow)?;
-
-    let escrow_seeds = &[
-        ESCROW_SEED,
-        data_hash_bytes,
-        payer_ref,
-        &[ctx.accounts.escrow.bump],
-    ];
-
-    // Transfer slash amount to treasury
-    if slash_amount > 0 {
-        system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: ctx.accounts.escrow.to_account_info(),
-                    to: ctx.accounts.treasury.to_account_info(),
-                },
-                &[&escrow_seeds[..]],
-            ),
-            slash_amount,
-        )?;
-    }
-
-    // Refund remaining amount to payer
-    if refund_amount > 0 {
-        system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: ctx.accounts.escrow.to_account_info(),
-                    to: ctx.accounts.payer.to_account_info(),
-                },
-                &[&escrow_seeds[..]],
-            ),
-            refund_amount,
-        )?;
-    }
-
-    // Collect event data for shard before modifying it
     let actual_size_bytes = shard
         .oversized_reports
         .first()
         .map(|r| r.actual_size_mb)
         .unwrap_or(0);
 
-    // Mark shard as slashed and update nodes
-    shard.verified_count = u8::MAX;
-    for key in shard.node_keys.iter_mut() {
-        if *key != Pubkey::default() {
-            let node_account = ctx
+    // Mark the shard slashed before paying out, so the escrow share goes out only once
+    shard.user_slashed = true;
+    for key in shard.node_keys.clone() {
+        if key == Pubkey::default() || !shard.mark_upload_released(&key) {
+            continue;
+        }
+        // The calling node is written back by Anchor, so it is updated in place
+        if key == ctx.accounts.node.key() {
+            let node = &mut ctx.accounts.node;
+            node.upload_count = node
+                .upload_count
+                .checked_sub(1)
+                .ok_or(SoladError::MathOverflow)?;
+        } else {
+            let node_info = ctx
                 .remaining_accounts
                 .iter()
-                .find(|acc| acc.key() == *key)
+                .find(|acc| acc.key() == key)
                 .ok_or(SoladError::InvalidNodeAccount)?;
-            let mut node_data = node_account.data.borrow_mut();
-            let mut node: Node = Node::try_deserialize(&mut node_data.as_ref())
-                .map_err(|_| SoladError::InvalidNodeAccount)?;
+            require!(node_info.is_writable, SoladError::AccountNotWritable);
+            let mut node: Account<Node> = Account::try_from(node_info)?;
             node.upload_count = node
                 .upload_count
                 .checked_sub(1)
                 .ok_or(SoladError::MathOverflow)?;
-            let mut serialized = Vec::new();
-            node.try_serialize(&mut serialized)?;
-            node_data.copy_from_slice(&serialized);
+            node.exit(ctx.program_id)?;
         }
     }
 
+    // The shard's share leaves the escrow, so later claims and closes cannot pay it again
+    let escrow = &mut ctx.accounts.escrow;
+    escrow.lamports = escrow
+        .lamports
+        .checked_sub(shard_lamports)
+        .ok_or(SoladError::InsufficientFunds)?;
+
+    let escrow_seeds = &[
+        ESCROW_SEED,
+        data_hash.as_bytes(),
+        payer.as_ref(),
+        &[ctx.accounts.escrow.bump],
+    ];
+
+    if payment_mint == Pubkey::default() {
+        // The escrow is program-owned, so lamports are moved directly
+        let escrow_info = ctx.accounts.escrow.to_account_info();
+        **escrow_info.try_borrow_mut_lamports()? -= shard_lamports;
+        **ctx.accounts.treasury.try_borrow_mut_lamports()? += slash_amount;
+        **ctx.accounts.payer.try_borrow_mut_lamports()? += refund_amount;
+    } else {
+        // Token uploads are slashed and refunded from the token escrow
+        let token_program = token::token_program(&ctx.accounts.token_program)?;
+        let escrow_key = ctx.accounts.escrow.key();
+        let (escrow_token, _) =
+            token::checked_token_account(&ctx.accounts.escrow_token, &payment_mint, &escrow_key)?;
+        let (treasury_token, _) = token::checked_token_account(
+            &ctx.accounts.treasury_token,
+            &payment_mint,
+            &config.treasury,
+        )?;
+        let (payer_token, _) = token::checked_token_account(
+            &ctx.accounts.payer_token,
+            &payment_mint,
+            &refund_recipient,
+        )?;
+        let escrow_info = ctx.accounts.escrow.to_account_info();
+        token::transfer(
+            token_program,
+            escrow_token,
+            treasury_token,
+            &escrow_info,
+            slash_amount,
+            &[&escrow_seeds[..]],
+        )?;
+        token::transfer(
+            token_program,
+            escrow_token,
+            payer_token,
+            &escrow_info,
+            refund_amount,
+            &[&escrow_seeds[..]],
+        )?;
+    }
+
     // Emit the event after all modifications
     emit!(UserSlashedEvent {
-        payer: event_payer,
+        payer,
         data_hash,
         shard_id,
         slash_amount,
@@ -163,7 +185,7 @@ pub struct SlashUser<'info> {
     pub upload: Account<'info, Upload>,
     #[account(
         mut,
-        seeds = [NODE_SEED, node.key().as_ref()],
+        seeds = [NODE_SEED, node.owner.as_ref()],
         bump
     )]
     pub node: Account<'info, Node>,
@@ -173,8 +195,8 @@ pub struct SlashUser<'info> {
         bump
     )]
     pub escrow: Account<'info, Escrow>,
-    /// CHECK: Safe
-    #[account(mut)]
+    /// CHECK: Upload sponsor, or owner if unsponsored; receives the refund
+    #[account(mut, address = upload.refund_recipient())]
     pub payer: AccountInfo<'info>,
     #[account(mut)]
     pub config: Account<'info, StorageConfig>,
@@ -182,4 +204,17 @@ pub struct SlashUser<'info> {
     #[account(mut, address = config.treasury)]
     pub treasury: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
+    // Token accounts, required when the upload was paid in an SPL token
+    /// CHECK: Token account owned by the escrow PDA that holds the node share
+    #[account(mut)]
+    pub escrow_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Treasury's token account for the payment mint
+    #[account(mut)]
+    pub treasury_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Refund recipient's token account for the payment mint
+    #[account(mut)]
+    pub payer_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL Token program, validated by address
+    #[account(address = TOKEN_PROGRAM_ID)]
+    pub token_program: Option<UncheckedAccount<'info>>,
 }
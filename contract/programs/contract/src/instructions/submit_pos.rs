@@ -1,19 +1,20 @@
 use anchor_lang::prelude::*;
-use anchor_lang::system_program;
+use anchor_lang::solana_program::sysvar::instructions as instructions_sysvar;
 
 use crate::{
     errors::SoladError,
     events::{OversizedDataReportedEvent, PoSEvent, ReplacementVerifiedEvent},
     states::{
-        Node, OversizedReport, PoSSubmission, Replacement, StorageConfig, Upload, NODE_SEED,
-        REPLACEMENT_SEED, STAKE_ESCROW_SEED, UPLOAD_SEED,
+        Challenge, Node, OversizedReport, PoSSubmission, Replacement, StorageConfig, Upload,
+        CHALLENGE_LEAF_COUNT, NODE_SEED, REPLACEMENT_SEED, UPLOAD_SEED,
     },
-    utils::{verify_merkle_proof, verify_signature},
+    utils::{pos_attestation_message, verify_ed25519_signature, verify_merkle_proof},
 };
 
+// Standard submissions answer the shard's current `Challenge`; see `verify_pos_response`.
 /// Submits a single Proof of Storage (PoS) submission for a specific shard.
 /// # Arguments
-/// * `ctx` - Context containing upload, node, replacement, and owner accounts.
+/// * `ctx` - Context containing upload, challenge, node, replacement, and owner accounts.
 /// * `submission` - PoS submission data for a single shard.
 /// # Errors
 /// Returns errors for invalid proofs, signatures, unauthorized challengers, or invalid submissions.
@@ -38,7 +39,7 @@ pub fn process_submit_pos<'info>(
         ctx.accounts.upload.payer.key(), uploader.key(), SoladError::InvalidUploader
     );
 
-    let upload = &mut ctx.accounts.upload.clone();
+    let upload = &ctx.accounts.upload;
     require!(
         upload.data_hash == submission.data_hash,
         SoladError::InvalidHash
@@ -48,24 +49,18 @@ pub fn process_submit_pos<'info>(
         SoladError::InvalidShardId
     );
 
-    let shard = upload
-        .shards
-        .get_mut(submission.shard_id as usize)
-        .ok_or(SoladError::InvalidShardId)?;
+    let shard = &upload.shards[submission.shard_id as usize];
+    let peer_count = upload.shard_peers(shard).len();
+    let node_key = ctx.accounts.node.key();
     require!(
-        shard.node_keys.contains(&ctx.accounts.node.key()),
+        shard.node_keys.contains(&node_key),
         SoladError::Unauthorized
     );
 
-    let node_count = shard
-        .node_keys
-        .iter()
-        .filter(|&&k| k != Pubkey::default())
-        .count();
-    require!(node_count > 1, SoladError::SingleNodeShard);
+    let node_count = shard.node_count();
+    require!(peer_count > 1, SoladError::SingleNodeShard);
 
     // Handle oversized data report
-    let upload = &ctx.accounts.upload;
     if let Some(actual_size) = submission.actual_size_mb {
         require!(actual_size > shard.size_mb, SoladError::InvalidSizeReport);
         require!(
@@ -73,16 +68,15 @@ pub fn process_submit_pos<'info>(
                 > Clock::get()?.unix_timestamp,
             SoladError::SizeReportTimeout
         );
+        // Reports are written to the upload account in place
+        let shard = &mut ctx.accounts.upload.shards[submission.shard_id as usize];
         require!(
-            !shard
-                .oversized_reports
-                .iter()
-                .any(|r| r.node == ctx.accounts.node.key()),
+            !shard.oversized_reports.iter().any(|r| r.node == node_key),
             SoladError::TooManyReports
         );
 
         let report = OversizedReport {
-            node: ctx.accounts.node.key(),
+            node: node_key,
             actual_size_mb: actual_size,
         };
         shard.oversized_reports.push(report);
@@ -90,127 +84,124 @@ pub fn process_submit_pos<'info>(
         emit!(OversizedDataReportedEvent {
             data_hash: submission.data_hash,
             shard_id: submission.shard_id,
-            node: ctx.accounts.node.key(),
+            node: node_key,
             declared_size_mb: shard.size_mb,
             actual_size_mb: actual_size,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
-        let required_reports =
-            ((node_count as f64 * config.oversized_report_threshold) / 100.0) as u64;
-        if shard.oversized_reports.len() as u64 >= required_reports {
+        // Once invalid, the shard's reports can be disputed for `dispute_window_epochs`
+        let required_reports = shard.required_reports(config.oversized_report_threshold);
+        if shard.oversized_reports.len() as u64 >= required_reports
+            && shard.verified_count != u8::MAX
+        {
             shard.verified_count = u8::MAX; // Mark shard as invalid
+            shard.invalidated_epoch = Clock::get()?.slot / config.slots_per_epoch;
         }
-
         return Ok(());
     }
 
-    // Standard PoS submission
-    let merkle_root = submission.merkle_root.ok_or(SoladError::MissingPoSData)?;
-    let merkle_proof = submission.merkle_proof.ok_or(SoladError::MissingPoSData)?;
-    let leaf = submission.leaf.ok_or(SoladError::MissingPoSData)?;
-    let challenger_signature = submission
-        .challenger_signature
-        .ok_or(SoladError::MissingPoSData)?;
-    let challenger_pubkey = submission
-        .challenger_pubkey
-        .ok_or(SoladError::MissingPoSData)?;
-
-    require!(
-        shard.node_keys.contains(&challenger_pubkey),
-        SoladError::InvalidChallenger
-    );
-    require!(
-        ctx.accounts.node.key() != challenger_pubkey,
-        SoladError::ChallengerIsNode
-    );
-
-    verify_merkle_proof(&merkle_root, &merkle_proof, &leaf)?;
-
-    let timestamp = Clock::get()?.unix_timestamp;
-    let message = format!(
-        "{}:{}:{:?}:{}",
-        submission.data_hash, submission.shard_id, merkle_root, timestamp
-    );
-    verify_signature(
-        &message,
-        &challenger_signature,
-        &challenger_pubkey,
-        timestamp,
+    // Standard PoS submission answers the shard's challenge for this epoch
+    let challenger_node = verify_pos_response(
+        &ctx.accounts.upload,
+        &ctx.accounts.challenge,
+        node_key,
+        &submission,
+        &ctx.accounts.instructions,
+        ctx.program_id,
     )?;
+    record_pos_response(&mut ctx.accounts.challenge, node_key)?;
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.node.last_pos_time = timestamp;
 
+    // The shard is updated in place on the upload account
+    let shard = &mut ctx.accounts.upload.shards[submission.shard_id as usize];
+    let merkle_root = shard.commitment.merkle_root;
     shard.verified_count = shard
         .verified_count
         .checked_add(1)
         .ok_or(SoladError::MathOverflow)?;
-    shard.challenger = challenger_pubkey;
-
-    // Handle node replacement
-    let replacement = &mut ctx.accounts.replacement;
-    if replacement.data_hash == submission.data_hash
-        && replacement.shard_id == submission.shard_id
-        && replacement.replacement_node == ctx.accounts.node.key()
-        && !replacement.pos_submitted
-        && replacement.request_epoch + config.replacement_timeout_epochs > Clock::get()?.epoch
-    {
-        replacement.pos_submitted = true;
+    shard.challenger = challenger_node;
+    shard.record_proof(&node_key)?;
 
-        let exiting_node_account = ctx
-            .remaining_accounts
-            .iter()
-            .find(|acc| acc.key() == replacement.exiting_node)
-            .ok_or(SoladError::InvalidNodeAccount)?;
-        msg!("Found exiting_node_account: {}", exiting_node_account.key());
-
-        let (stake_escrow_key, _bump) = Pubkey::find_program_address(
-            &[STAKE_ESCROW_SEED, replacement.exiting_node.as_ref()],
+    // Handle node replacement, when the submitting node is taking over an exiting node's shard
+    if let Some(replacement) = ctx.accounts.replacement.as_mut() {
+        // The replacement is keyed by the exiting node, so its PDA is checked here
+        let (replacement_pda, _) = Pubkey::find_program_address(
+            &[
+                REPLACEMENT_SEED,
+                replacement.exiting_node.as_ref(),
+                submission.data_hash.as_bytes(),
+                &[submission.shard_id],
+            ],
             ctx.program_id,
         );
-        let exiting_stake_escrow = ctx
-            .remaining_accounts
-            .iter()
-            .find(|acc| acc.key() == stake_escrow_key)
-            .ok_or(SoladError::InvalidNodeAccount)?;
-        msg!("Found exiting_stake_escrow: {}", exiting_stake_escrow.key());
+        require!(
+            replacement.key() == replacement_pda,
+            SoladError::InvalidReplacement
+        );
 
-        let exiting_node_data = exiting_node_account.data.borrow();
-        let exiting_node: Node = Node::try_deserialize(&mut exiting_node_data.as_ref())
-            .map_err(|_| SoladError::InvalidNodeAccount)?;
+        if replacement.data_hash == submission.data_hash
+            && replacement.shard_id == submission.shard_id
+            && replacement.replacement_node == ctx.accounts.node.key()
+            && !replacement.pos_submitted
+            && replacement.request_epoch + config.replacement_timeout_epochs > Clock::get()?.epoch
+        {
+            replacement.pos_submitted = true;
 
-        let stake_escrow_seeds = &[
-            STAKE_ESCROW_SEED,
-            exiting_node.owner.as_ref(),
-            &[exiting_stake_escrow.data.borrow()[8]],
-        ];
-        system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: exiting_stake_escrow.to_account_info(),
-                    to: ctx.accounts.owner.to_account_info(),
-                },
-                &[&stake_escrow_seeds[..]],
-            ),
-            exiting_node.stake_amount,
-        )?;
+            let exiting_node_account = ctx
+                .remaining_accounts
+                .iter()
+                .find(|acc| acc.key() == replacement.exiting_node)
+                .ok_or(SoladError::InvalidNodeAccount)?;
 
-        emit!(ReplacementVerifiedEvent {
-            exiting_node: replacement.exiting_node,
-            replacement_node: replacement.replacement_node,
-            data_hash: submission.data_hash.clone(),
-            shard_id: submission.shard_id,
-            timestamp,
-        });
+            // The exiting node's stake starts unbonding; it stays slashable until withdrawn
+            let mut exiting_node_data = exiting_node_account.data.borrow_mut();
+            let mut exiting_node: Node = Node::try_deserialize(&mut exiting_node_data.as_ref())
+                .map_err(|_| SoladError::InvalidNodeAccount)?;
+            let release_epoch = (Clock::get()?.slot / config.slots_per_epoch)
+                .checked_add(config.unbonding_epochs)
+                .ok_or(SoladError::MathOverflow)?;
+            let stake_amount = exiting_node.stake_amount;
+            exiting_node.begin_unbonding(stake_amount, release_epoch)?;
+            let mut serialized = Vec::new();
+            exiting_node.try_serialize(&mut serialized)?;
+            exiting_node_data[..serialized.len()].copy_from_slice(&serialized);
+
+            emit!(ReplacementVerifiedEvent {
+                exiting_node: replacement.exiting_node,
+                replacement_node: replacement.replacement_node,
+                data_hash: submission.data_hash.clone(),
+                shard_id: submission.shard_id,
+                timestamp,
+            });
+        }
     }
 
-    // Update node accounts if shard is fully verified
+    // Once the shard is fully verified each node gives back its upload slot, at most once
     if shard.verified_count as usize >= node_count {
-        for key in shard.node_keys.iter().filter(|&&k| k != Pubkey::default()) {
-            msg!("Resolving node account: {}", key);
+        let shard_nodes: Vec<Pubkey> = shard
+            .node_keys
+            .iter()
+            .copied()
+            .filter(|&k| k != Pubkey::default())
+            .collect();
+        for key in shard_nodes {
+            if !shard.mark_upload_released(&key) {
+                continue;
+            }
+            if key == node_key {
+                let node = &mut ctx.accounts.node;
+                node.upload_count = node
+                    .upload_count
+                    .checked_sub(1)
+                    .ok_or(SoladError::MathOverflow)?;
+                continue;
+            }
             let node_account = ctx
                 .remaining_accounts
                 .iter()
-                .find(|acc| acc.key() == *key)
+                .find(|acc| acc.key() == key)
                 .ok_or(SoladError::InvalidNodeAccount)?;
             let mut node_data = node_account.data.borrow_mut();
             let mut node: Node = Node::try_deserialize(&mut node_data.as_ref())
@@ -230,35 +221,136 @@ pub fn process_submit_pos<'info>(
         shard_id: submission.shard_id,
         node: ctx.accounts.node.key(),
         merkle_root,
-        challenger: challenger_pubkey,
+        challenger: challenger_node,
         timestamp,
     });
 
     Ok(())
 }
 
+// Checks a standard PoS response and returns the challenger's node PDA.
+// The response must answer the shard's current `Challenge` once its leaves are revealed: one
+// proof per challenged leaf, in challenge order, before the challenge deadline, and each node
+// answers once. Proofs are checked only against the shard's Merkle commitment from upload
+// time (the upload's root, or the fragment's own for an erasure-coded shard), and must be as
+// long as the committed tree is deep so an inner node cannot stand in for a leaf. Another of the shard's peers (see
+// `Upload::shard_peers`) attests the response by signing `pos_attestation_message` with its
+// owner key; that signature is checked by an Ed25519 program instruction earlier in the
+// same transaction.
+// Shared by `submit_pos` and `submit_pos_batch`; nothing is written here.
+pub(crate) fn verify_pos_response(
+    upload: &Account<Upload>,
+    challenge: &Account<Challenge>,
+    node_key: Pubkey,
+    submission: &PoSSubmission,
+    instructions: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<Pubkey> {
+    require!(
+        upload.data_hash == submission.data_hash,
+        SoladError::InvalidHash
+    );
+    let shard = upload
+        .shards
+        .get(submission.shard_id as usize)
+        .ok_or(SoladError::InvalidShardId)?;
+    require!(shard.node_keys.contains(&node_key), SoladError::Unauthorized);
+    let peers = upload.shard_peers(shard);
+    require!(peers.len() > 1, SoladError::SingleNodeShard);
+
+    require_keys_eq!(challenge.upload, upload.key(), SoladError::InvalidChallenge);
+    require!(
+        challenge.shard_id == submission.shard_id,
+        SoladError::InvalidChallenge
+    );
+    require!(challenge.revealed, SoladError::ChallengeNotRevealed);
+    require!(
+        Clock::get()?.slot < challenge.deadline_slot,
+        SoladError::ChallengeExpired
+    );
+    require!(
+        !challenge.responders.contains(&node_key),
+        SoladError::ChallengeAlreadyAnswered
+    );
+
+    let proofs = submission
+        .proofs
+        .as_ref()
+        .ok_or(SoladError::MissingPoSData)?;
+    let challenger_pubkey = submission
+        .challenger_pubkey
+        .ok_or(SoladError::MissingPoSData)?;
+    let (challenger_node, _) =
+        Pubkey::find_program_address(&[NODE_SEED, challenger_pubkey.as_ref()], program_id);
+    require!(
+        peers.contains(&challenger_node),
+        SoladError::InvalidChallenger
+    );
+    require!(node_key != challenger_node, SoladError::ChallengerIsNode);
+
+    let commitment = &shard.commitment;
+    let tree_depth = (commitment.leaf_count as u64)
+        .next_power_of_two()
+        .trailing_zeros() as usize;
+    require!(
+        proofs.len() == CHALLENGE_LEAF_COUNT,
+        SoladError::ChallengeIndexMismatch
+    );
+    for (proof, &leaf_index) in proofs.iter().zip(challenge.leaf_indices.iter()) {
+        require!(
+            proof.leaf_index == leaf_index,
+            SoladError::ChallengeIndexMismatch
+        );
+        require!(
+            proof.merkle_proof.len() == tree_depth,
+            SoladError::InvalidMerkleProof
+        );
+        verify_merkle_proof(
+            &commitment.merkle_root,
+            &proof.merkle_proof,
+            &proof.leaf,
+            leaf_index,
+        )?;
+    }
+
+    let message = pos_attestation_message(&challenge.key(), &node_key, &commitment.merkle_root);
+    verify_ed25519_signature(instructions, &challenger_pubkey, &message)?;
+
+    Ok(challenger_node)
+}
+
+// Marks `node_key` as having answered the challenge.
+pub(crate) fn record_pos_response(challenge: &mut Challenge, node_key: Pubkey) -> Result<()> {
+    let responder = challenge
+        .responders
+        .iter_mut()
+        .find(|k| **k == Pubkey::default())
+        .ok_or(SoladError::ChallengeAlreadyAnswered)?;
+    *responder = node_key;
+    Ok(())
+}
+
 #[derive(Accounts)]
-#[instruction(data_hash: String, shard_id: u8, uploader: Pubkey)]
+#[instruction(submission: PoSSubmission, uploader: Pubkey)]
 pub struct SubmitPoS<'info> {
     #[account(
         mut,
-        seeds = [UPLOAD_SEED, data_hash.as_bytes(), uploader.key().as_ref()],
+        seeds = [UPLOAD_SEED, submission.data_hash.as_bytes(), uploader.as_ref()],
         bump
     )]
     pub upload: Box<Account<'info, Upload>>,
+    #[account(mut)]
+    pub challenge: Box<Account<'info, Challenge>>,
     #[account(
         mut,
-        seeds = [NODE_SEED, node.key().as_ref()],
+        seeds = [NODE_SEED, owner.key().as_ref()],
         bump
     )]
     pub node: Box<Account<'info, Node>>,
-    #[account(
-        mut,
-        seeds = [REPLACEMENT_SEED, node.key().as_ref(), data_hash.as_bytes(), &[shard_id]],
-        bump,
-        close = owner
-    )]
-    pub replacement: Box<Account<'info, Replacement>>,
+    // Pending replacement of an exiting node's shard, if the submitter is its successor;
+    // its PDA is keyed by the exiting node and checked in the handler
+    #[account(mut, close = owner)]
+    pub replacement: Option<Box<Account<'info, Replacement>>>,
     #[account(mut)]
     pub owner: Signer<'info>,
     #[account(mut)]
@@ -266,5 +358,8 @@ pub struct SubmitPoS<'info> {
     /// CHECK: Safe, as the treasury account is validated against config.treasury
     #[account(mut)]
     pub treasury: AccountInfo<'info>,
+    /// CHECK: Instructions sysvar, validated by address and read for the Ed25519 attestation
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
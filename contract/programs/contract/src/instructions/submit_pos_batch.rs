@@ -0,0 +1,187 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as instructions_sysvar;
+
+use crate::{
+    errors::SoladError,
+    events::PoSBatchItemEvent,
+    instructions::submit_pos::{record_pos_response, verify_pos_response},
+    states::{
+        Challenge, Node, PoSSubmission, StorageConfig, Upload, NODE_SEED, STORAGE_CONFIG_SEED,
+        UPLOAD_SEED,
+    },
+};
+
+// Submits up to `max_submssions` standard PoS responses in one transaction.
+// Remaining accounts hold an (upload, challenge) pair per submission, in submission order,
+// followed by the node accounts of any shard that a response fully verifies. Each item is
+// checked with the same rules as `submit_pos` and succeeds or fails on its own: a failing
+// item writes nothing and is reported with its error code in `PoSBatchItemEvent`.
+// Oversized reports and replacement verification still go through `submit_pos`.
+/// Submits a batch of PoS responses.
+/// # Arguments
+/// * `ctx` - Context containing node, owner, config, and instructions sysvar accounts, with
+///   uploads and challenges as remaining accounts.
+/// * `submissions` - PoS responses, one per (upload, challenge) pair.
+/// # Errors
+/// Returns errors for an empty or oversized batch or missing accounts; per-item failures are
+/// reported in events instead.
+pub fn process_submit_pos_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SubmitPoSBatch<'info>>,
+    submissions: Vec<PoSSubmission>,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require!(
+        !submissions.is_empty() && submissions.len() as u64 <= config.max_submssions,
+        SoladError::InvalidBatchSize
+    );
+    require!(
+        ctx.remaining_accounts.len() >= submissions.len() * 2,
+        SoladError::InsufficientAccounts
+    );
+
+    let timestamp = Clock::get()?.unix_timestamp;
+    for (index, submission) in submissions.iter().enumerate() {
+        let result = submit_batch_item(
+            ctx.remaining_accounts,
+            index,
+            submission,
+            &mut ctx.accounts.node,
+            &ctx.accounts.instructions,
+            ctx.program_id,
+        );
+        if let Err(err) = &result {
+            msg!("PoS batch item {} failed: {}", index, err);
+        }
+
+        emit!(PoSBatchItemEvent {
+            index: index as u16,
+            data_hash: submission.data_hash.clone(),
+            shard_id: submission.shard_id,
+            node: ctx.accounts.node.key(),
+            verified: result.is_ok(),
+            error_code: result.as_ref().err().map_or(0, error_code),
+            timestamp,
+        });
+    }
+
+    Ok(())
+}
+
+// Verifies and records one batch item. Accounts are only written once every check has
+// passed, so a failing item leaves no partial state behind.
+fn submit_batch_item<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    index: usize,
+    submission: &PoSSubmission,
+    node: &mut Account<'info, Node>,
+    instructions: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let upload_info = &remaining_accounts[index * 2];
+    let challenge_info = &remaining_accounts[index * 2 + 1];
+    require!(
+        upload_info.is_writable && challenge_info.is_writable,
+        SoladError::AccountNotWritable
+    );
+
+    let mut upload: Account<Upload> = Account::try_from(upload_info)?;
+    let (upload_pda, _) = Pubkey::find_program_address(
+        &[
+            UPLOAD_SEED,
+            submission.data_hash.as_bytes(),
+            upload.payer.as_ref(),
+        ],
+        program_id,
+    );
+    require_keys_eq!(upload.key(), upload_pda, SoladError::InvalidUpload);
+    let mut challenge: Account<Challenge> = Account::try_from(challenge_info)?;
+
+    let node_key = node.key();
+    let challenger_node = verify_pos_response(
+        &upload,
+        &challenge,
+        node_key,
+        submission,
+        instructions,
+        program_id,
+    )?;
+    record_pos_response(&mut challenge, node_key)?;
+
+    let shard = &mut upload.shards[submission.shard_id as usize];
+    shard.verified_count = shard
+        .verified_count
+        .checked_add(1)
+        .ok_or(SoladError::MathOverflow)?;
+    shard.challenger = challenger_node;
+    shard.record_proof(&node_key)?;
+
+    // Once the shard is fully verified each node gives back its upload slot, at most once
+    let shard_nodes: Vec<Pubkey> = shard
+        .node_keys
+        .iter()
+        .copied()
+        .filter(|&k| k != Pubkey::default())
+        .collect();
+    let mut released_nodes = Vec::new();
+    let mut own_upload_count = node.upload_count;
+    if shard.verified_count as usize >= shard_nodes.len() {
+        for key in shard_nodes {
+            if !shard.mark_upload_released(&key) {
+                continue;
+            }
+            if key == node_key {
+                own_upload_count = own_upload_count
+                    .checked_sub(1)
+                    .ok_or(SoladError::MathOverflow)?;
+                continue;
+            }
+            let node_info = remaining_accounts
+                .iter()
+                .find(|acc| acc.key() == key)
+                .ok_or(SoladError::InvalidNodeAccount)?;
+            let mut shard_node: Account<Node> = Account::try_from(node_info)?;
+            shard_node.upload_count = shard_node
+                .upload_count
+                .checked_sub(1)
+                .ok_or(SoladError::MathOverflow)?;
+            released_nodes.push(shard_node);
+        }
+    }
+
+    upload.exit(program_id)?;
+    challenge.exit(program_id)?;
+    for shard_node in released_nodes.iter() {
+        shard_node.exit(program_id)?;
+    }
+    node.upload_count = own_upload_count;
+    node.last_pos_time = Clock::get()?.unix_timestamp;
+
+    Ok(())
+}
+
+fn error_code(err: &Error) -> u32 {
+    match err {
+        Error::AnchorError(err) => err.error_code_number,
+        Error::ProgramError(err) => u64::from(err.program_error.clone()) as u32,
+    }
+}
+
+#[derive(Accounts)]
+pub struct SubmitPoSBatch<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub node: Box<Account<'info, Node>>,
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Box<Account<'info, StorageConfig>>,
+    /// CHECK: Instructions sysvar, validated by address and read for the Ed25519 attestations
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+}
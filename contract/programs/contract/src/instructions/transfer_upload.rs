@@ -0,0 +1,92 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::UploadTransferredEvent,
+    states::{StorageConfig, Upload, UserUploadKeys, UPLOAD_SEED, USER_UPLOAD_KEYS_SEED},
+};
+
+// Transfers an upload to a new owner.
+// The upload and escrow PDAs stay seeded by the original payer, so only `Upload.owner`
+// changes: the new owner may extend, close and delegate the upload and receives its refunds.
+// The upload moves from the current owner's `UserUploadKeys` to the new owner's, which is
+// created here if needed and counts against `max_user_uploads`. Any delegate is cleared,
+// since it acted for the previous owner.
+/// Transfers upload ownership.
+/// # Arguments
+/// * `ctx` - Context containing the upload, config, owner, and both owners' upload key accounts.
+/// * `data_hash` - Hash of the uploaded data.
+/// * `new_owner` - Public key of the new owner.
+/// # Errors
+/// Returns errors if the signer is not the owner, the new owner is the current owner or the
+/// default key, or the new owner already holds `max_user_uploads` uploads.
+pub fn process_transfer_upload(
+    ctx: Context<TransferUpload>,
+    data_hash: String,
+    new_owner: Pubkey,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let upload = &mut ctx.accounts.upload;
+    require_keys_eq!(
+        upload.owner,
+        ctx.accounts.owner.key(),
+        SoladError::Unauthorized
+    );
+    require!(
+        new_owner != upload.owner && new_owner != Pubkey::default(),
+        SoladError::InvalidUploadOwner
+    );
+
+    let upload_key = upload.key();
+    ctx.accounts
+        .owner_upload_keys
+        .uploads
+        .retain(|key| *key != upload_key);
+    ctx.accounts
+        .new_owner_upload_keys
+        .add(new_owner, upload_key, config.max_user_uploads)?;
+
+    let previous_owner = upload.owner;
+    upload.owner = new_owner;
+    upload.delegate = Pubkey::default();
+
+    emit!(UploadTransferredEvent {
+        upload_pda: upload_key,
+        data_hash,
+        previous_owner,
+        new_owner,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(data_hash: String, new_owner: Pubkey)]
+pub struct TransferUpload<'info> {
+    #[account(
+        mut,
+        seeds = [UPLOAD_SEED, data_hash.as_bytes(), upload.payer.as_ref()],
+        bump
+    )]
+    pub upload: Account<'info, Upload>,
+    pub config: Account<'info, StorageConfig>,
+    #[account(
+        mut,
+        seeds = [USER_UPLOAD_KEYS_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub owner_upload_keys: Box<Account<'info, UserUploadKeys>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = size_of::<UserUploadKeys>() + 8 + (config.max_user_uploads as usize * 32),
+        seeds = [USER_UPLOAD_KEYS_SEED, new_owner.as_ref()],
+        bump
+    )]
+    pub new_owner_upload_keys: Box<Account<'info, UserUploadKeys>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::UndelegatedEvent,
+    states::{Delegation, Node, StorageConfig, DELEGATION_SEED, NODE_SEED, STORAGE_CONFIG_SEED},
+};
+
+// Starts undelegating shares from a node's pool.
+// The shares stop counting towards the node's selection weight and stop earning rewards
+// immediately, but stay in the pool, and so stay slashable, for `config.unbonding_epochs`.
+// Their lamport value is fixed only when `claim_delegator_rewards` pays them out. Queuing
+// more shares restarts the window for the whole unbonding balance.
+// # Arguments
+// * `ctx` - Context containing node, delegation, delegator, and config accounts.
+// * `shares` - Active shares to undelegate (must be > 0).
+// # Errors
+// Returns `SoladError` variants if the program is not initialized, the shares are zero,
+// or the delegator holds fewer active shares.
+pub fn process_undelegate(ctx: Context<Undelegate>, shares: u64) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require!(shares > 0, SoladError::InsufficientShares);
+
+    let node = &mut ctx.accounts.node;
+    let delegation = &mut ctx.accounts.delegation;
+    require!(
+        delegation.active_shares() >= shares,
+        SoladError::InsufficientShares
+    );
+
+    delegation.settle(node.reward_per_share)?;
+
+    let release_epoch = (Clock::get()?.slot / config.slots_per_epoch)
+        .checked_add(config.unbonding_epochs)
+        .ok_or(SoladError::MathOverflow)?;
+    delegation.unbonding_shares = delegation
+        .unbonding_shares
+        .checked_add(shares)
+        .ok_or(SoladError::MathOverflow)?;
+    delegation.unbonding_release_epoch = release_epoch;
+    delegation.reset_debt(node.reward_per_share)?;
+    node.unbonding_shares = node
+        .unbonding_shares
+        .checked_add(shares)
+        .ok_or(SoladError::MathOverflow)?;
+
+    emit!(UndelegatedEvent {
+        node: node.key(),
+        delegator: delegation.delegator,
+        shares,
+        release_epoch,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Undelegate<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, node.owner.as_ref()],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+    #[account(
+        mut,
+        seeds = [DELEGATION_SEED, node.key().as_ref(), delegator.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    pub delegator: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+}
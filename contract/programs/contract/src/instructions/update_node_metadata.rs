@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::NodeMetadataUpdatedEvent,
+    states::{
+        Node, NodeMetadata, NodeMetadataParams, StorageConfig, NODE_METADATA_SEED, NODE_SEED,
+        STORAGE_CONFIG_SEED,
+    },
+};
+
+// Replaces the endpoints, region, capacity and version a node advertises.
+// The declared capacity is mirrored onto the node account, where `assign_upload` checks it.
+// Lowering it below what is already committed is allowed; the node just stops receiving
+// new shards until enough are released.
+// Operators call this when a node moves host or is upgraded, so clients picking upload
+// targets and peers bootstrapping from chain never see stale addresses. The account is
+// allocated at its maximum size at registration, so updates never realloc.
+// # Arguments
+// * `ctx` - Context containing node, node metadata, owner, and config accounts.
+// * `metadata` - The new metadata; it replaces every field.
+// # Errors
+// Returns `SoladError` variants if the program is not initialized, the caller is not the node owner,
+// or the metadata is invalid.
+pub fn process_update_node_metadata(
+    ctx: Context<UpdateNodeMetadata>,
+    metadata: NodeMetadataParams,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+    require!(
+        ctx.accounts.node.owner == ctx.accounts.owner.key(),
+        SoladError::Unauthorized
+    );
+    metadata.validate()?;
+
+    ctx.accounts.node.capacity_mb = metadata.capacity_mb;
+    let node_metadata = &mut ctx.accounts.node_metadata;
+    node_metadata.apply_params(&metadata, Clock::get()?.unix_timestamp);
+
+    emit!(NodeMetadataUpdatedEvent {
+        node: node_metadata.node,
+        http_url: metadata.http_url,
+        multiaddr: metadata.multiaddr,
+        region: metadata.region,
+        capacity_mb: metadata.capacity_mb,
+        version: metadata.version,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateNodeMetadata<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+    #[account(
+        mut,
+        seeds = [NODE_METADATA_SEED, node.key().as_ref()],
+        bump
+    )]
+    pub node_metadata: Account<'info, NodeMetadata>,
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+}
@@ -1,17 +1,38 @@
-use crate::states::{UserUploadKeys, ESCROW_SEED, UPLOAD_SEED, USER_UPLOAD_KEYS_SEED};
+use crate::states::{
+    DataCommitment, ErasureCoding, HashAlgo, MintPricing, ShardRole, UserUploadKeys, ESCROW_SEED,
+    MAX_STORAGE_DURATION_DAYS, MINT_PRICING_SEED, SELECTION_SEED_DELAY, UPLOAD_SEED,
+    USER_UPLOAD_KEYS_SEED,
+};
 use crate::{
     errors::SoladError,
-    events::UploadEvent,
-    states::{Escrow, Node, NodeRegistry, ShardInfo, StorageConfig, Upload},
+    events::NodeSelectionPendingEvent,
+    states::{Escrow, ShardInfo, StorageConfig, Upload},
+    token::{self, TOKEN_PROGRAM_ID},
+    utils::{calculate_storage_fee, lamports_per_gb},
 };
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
-use sha2::{Digest, Sha256};
 use std::mem::size_of;
 
 // Processes data upload to the Solad storage system.
-// Initializes an upload, validates inputs, assigns shards to nodes,
-// handles payments, and emits an event.
+// Initializes an upload, validates inputs, lays out its shards and takes payment. Nodes are
+// not drawn here: the upload records a selection slot `SELECTION_SEED_DELAY` slots ahead,
+// and once it has passed anyone can `assign_upload` to draw the shards' nodes from that
+// slot's hash. The payer has paid before the hash exists, so it cannot try payer keys or
+// upload indices until one draws nodes it controls.
+// Each shard will be assigned up to `replication_factor` nodes and at least the configured
+// minimum. The fee for every requested replica is held in escrow, treasury share included,
+// and `assign_upload` keeps the fee for the replicas actually assigned and refunds the rest.
+// Passing `erasure` stores the data Reed-Solomon coded instead: each shard is one fragment,
+// data fragments first, held by a single node, and no node holds two fragments of the
+// upload. Fragments carry their own Merkle commitment, so PoS proves each one on its own,
+// and the fee covers the encoded footprint rather than full copies.
+// Payment is in lamports by default. Passing a listed `MintPricing` and its token accounts
+// pays in that SPL token instead, into a token escrow owned by the escrow PDA.
+// Lamport prices follow `config.usd_micros_per_gb` at the price feed's current rate when
+// USD pricing is on, so the price feed account must then be passed.
+// A signing `sponsor` pays the fee (from its own token account for token payments) while the
+// payer still signs, pays the account rent and owns the upload; refunds go to the sponsor.
 /// Processes data upload.
 /// # Arguments
 /// * `ctx` - Context with accounts for upload processing.
@@ -19,14 +40,25 @@ use std::mem::size_of;
 /// * `size_bytes` - Data size in bytes (min 1 KB).
 /// * `shard_count` - Number of shards to split data into.
 /// * `storage_duration_days` - Duration to store data in days.
+/// * `commitment` - Chunk Merkle root, chunk size, and leaf count; the leaf count must
+///   equal ceil(size_bytes / chunk_size). PoS proofs are checked against the root.
+/// * `hash_algo` - Algorithm that produced `data_hash`; nodes verify the data with it.
+/// * `replication_factor` - Nodes to store each shard, within the configured range; 1 for
+///   an erasure-coded upload.
+/// * `erasure` - Optional Reed-Solomon layout; `shard_count` must equal its fragment count.
 /// # Errors
-/// Returns errors for invalid inputs, insufficient nodes, or payment issues.
+/// Returns errors for invalid inputs or payment issues.
+#[allow(clippy::too_many_arguments)]
 pub fn process_upload_data<'info>(
     ctx: Context<'_, '_, 'info, 'info, UploadData<'info>>,
     data_hash: String,
     size_bytes: u64,
     shard_count: u8,
     storage_duration_days: u64,
+    commitment: DataCommitment,
+    hash_algo: HashAlgo,
+    replication_factor: u8,
+    erasure: Option<ErasureCoding>,
 ) -> Result<()> {
     let config = &ctx.accounts.config;
     require!(config.is_initialized, SoladError::NotInitialized);
@@ -37,124 +69,76 @@ pub fn process_upload_data<'info>(
         shard_count >= config.min_shard_count && shard_count <= config.max_shard_count,
         SoladError::InvalidShardCount
     );
+    // Fragments are not replicated; parity fragments provide the redundancy
+    let replication_valid = match &erasure {
+        Some(_) => replication_factor == 1,
+        None => (config.min_replication_factor..=config.max_replication_factor)
+            .contains(&replication_factor),
+    };
+    require!(replication_valid, SoladError::InvalidReplicationFactor);
     require!(
         data_hash.len() <= 64 && !data_hash.is_empty(),
         SoladError::InvalidHash
     );
     require!(
-        storage_duration_days >= 1 && storage_duration_days <= 365 * 2000,
+        (1..=MAX_STORAGE_DURATION_DAYS).contains(&storage_duration_days),
         SoladError::InvalidStorageDuration
     );
+    commitment.validate(size_bytes)?;
+    let fragments = match &erasure {
+        Some(coding) => {
+            require!(
+                shard_count == coding.fragment_count(),
+                SoladError::InvalidShardCount
+            );
+            coding.fragment_commitments(size_bytes)?
+        }
+        None => Vec::new(),
+    };
 
     let upload = &mut ctx.accounts.upload;
-    let node_registry = &ctx.accounts.node_registry;
 
     // Initialize or update UserUploadKeys
-    let user_upload_keys = &mut ctx.accounts.user_upload_keys;
-    if user_upload_keys.uploads.is_empty() {
-        user_upload_keys.user = ctx.accounts.payer.key();
-        user_upload_keys.uploads = vec![upload.key()];
-    } else {
-        require!(
-            user_upload_keys.uploads.len() < config.max_user_uploads as usize,
-            SoladError::TooManyUploads
-        );
-        user_upload_keys.uploads.push(upload.key());
-    }
-
-    // Collect and validate nodes
-    let mut node_stakes = Vec::new();
-    let mut processed_keys = Vec::new();
-
-    for node_info in ctx.remaining_accounts.iter() {
-        let node_key = node_info.key();
-        require!(
-            node_registry.nodes.contains(&node_key),
-            SoladError::InvalidNodeAccount
-        );
-        require!(
-            !processed_keys.contains(&node_key),
-            SoladError::DuplicateNodeAccount
-        );
-        require!(node_info.is_writable, SoladError::AccountNotWritable);
-        processed_keys.push(node_key);
-        let node_account: Account<Node> = Account::try_from(node_info)?;
-        if node_account.is_active && node_account.stake_amount >= config.min_node_stake {
-            node_stakes.push((node_key, node_account.stake_amount));
-        }
-    }
-
-    require!(!node_stakes.is_empty(), SoladError::InsufficientNodes);
-
-    let node_count = node_stakes.len() as u64;
-    let max_possible_shards = node_count.min(config.max_shard_count as u64) as u8;
-    require!(
-        shard_count <= max_possible_shards,
-        SoladError::InvalidShardCount
-    );
-
-    // Calculate lamports
-    let base_lamports = size_bytes
-        .checked_mul(config.sol_per_gb)
-        .ok_or(SoladError::MathOverflow)?
-        .checked_div(1024 * 1024 * 1024)
-        .ok_or(SoladError::MathOverflow)?
-        .checked_mul(shard_count as u64)
-        .ok_or(SoladError::MathOverflow)?
-        .checked_mul(storage_duration_days)
-        .ok_or(SoladError::MathOverflow)?
-        .checked_div(7300)
-        .ok_or(SoladError::MathOverflow)?;
-    let total_lamports = base_lamports;
-    let treasury_lamports = total_lamports
-        .checked_mul(config.treasury_fee_percent)
-        .ok_or(SoladError::MathOverflow)?
-        / 100;
-    let node_lamports = total_lamports
-        .checked_mul(config.node_fee_percent)
-        .ok_or(SoladError::MathOverflow)?
-        / 100;
-
-    // Transfer lamports
-    system_program::transfer(
-        CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            system_program::Transfer {
-                from: ctx.accounts.payer.to_account_info(),
-                to: ctx.accounts.treasury.to_account_info(),
-            },
-        ),
-        treasury_lamports,
+    ctx.accounts.user_upload_keys.add(
+        ctx.accounts.payer.key(),
+        upload.key(),
+        config.max_user_uploads,
     )?;
 
-    let escrow = &mut ctx.accounts.escrow;
-    escrow.lamports = node_lamports;
-    system_program::transfer(
-        CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            system_program::Transfer {
-                from: ctx.accounts.payer.to_account_info(),
-                to: ctx.accounts.escrow.to_account_info(),
-            },
+    // Price in the payment mint's base units when a mint pricing is passed, otherwise in
+    // lamports converted from the USD price at execution time
+    let (payment_mint, price_per_gb) = match &ctx.accounts.mint_pricing {
+        Some(pricing) => {
+            require!(pricing.is_enabled, SoladError::MintNotAccepted);
+            let epoch = Clock::get()?.slot / config.slots_per_epoch;
+            (pricing.mint, pricing.price_at(epoch))
+        }
+        None => (
+            Pubkey::default(),
+            lamports_per_gb(
+                config,
+                ctx.accounts.price_feed.as_deref(),
+                Clock::get()?.unix_timestamp,
+            )?,
         ),
-        node_lamports,
-    )?;
+    };
 
-    // Calculate shard sizes
+    // Calculate shard sizes. Fragments all have the size of one data fragment, padding
+    // included, and are never merged.
     let size_mb = size_bytes
         .checked_add(1024 * 1024 - 1)
         .ok_or(SoladError::MathOverflow)?
         / (1024 * 1024);
     let mut adjusted_shard_count = shard_count;
-    let mut shard_sizes_mb = vec![0u64; shard_count as usize];
-    let base_shard_size = size_mb / (shard_count as u64);
-    let remainder_mb = size_mb % (shard_count as u64);
-
-    for i in 0..shard_count as usize {
-        shard_sizes_mb[i] = base_shard_size + if i < remainder_mb as usize { 1 } else { 0 };
-    }
+    let mut shard_sizes_mb = match &erasure {
+        Some(coding) => {
+            let fragment_bytes = size_bytes.div_ceil(coding.data_shards as u64);
+            vec![fragment_bytes.div_ceil(1024 * 1024); shard_count as usize]
+        }
+        None => split_shard_sizes(size_mb, shard_count),
+    };
 
-    if size_mb >= config.shard_min_mb {
+    if erasure.is_none() && size_mb >= config.shard_min_mb {
         let mut all_valid = true;
         for &size in shard_sizes_mb.iter() {
             if size > 0 && size < config.shard_min_mb {
@@ -163,164 +147,168 @@ pub fn process_upload_data<'info>(
             }
         }
         if !all_valid {
-            adjusted_shard_count = ((size_mb + config.shard_min_mb - 1) / config.shard_min_mb)
+            adjusted_shard_count = size_mb
+                .div_ceil(config.shard_min_mb)
                 .max(config.min_shard_count as u64)
-                .min(max_possible_shards as u64)
                 .min(shard_count as u64) as u8;
-            shard_sizes_mb = vec![0u64; adjusted_shard_count as usize];
-            let new_base_size = size_mb / (adjusted_shard_count as u64);
-            let new_remainder = size_mb % (adjusted_shard_count as u64);
-            for j in 0..adjusted_shard_count as usize {
-                shard_sizes_mb[j] = new_base_size + if j < new_remainder as usize { 1 } else { 0 };
-            }
+            shard_sizes_mb = split_shard_sizes(size_mb, adjusted_shard_count);
         }
     }
 
     require!(
-        adjusted_shard_count >= config.min_shard_count
-            && adjusted_shard_count <= max_possible_shards,
+        adjusted_shard_count >= config.min_shard_count,
         SoladError::InvalidShardCount
     );
+    require!(
+        shard_sizes_mb.len() == adjusted_shard_count as usize,
+        SoladError::InvalidShardSizes
+    );
 
     // Initialize upload account
+    let slot = Clock::get()?.slot;
     upload.data_hash = data_hash.clone();
+    upload.hash_algo = hash_algo;
     upload.size_bytes = size_bytes;
+    upload.merkle_root = commitment.merkle_root;
+    upload.chunk_size = commitment.chunk_size;
+    upload.leaf_count = commitment.leaf_count;
     upload.shard_count = adjusted_shard_count;
-    upload.node_lamports = node_lamports;
+    upload.replication_factor = replication_factor;
+    upload.data_shards = erasure.as_ref().map_or(0, |coding| coding.data_shards);
+    upload.parity_shards = erasure.as_ref().map_or(0, |coding| coding.parity_shards);
     upload.payer = ctx.accounts.payer.key();
+    upload.owner = ctx.accounts.payer.key();
+    upload.delegate = Pubkey::default();
+    upload.sponsor = ctx
+        .accounts
+        .sponsor
+        .as_ref()
+        .map_or(Pubkey::default(), |s| s.key());
+    upload.payment_mint = payment_mint;
     upload.upload_time = Clock::get()?.unix_timestamp;
     upload.storage_duration_days = storage_duration_days;
     upload.expiry_time = upload
         .upload_time
         .checked_add((storage_duration_days as i64) * 86400)
         .ok_or(SoladError::MathOverflow)?;
-    upload.current_slot = Clock::get()?.slot;
+    upload.term_start = upload.upload_time;
+    upload.current_slot = slot;
+    upload.selection_slot = slot
+        .checked_add(SELECTION_SEED_DELAY)
+        .ok_or(SoladError::MathOverflow)?;
+    upload.nodes_assigned = false;
+    upload.selection_seed = [0; 32];
     upload.shards = Vec::new();
 
-    // Assign nodes to shards
-    let mut assigned_nodes: Vec<Vec<Pubkey>> = vec![vec![]; adjusted_shard_count as usize];
-    let mut updated_nodes: Vec<Pubkey> = Vec::new();
-
-    for i in 0..adjusted_shard_count as usize {
-        let mut nodes_for_shard = vec![];
-        let mut remaining_nodes = node_stakes.clone();
-
-        let seed = format!(
-            "{}:{}:{}:{}",
-            data_hash,
-            i,
-            upload.current_slot,
-            Clock::get()?.unix_timestamp,
-        );
-        let mut rng_state =
-            u64::from_le_bytes(Sha256::digest(seed.as_bytes())[..8].try_into().unwrap());
-
-        let nodes_needed = (remaining_nodes.len() as usize).min(3);
-        for _ in 0..nodes_needed {
-            if remaining_nodes.is_empty() {
-                break;
-            }
-            let total_remaining_stake: u64 = remaining_nodes.iter().map(|(_, stake)| stake).sum();
-            if total_remaining_stake == 0 {
-                let (selected_pubkey, _) = remaining_nodes.remove(0);
-                nodes_for_shard.push(selected_pubkey);
-                if !updated_nodes.contains(&selected_pubkey) {
-                    updated_nodes.push(selected_pubkey);
-                }
-                continue;
-            }
-            rng_state ^= rng_state << 13;
-            rng_state ^= rng_state >> 7;
-            rng_state ^= rng_state << 17;
-            let target = rng_state % total_remaining_stake;
-            let mut cumulative = 0u64;
-            let mut selected_index = 0;
-
-            for (j, (_, stake)) in remaining_nodes.iter().enumerate() {
-                cumulative = cumulative
-                    .checked_add(*stake)
-                    .ok_or(SoladError::MathOverflow)?;
-                if target < cumulative {
-                    selected_index = j;
-                    break;
-                }
-            }
-
-            let (selected_pubkey, _) = remaining_nodes.remove(selected_index);
-            nodes_for_shard.push(selected_pubkey);
-            if !updated_nodes.contains(&selected_pubkey) {
-                updated_nodes.push(selected_pubkey);
-            }
-        }
-
-        require!(nodes_for_shard.len() >= 1, SoladError::InsufficientNodes);
-        assigned_nodes[i] = nodes_for_shard;
-    }
-
-    // Update node upload counts
-    for node_key in updated_nodes.clone() {
-        let node_info = ctx
-            .remaining_accounts
-            .iter()
-            .find(|acc| acc.key() == node_key)
-            .ok_or(SoladError::InvalidNodeAccount)?;
-        let mut node_account: Account<Node> = Account::try_from(node_info)?;
-        node_account.upload_count = node_account
-            .upload_count
-            .checked_add(1)
-            .ok_or(SoladError::MathOverflow)?;
-
-        let mut node_data = node_info.try_borrow_mut_data()?;
-        node_account
-            .serialize(&mut &mut node_data[..])
-            .map_err(|_| SoladError::SerializationError)?;
-    }
-
-    // Assign shards
-    require!(
-        shard_sizes_mb.len() == adjusted_shard_count as usize,
-        SoladError::InvalidShardSizes
-    );
-    require!(
-        assigned_nodes.len() == adjusted_shard_count as usize,
-        SoladError::InvalidNodeAssignments
-    );
-
-    for i in 0..adjusted_shard_count as usize {
-        let shard_id = i as u8;
-        let mut node_array = [Pubkey::default(); 3];
-        let nodes = &assigned_nodes[i];
-        for (j, &key) in nodes.iter().enumerate().take(3) {
-            node_array[j] = key;
-        }
+    // Lay out the shards; their nodes are filled in by `assign_upload`
+    for (i, &shard_size_mb) in shard_sizes_mb.iter().enumerate() {
+        let (role, shard_commitment) = match &erasure {
+            Some(coding) if i < coding.data_shards as usize => (ShardRole::Data, fragments[i]),
+            Some(_) => (ShardRole::Parity, fragments[i]),
+            None => (ShardRole::Replica, commitment),
+        };
         upload.shards.push(ShardInfo {
-            shard_id,
-            node_keys: node_array,
+            shard_id: i as u8,
+            role,
+            commitment: shard_commitment,
+            proven_epochs: vec![],
+            node_keys: vec![],
             verified_count: 0,
-            size_mb: shard_sizes_mb[i],
+            size_mb: shard_size_mb,
             challenger: Pubkey::default(),
             oversized_reports: vec![],
-            rewarded_nodes: vec![],
+            invalidated_epoch: 0,
+            capacity_released: 0,
+            upload_released: 0,
+            user_slashed: false,
         });
     }
 
-    // Emit event
-    emit!(UploadEvent {
+    // Charge for every requested replica; `assign_upload` settles the fee for the replicas
+    // actually assigned
+    let (treasury_lamports, node_lamports) = calculate_storage_fee(
+        config,
+        price_per_gb,
+        upload.requested_bytes()?,
+        storage_duration_days,
+    )?;
+    let held_amount = treasury_lamports
+        .checked_add(node_lamports)
+        .ok_or(SoladError::MathOverflow)?;
+
+    // A sponsor pays the fee in place of the payer, who still pays the account rent
+    let funder_info = match &ctx.accounts.sponsor {
+        Some(sponsor) => sponsor.to_account_info(),
+        None => ctx.accounts.payer.to_account_info(),
+    };
+    if payment_mint == Pubkey::default() {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: funder_info,
+                    to: ctx.accounts.escrow.to_account_info(),
+                },
+            ),
+            held_amount,
+        )?;
+    } else {
+        let token_program = token::token_program(&ctx.accounts.token_program)?;
+        let (payer_token, _) = token::checked_token_account(
+            &ctx.accounts.payer_token,
+            &payment_mint,
+            funder_info.key,
+        )?;
+        let (escrow_token, _) = token::checked_token_account(
+            &ctx.accounts.escrow_token,
+            &payment_mint,
+            &ctx.accounts.escrow.key(),
+        )?;
+        token::transfer(
+            token_program,
+            payer_token,
+            escrow_token,
+            &funder_info,
+            held_amount,
+            &[],
+        )?;
+    }
+
+    // The escrow holds the treasury share too until the nodes are assigned
+    let escrow = &mut ctx.accounts.escrow;
+    escrow.bump = ctx.bumps.escrow;
+    escrow.lamports = held_amount;
+    upload.node_lamports = node_lamports;
+
+    emit!(NodeSelectionPendingEvent {
         upload_pda: upload.key(),
         data_hash,
-        size_bytes,
-        shard_count: adjusted_shard_count,
-        payer: ctx.accounts.payer.key(),
-        nodes: updated_nodes,
-        storage_duration_days,
-        timestamp: Clock::get()?.unix_timestamp,
+        selection_slot: upload.selection_slot,
+        held_amount,
     });
 
     Ok(())
 }
 
+// Splits `size_mb` across `shard_count` shards, spreading the remainder over the first shards.
+fn split_shard_sizes(size_mb: u64, shard_count: u8) -> Vec<u64> {
+    let base_shard_size = size_mb / (shard_count as u64);
+    let remainder_mb = size_mb % (shard_count as u64);
+    (0..shard_count as u64)
+        .map(|i| base_shard_size + u64::from(i < remainder_mb))
+        .collect()
+}
+
 #[derive(Accounts)]
-#[instruction(data_hash: String, size_bytes: u64, shard_count: u8, storage_duration_days: u64)]
+#[instruction(
+    data_hash: String,
+    size_bytes: u64,
+    shard_count: u8,
+    storage_duration_days: u64,
+    commitment: DataCommitment,
+    hash_algo: HashAlgo,
+    replication_factor: u8
+)]
 pub struct UploadData<'info> {
     #[account(
         init_if_needed,
@@ -333,20 +321,16 @@ pub struct UploadData<'info> {
     #[account(
         init,
         payer = payer,
-        space = 8 + 64 + 8 + 1 + 8 + 32 + 8 + 8 + 8 + 8 + (146 * shard_count as usize),
+        space = 8 + 64 + 1 + 8 + 32 + 4 + 4 + 1 + 1 + 1 + 1 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 32
+            + ShardInfo::space(replication_factor) * shard_count as usize,
         seeds = [UPLOAD_SEED, data_hash.as_bytes(), payer.key().as_ref()],
         bump
     )]
     pub upload: Account<'info, Upload>,
     #[account(mut)]
     config: Account<'info, StorageConfig>,
-    #[account(mut, seeds = [b"node_registry"], bump)]
-    pub node_registry: Account<'info, NodeRegistry>,
     #[account(mut)]
     pub payer: Signer<'info>,
-    /// CHECK: Safe
-    #[account(mut, address = config.treasury)]
-    pub treasury: AccountInfo<'info>,
     #[account(
         init,
         payer = payer,
@@ -359,4 +343,23 @@ pub struct UploadData<'info> {
     #[account(address = crate::ID)]
     pub program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
+    // Token payment accounts, all passed when paying in an SPL token. The token accounts
+    // are checked against the mint in the handler.
+    #[account(seeds = [MINT_PRICING_SEED, mint_pricing.mint.as_ref()], bump)]
+    pub mint_pricing: Option<Account<'info, MintPricing>>,
+    /// CHECK: Payer's token account for the payment mint, or the sponsor's when sponsored
+    #[account(mut)]
+    pub payer_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: Token account owned by the escrow PDA that holds the node share
+    #[account(mut)]
+    pub escrow_token: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL Token program, validated by address
+    #[account(address = TOKEN_PROGRAM_ID)]
+    pub token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: SOL/USD price feed, required while USD pricing is on; checked against
+    /// `config.price_feed` and parsed as a `PriceFeed`
+    pub price_feed: Option<UncheckedAccount<'info>>,
+    /// Pays the fee in place of the payer, who still owns the upload
+    #[account(mut)]
+    pub sponsor: Option<Signer<'info>>,
 }
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::SoladError,
+    events::StakeWithdrawnEvent,
+    states::{Escrow, Node, StorageConfig, NODE_SEED, STAKE_ESCROW_SEED, STORAGE_CONFIG_SEED},
+};
+
+// Releases a node's unbonding stake back to its owner once the unbonding period is over.
+// Whatever survived slashing during the window is paid out in full. The stake escrow is
+// program-owned, so lamports are debited from it directly rather than through the system
+// program.
+// # Arguments
+// * `ctx` - Context containing node, stake escrow, owner, and config accounts.
+// # Errors
+// Returns `SoladError` variants if the program is not initialized, the caller is not the node owner,
+// nothing is unbonding, or the unbonding period has not ended.
+pub fn process_withdraw_unstaked(ctx: Context<WithdrawUnstaked>) -> Result<()> {
+    let config = &ctx.accounts.config;
+    require!(config.is_initialized, SoladError::NotInitialized);
+
+    let node = &mut ctx.accounts.node;
+    require!(
+        node.owner == ctx.accounts.owner.key(),
+        SoladError::Unauthorized
+    );
+    require!(node.unbonding_amount > 0, SoladError::NoUnbondingStake);
+
+    let current_epoch = Clock::get()?.slot / config.slots_per_epoch;
+    require!(
+        current_epoch >= node.unbonding_release_epoch,
+        SoladError::UnbondingNotComplete
+    );
+
+    let amount = node.unbonding_amount;
+    node.unbonding_amount = 0;
+    node.unbonding_release_epoch = 0;
+
+    let stake_escrow_info = ctx.accounts.stake_escrow.to_account_info();
+    let escrow_lamports = stake_escrow_info
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(SoladError::InsufficientFunds)?;
+    **stake_escrow_info.try_borrow_mut_lamports()? = escrow_lamports;
+    **ctx
+        .accounts
+        .owner
+        .to_account_info()
+        .try_borrow_mut_lamports()? += amount;
+
+    emit!(StakeWithdrawnEvent {
+        node: node.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUnstaked<'info> {
+    #[account(
+        mut,
+        seeds = [NODE_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+    #[account(
+        mut,
+        seeds = [STAKE_ESCROW_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub stake_escrow: Account<'info, Escrow>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [STORAGE_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, StorageConfig>,
+}
@@ -6,10 +6,15 @@ use instructions::*;
 
 mod errors;
 mod events;
-mod states;
+pub mod selection;
+pub mod states;
+mod token;
 mod utils;
 
-use crate::states::{PoSSubmission, ShardReplacement};
+use crate::states::{
+    ConfigParams, DataCommitment, ErasureCoding, HashAlgo, NodeMetadataParams, PoSSubmission,
+    ShardReplacement,
+};
 
 declare_id!("4Fbo2dQdqrVhxLBbZrxVEbDBxp8GmNa9voEN96d4fQJp");
 
@@ -27,64 +32,68 @@ pub mod contract {
 
     use super::*;
 
-    pub fn initialize(
-        ctx: Context<Initialize>,
-        treasury: Pubkey,
-        sol_per_gb: u64,
-        treasury_fee_percent: u64,
-        node_fee_percent: u64,
-        shard_min_mb: u64,
-        epochs_total: u64,
-        slash_penalty_percent: u64,
-        min_shard_count: u8,
-        max_shard_count: u8,
-        slots_per_epoch: u64,
-        min_node_stake: u64,
-        replacement_timeout_epochs: u64,
-        min_lamports_per_upload: u64,
-        max_user_uploads: u64,
-        user_slash_penalty_percent: u64,
-        reporting_window: u64,
-        oversized_report_threshold: f64,
-        max_submssions: u64,
-    ) -> Result<()> {
-        process_initialize(
-            ctx,
-            treasury,
-            sol_per_gb,
-            treasury_fee_percent,
-            node_fee_percent,
-            shard_min_mb,
-            epochs_total,
-            slash_penalty_percent,
-            min_shard_count,
-            max_shard_count,
-            slots_per_epoch,
-            min_node_stake,
-            replacement_timeout_epochs,
-            min_lamports_per_upload,
-            max_user_uploads,
-            user_slash_penalty_percent,
-            reporting_window,
-            oversized_report_threshold,
-            max_submssions,
-        )
+    pub fn initialize(ctx: Context<Initialize>, params: ConfigParams) -> Result<()> {
+        process_initialize(ctx, params)
     }
 
-    pub fn register_node(ctx: Context<RegisterNode>, stake_amount: u64) -> Result<()> {
-        process_register_node(ctx, stake_amount)
+    pub fn register_node(
+        ctx: Context<RegisterNode>,
+        stake_amount: u64,
+        metadata: NodeMetadataParams,
+    ) -> Result<()> {
+        process_register_node(ctx, stake_amount, metadata)
     }
 
     pub fn deregister_node(ctx: Context<DeregisterNode>) -> Result<()> {
         process_deregister_node(ctx)
     }
 
+    pub fn update_node_metadata(
+        ctx: Context<UpdateNodeMetadata>,
+        metadata: NodeMetadataParams,
+    ) -> Result<()> {
+        process_update_node_metadata(ctx, metadata)
+    }
+
+    pub fn add_stake(ctx: Context<AddStake>, amount: u64) -> Result<()> {
+        process_add_stake(ctx, amount)
+    }
+
+    pub fn request_unstake(ctx: Context<RequestUnstake>, amount: u64) -> Result<()> {
+        process_request_unstake(ctx, amount)
+    }
+
+    pub fn withdraw_unstaked(ctx: Context<WithdrawUnstaked>) -> Result<()> {
+        process_withdraw_unstaked(ctx)
+    }
+
+    pub fn set_commission(ctx: Context<SetCommission>, commission_percent: u8) -> Result<()> {
+        process_set_commission(ctx, commission_percent)
+    }
+
+    pub fn delegate(ctx: Context<Delegate>, amount: u64) -> Result<()> {
+        process_delegate(ctx, amount)
+    }
+
+    pub fn undelegate(ctx: Context<Undelegate>, shares: u64) -> Result<()> {
+        process_undelegate(ctx, shares)
+    }
+
+    pub fn claim_delegator_rewards(ctx: Context<ClaimDelegatorRewards>) -> Result<()> {
+        process_claim_delegator_rewards(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn upload_data<'info>(
         ctx: Context<'_, '_, 'info, 'info, UploadData<'info>>,
         data_hash: String,
         size_bytes: u64,
         shard_count: u8,
         storage_duration_days: u64,
+        commitment: DataCommitment,
+        hash_algo: HashAlgo,
+        replication_factor: u8,
+        erasure: Option<ErasureCoding>,
     ) -> Result<()> {
         process_upload_data(
             ctx,
@@ -92,13 +101,62 @@ pub mod contract {
             size_bytes,
             shard_count,
             storage_duration_days,
+            commitment,
+            hash_algo,
+            replication_factor,
+            erasure,
         )
     }
 
-    pub fn slash_user(ctx: Context<SlashUser>, data_hash: String, shard_id: u8) -> Result<()> {
+    pub fn assign_upload<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AssignUpload<'info>>,
+        data_hash: String,
+    ) -> Result<()> {
+        process_assign_upload(ctx, data_hash)
+    }
+
+    pub fn extend_upload(
+        ctx: Context<ExtendUpload>,
+        data_hash: String,
+        additional_days: u64,
+    ) -> Result<()> {
+        process_extend_upload(ctx, data_hash, additional_days)
+    }
+
+    pub fn slash_user<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SlashUser<'info>>,
+        data_hash: String,
+        shard_id: u8,
+    ) -> Result<()> {
         process_slash_user(ctx, data_hash, shard_id)
     }
 
+    pub fn dispute_oversized_report<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DisputeOversizedReport<'info>>,
+        data_hash: String,
+        shard_id: u8,
+    ) -> Result<()> {
+        process_dispute_oversized_report(ctx, data_hash, shard_id)
+    }
+
+    pub fn issue_challenge(
+        ctx: Context<IssueChallenge>,
+        data_hash: String,
+        uploader: Pubkey,
+        shard_id: u8,
+        epoch: u64,
+    ) -> Result<()> {
+        process_issue_challenge(ctx, data_hash, uploader, shard_id, epoch)
+    }
+
+    pub fn reveal_challenge(ctx: Context<RevealChallenge>) -> Result<()> {
+        process_reveal_challenge(ctx)
+    }
+
+    pub fn close_challenge(ctx: Context<CloseChallenge>) -> Result<()> {
+        process_close_challenge(ctx)
+    }
+
     pub fn submit_pos<'info>(
         ctx: Context<'_, '_, 'info, 'info, SubmitPoS<'info>>,
         submission: PoSSubmission,
@@ -107,6 +165,13 @@ pub mod contract {
         process_submit_pos(ctx, submission, uploader)
     }
 
+    pub fn submit_pos_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SubmitPoSBatch<'info>>,
+        submissions: Vec<PoSSubmission>,
+    ) -> Result<()> {
+        process_submit_pos_batch(ctx, submissions)
+    }
+
     pub fn claim_rewards(
         ctx: Context<ClaimRewards>,
         data_hash: String,
@@ -134,9 +199,24 @@ pub mod contract {
     pub fn close_upload<'info>(
         ctx: Context<'_, '_, 'info, 'info, CloseUpload<'info>>,
         data_hash: String,
-        shard_id: u8,
     ) -> Result<()> {
-        process_close_upload(ctx, data_hash, shard_id)
+        process_close_upload(ctx, data_hash)
+    }
+
+    pub fn transfer_upload(
+        ctx: Context<TransferUpload>,
+        data_hash: String,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        process_transfer_upload(ctx, data_hash, new_owner)
+    }
+
+    pub fn set_upload_delegate(
+        ctx: Context<SetUploadDelegate>,
+        data_hash: String,
+        delegate: Pubkey,
+    ) -> Result<()> {
+        process_set_upload_delegate(ctx, data_hash, delegate)
     }
 
     pub fn slash_timeout(
@@ -148,34 +228,40 @@ pub mod contract {
         process_slash_timeout(ctx, data_hash, shard_id, exiting_node)
     }
 
-    pub fn update_config(
-        ctx: Context<UpdateConfig>,
-        sol_per_gb: Option<u64>,
-        treasury_fee_percent: Option<u64>,
-        node_fee_percent: Option<u64>,
-        shard_min_mb: Option<u64>,
-        epochs_total: Option<u64>,
-        slash_penalty_percent: Option<u64>,
-        min_shard_count: Option<u8>,
-        max_shard_count: Option<u8>,
-        slots_per_epoch: Option<u64>,
-        min_node_stake: Option<u64>,
-        replacement_timeout_epochs: Option<u64>,
+    pub fn slash_inactive<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SlashInactive<'info>>,
+        data_hash: String,
+        shard_id: u8,
     ) -> Result<()> {
-        process_update_config(
-            ctx,
-            sol_per_gb,
-            treasury_fee_percent,
-            node_fee_percent,
-            shard_min_mb,
-            epochs_total,
-            slash_penalty_percent,
-            min_shard_count,
-            max_shard_count,
-            slots_per_epoch,
-            min_node_stake,
-            replacement_timeout_epochs,
-        )
+        process_slash_inactive(ctx, data_hash, shard_id)
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+        process_update_config(ctx, params)
+    }
+
+    pub fn apply_config_change(ctx: Context<ApplyConfigChange>) -> Result<()> {
+        process_apply_config_change(ctx)
+    }
+
+    pub fn cancel_config_change(ctx: Context<CancelConfigChange>) -> Result<()> {
+        process_cancel_config_change(ctx)
+    }
+
+    pub fn set_mint_pricing(
+        ctx: Context<SetMintPricing>,
+        price_per_gb: u64,
+        is_enabled: bool,
+    ) -> Result<()> {
+        process_set_mint_pricing(ctx, price_per_gb, is_enabled)
+    }
+
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        process_propose_authority(ctx, new_authority)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        process_accept_authority(ctx)
     }
 }
 // CLI instructions for interacting with the Solad program.
@@ -192,45 +278,167 @@ pub mod contract {
 //     --slash-penalty-percent <SLASH_PENALTY_PERCENT> \
 //     --min-shard-count <MIN_SHARD_COUNT> \
 //     --max-shard-count <MAX_SHARD_COUNT> \
+//     --min-replication-factor <MIN_REPLICATION_FACTOR> \
+//     --max-replication-factor <MAX_REPLICATION_FACTOR> \
 //     --slots-per-epoch <SLOTS_PER_EPOCH> \
 //     --min-node-stake <MIN_NODE_STAKE> \
 //     --replacement-timeout-epochs <REPLACEMENT_TIMEOUT_EPOCHS> \
+//     --inactivity-epochs <INACTIVITY_EPOCHS> \
 //     --min-lamports-per-upload <MIN_LAMPORTS_PER_UPLOAD> \
 //     --user-slash-penalty-percent <USER_SLASH_PENALTY_PERCENT> \
+//     --reporting-window <REPORTING_WINDOW_EPOCHS> \
+//     --max-user-uploads <MAX_USER_UPLOADS> \
+//     --oversized-report-threshold <OVERSIZED_REPORT_THRESHOLD_PERCENT> \
+//     --dispute-window-epochs <DISPUTE_WINDOW_EPOCHS> \
+//     --max-submissions <MAX_SUBMISSIONS> \
+//     --config-timelock-epochs <CONFIG_TIMELOCK_EPOCHS> \
+//     --unbonding-epochs <UNBONDING_EPOCHS> \
+//     --usd-micros-per-gb <USD_MICROS_PER_GB> \
+//     --price-feed <PRICE_FEED_PUBKEY> \
+//     --max-price-age-secs <MAX_PRICE_AGE_SECS> \
+//     --max-price-confidence-bps <MAX_PRICE_CONFIDENCE_BPS> \
+//     --price-fallback-to-sol <true|false> \
 //     --authority <AUTHORITY_KEYPAIR>
 
 // Register a new storage node
 // solad register-node \
 //     --stake-amount <STAKE_AMOUNT> \
+//     --http-url <HTTP_URL> \
+//     --multiaddr <MULTIADDR> \
+//     --region <REGION> \
+//     --capacity-mb <CAPACITY_MB> \
+//     --version <NODE_VERSION> \
+//     --owner <NODE_OWNER_KEYPAIR>
+
+// Update the endpoints, region, capacity and version a node advertises
+// solad update-node-metadata \
+//     --http-url <HTTP_URL> \
+//     --multiaddr <MULTIADDR> \
+//     --region <REGION> \
+//     --capacity-mb <CAPACITY_MB> \
+//     --version <NODE_VERSION> \
+//     --owner <NODE_OWNER_KEYPAIR>
+
+// Add stake to a registered node
+// solad add-stake \
+//     --amount <AMOUNT> \
 //     --owner <NODE_OWNER_KEYPAIR>
 
-// Deregister a node
+// Start unbonding part of a node's stake (withdrawable after unbonding_epochs)
+// solad request-unstake \
+//     --amount <AMOUNT> \
+//     --owner <NODE_OWNER_KEYPAIR>
+
+// Withdraw stake whose unbonding period has ended
+// solad withdraw-unstaked \
+//     --owner <NODE_OWNER_KEYPAIR>
+
+// Set the operator commission on delegator rewards
+// solad set-commission \
+//     --commission-percent <COMMISSION_PERCENT> \
+//     --owner <NODE_OWNER_KEYPAIR>
+
+// Delegate stake to a node
+// solad delegate \
+//     --node <NODE_PUBKEY> \
+//     --amount <AMOUNT> \
+//     --delegator <DELEGATOR_KEYPAIR>
+
+// Start undelegating shares (withdrawable after unbonding_epochs)
+// solad undelegate \
+//     --node <NODE_PUBKEY> \
+//     --shares <SHARES> \
+//     --delegator <DELEGATOR_KEYPAIR>
+
+// Claim delegator rewards and any undelegated stake whose unbonding has ended
+// solad claim-delegator-rewards \
+//     --node <NODE_PUBKEY> \
+//     --delegator <DELEGATOR_KEYPAIR>
+
+// Deregister a node (after all stake has been unstaked and withdrawn)
 // solad deregister-node \
 //     --owner <NODE_OWNER_KEYPAIR>
 
-// Upload data with sharding
+// Upload data with sharding. Pass --payment-mint to pay in a listed SPL token from the
+// payer's associated token account instead of lamports.
 // solad upload \
 //     --data-hash <DATA_HASH> \
 //     --size-bytes <SIZE_BYTES> \
 //     --shard-count <SHARD_COUNT> \
+//     --replication-factor <NODES_PER_SHARD> \
 //     --storage-duration-days <DURATION> \
+//     --merkle-root <MERKLE_ROOT> \
+//     --chunk-size <CHUNK_SIZE_BYTES> \
+//     --leaf-count <LEAF_COUNT> \
+//     [--hash-algo <sha256|blake3|keccak256>] \
+//     [--data-shards <K> --parity-shards <M> --fragment-chunk-size <BYTES> \
+//         --fragment-roots <ROOT,...>] \
+//     [--payment-mint <MINT_PUBKEY>] \
+//     [--sponsor <SPONSOR_KEYPAIR>] \
+//     --payer <PAYER_KEYPAIR>
+
+// Assign an upload's shards to nodes once its selection slot has passed (anyone may crank)
+// solad assign-upload \
+//     --data-hash <DATA_HASH> \
+//     --uploader <UPLOADER_PUBKEY>
+
+// Extend or renew the storage duration of an upload (owner or delegate)
+// solad extend-upload \
+//     --data-hash <DATA_HASH> \
+//     --uploader <UPLOADER_PUBKEY> \
+//     --additional-days <ADDITIONAL_DAYS> \
 //     --payer <PAYER_KEYPAIR>
 
-// Slash a user for invalid data size
+// Slash a user for invalid data size, once the shard's dispute window has passed
 // solad slash-user \
 //     --data-hash <DATA_HASH> \
 //     --shard-id <SHARD_ID> \
 //     --node <NODE_KEYPAIR>
 
+// Dispute the oversized reports on an invalidated shard against its committed chunk count,
+// slashing the nodes whose reports the commitment disproves
+// solad dispute-oversized-report \
+//     --data-hash <DATA_HASH> \
+//     --uploader <UPLOADER_PUBKEY> \
+//     --shard-id <SHARD_ID> \
+//     --disputer <DISPUTER_KEYPAIR>
+
+// Issue a Proof of Storage challenge for a shard in the current epoch
+// solad issue-challenge \
+//     --data-hash <DATA_HASH> \
+//     --uploader <UPLOADER_PUBKEY> \
+//     --shard-id <SHARD_ID> \
+//     --epoch <CURRENT_EPOCH> \
+//     --caller <CALLER_KEYPAIR>
+
+// Draw a PoS challenge's leaves once its seed slot has passed
+// solad reveal-challenge \
+//     --challenge <CHALLENGE_PUBKEY>
+
+// Close a PoS challenge no longer usable as inactivity evidence; rent goes to its issuer
+// solad close-challenge \
+//     --challenge <CHALLENGE_PUBKEY>
+
 // Submit Proof of Storage (PoS)
 // solad submit-pos \
 //     --submissions <SUBMISSIONS_JSON> \
+//     --challenge <CHALLENGE_PUBKEY> \
 //     --node <NODE_KEYPAIR>
 //
 // Note: <SUBMISSIONS_JSON> is a JSON array of objects, each containing:
-//       {"data_hash": <DATA_HASH>, "shard_id": <SHARD_ID>, "merkle_root": <MERKLE_ROOT>,
-//        "merkle_proof": <MERKLE_PROOF>, "leaf": <LEAF_HASH>,
-//        "challenger_signature": <CHALLENGER_SIGNATURE>, "challenger_pubkey": <CHALLENGER_PUBKEY>}
+//       {"data_hash": <DATA_HASH>, "shard_id": <SHARD_ID>,
+//        "proofs": [{"leaf_index": <LEAF_INDEX>, "leaf": <LEAF_HASH>, "merkle_proof": <MERKLE_PROOF>}],
+//        "challenger_pubkey": <CHALLENGER_OWNER_PUBKEY>}
+//       The challenger's Ed25519 signature over the PoS attestation message is sent as an
+//       Ed25519 program instruction ahead of submit-pos.
+
+// Submit a batch of PoS responses (up to max_submissions) in one transaction
+// solad submit-pos-batch \
+//     --submissions <SUBMISSIONS_JSON> \
+//     --node <NODE_KEYPAIR>
+//
+// Note: <SUBMISSIONS_JSON> uses the submit-pos format, plus "uploader" and "epoch" per item
+//       so the upload and challenge accounts can be derived. Oversized reports are not batched.
 
 // Claim storage rewards
 // solad claim-rewards \
@@ -254,11 +462,27 @@ pub mod contract {
 // Note: <SHARD_REPLACEMENTS_JSON> is a JSON array of objects, each containing:
 //       {"data_hash": <DATA_HASH>, "shard_id": <SHARD_ID>}
 
-// Close an upload for a specific shard
+// Close an upload, paying nodes what they accrued and refunding the unearned escrow to
+// the owner (owner or delegate)
 // solad close-upload \
 //     --data-hash <DATA_HASH> \
-//     --shard-id <SHARD_ID> \
-//     --payer <PAYER_KEYPAIR>
+//     --uploader <UPLOADER_PUBKEY> \
+//     [--sponsor <SPONSOR_PUBKEY>] \
+//     --authority <OWNER_OR_DELEGATE_KEYPAIR>
+
+// Transfer an upload to a new owner
+// solad transfer-upload \
+//     --data-hash <DATA_HASH> \
+//     --uploader <UPLOADER_PUBKEY> \
+//     --new-owner <NEW_OWNER_PUBKEY> \
+//     --owner <OWNER_KEYPAIR>
+
+// Set or remove (with the default pubkey) the delegate of an upload
+// solad set-upload-delegate \
+//     --data-hash <DATA_HASH> \
+//     --uploader <UPLOADER_PUBKEY> \
+//     --delegate <DELEGATE_PUBKEY> \
+//     --owner <OWNER_KEYPAIR>
 
 // Slash a node for replacement timeout
 // solad slash-timeout \
@@ -267,8 +491,19 @@ pub mod contract {
 //     --exiting-node <EXITING_NODE_PUBKEY> \
 //     --caller <CALLER_KEYPAIR>
 
-// Update the storage configuration
+// Slash a node that missed every challenge of a shard for the last inactivity_epochs epochs,
+// replacing it on the shard
+// solad slash-inactive \
+//     --data-hash <DATA_HASH> \
+//     --uploader <UPLOADER_PUBKEY> \
+//     --shard-id <SHARD_ID> \
+//     --node <NODE_PUBKEY> \
+//     --caller <CALLER_KEYPAIR>
+
+// Queue a storage configuration change (applied after the timelock).
+// Every parameter is required; pass the current value to leave it unchanged.
 // solad update-config \
+//     --treasury <TREASURY_PUBKEY> \
 //     --sol-per-gb <LAMPORTS_PER_GB> \
 //     --treasury-fee-percent <TREASURY_FEE_PERCENT> \
 //     --node-fee-percent <NODE_FEE_PERCENT> \
@@ -277,7 +512,49 @@ pub mod contract {
 //     --slash-penalty-percent <SLASH_PENALTY_PERCENT> \
 //     --min-shard-count <MIN_SHARD_COUNT> \
 //     --max-shard-count <MAX_SHARD_COUNT> \
+//     --min-replication-factor <MIN_REPLICATION_FACTOR> \
+//     --max-replication-factor <MAX_REPLICATION_FACTOR> \
 //     --slots-per-epoch <SLOTS_PER_EPOCH> \
 //     --min-node-stake <MIN_NODE_STAKE> \
 //     --replacement-timeout-epochs <REPLACEMENT_TIMEOUT_EPOCHS> \
+//     --inactivity-epochs <INACTIVITY_EPOCHS> \
+//     --min-lamports-per-upload <MIN_LAMPORTS_PER_UPLOAD> \
+//     --user-slash-penalty-percent <USER_SLASH_PENALTY_PERCENT> \
+//     --reporting-window <REPORTING_WINDOW_EPOCHS> \
+//     --max-user-uploads <MAX_USER_UPLOADS> \
+//     --oversized-report-threshold <OVERSIZED_REPORT_THRESHOLD_PERCENT> \
+//     --dispute-window-epochs <DISPUTE_WINDOW_EPOCHS> \
+//     --max-submissions <MAX_SUBMISSIONS> \
+//     --config-timelock-epochs <CONFIG_TIMELOCK_EPOCHS> \
+//     --unbonding-epochs <UNBONDING_EPOCHS> \
+//     --usd-micros-per-gb <USD_MICROS_PER_GB> \
+//     --price-feed <PRICE_FEED_PUBKEY> \
+//     --max-price-age-secs <MAX_PRICE_AGE_SECS> \
+//     --max-price-confidence-bps <MAX_PRICE_CONFIDENCE_BPS> \
+//     --price-fallback-to-sol <true|false> \
+//     --authority <AUTHORITY_KEYPAIR>
+
+// Apply a queued configuration change once its activation epoch is reached
+// solad apply-config-change \
+//     --caller <CALLER_KEYPAIR>
+
+// Cancel a queued configuration change
+// solad cancel-config-change \
+//     --authority <AUTHORITY_KEYPAIR>
+
+// List, reprice or delist an SPL token mint for storage payments.
+// Repricing an already listed mint takes effect after the config timelock.
+// solad set-mint-pricing \
+//     --mint <MINT_PUBKEY> \
+//     --price-per-gb <BASE_UNITS_PER_GB> \
+//     --enabled <true|false> \
+//     --authority <AUTHORITY_KEYPAIR>
+
+// Propose a new config authority (step one of the handover)
+// solad propose-authority \
+//     --new-authority <NEW_AUTHORITY_PUBKEY> \
 //     --authority <AUTHORITY_KEYPAIR>
+
+// Accept the config authority (step two of the handover)
+// solad accept-authority \
+//     --pending-authority <PENDING_AUTHORITY_KEYPAIR>
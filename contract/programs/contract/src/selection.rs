@@ -0,0 +1,358 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use sha2::{Digest, Sha256};
+
+use crate::errors::SoladError;
+
+// Stake-weighted node selection shared by `assign_upload` and `request_replacement`.
+// Candidates come from a window of the node registry the program fixes, never from a list
+// the caller picks. An upload's window and draw are seeded from the hash of a slot after
+// the upload was paid for (see `SELECTION_SEED_DELAY`), so the payer commits to the upload
+// before the hash that places it exists and cannot grind payer keys for a draw it likes.
+// Replacement windows, and the pick within them, derive from the upload's stored
+// `selection_seed`, so no choice of when to call moves them.
+
+const SELECTION_DOMAIN: &[u8] = b"solad:node_selection";
+const UPLOAD_WINDOW_DOMAIN: &[u8] = b"solad:upload_window";
+const REPLACEMENT_WINDOW_DOMAIN: &[u8] = b"solad:replacement_window";
+
+// Reads the hash that draws a challenge's leaves, or an upload's nodes: that of the oldest
+// listed slot at or after `seed_slot`, so the draw is the same whenever it is revealed.
+// Fails until `seed_slot` has passed, and once it has aged out of the sysvar.
+pub fn challenge_seed(slot_hashes_info: &AccountInfo, seed_slot: u64) -> Result<[u8; 32]> {
+    require_keys_eq!(
+        slot_hashes_info.key(),
+        slot_hashes::ID,
+        SoladError::InvalidSlotHashes
+    );
+    let data = slot_hashes_info.try_borrow_data()?;
+    challenge_seed_from(&data, seed_slot)
+}
+
+// Whether `seed_slot` has aged out of the sysvar, so its draw can never be revealed.
+pub fn challenge_seed_expired(slot_hashes_info: &AccountInfo, seed_slot: u64) -> Result<bool> {
+    require_keys_eq!(
+        slot_hashes_info.key(),
+        slot_hashes::ID,
+        SoladError::InvalidSlotHashes
+    );
+    let data = slot_hashes_info.try_borrow_data()?;
+    let oldest = slot_hash_entries(&data)?
+        .last()
+        .ok_or(SoladError::InvalidSlotHashes)?;
+    Ok(entry_slot(oldest) > seed_slot)
+}
+
+// Same as `challenge_seed`, over raw SlotHashes data, so clients can derive the draw too.
+pub fn challenge_seed_from(data: &[u8], seed_slot: u64) -> Result<[u8; 32]> {
+    let mut entries = slot_hash_entries(data)?;
+    let newest = entries
+        .clone()
+        .next()
+        .ok_or(SoladError::InvalidSlotHashes)?;
+    require!(
+        entry_slot(newest) >= seed_slot,
+        SoladError::ChallengeSeedPending
+    );
+    let oldest = entries
+        .clone()
+        .last()
+        .ok_or(SoladError::InvalidSlotHashes)?;
+    require!(
+        entry_slot(oldest) <= seed_slot,
+        SoladError::ChallengeSeedExpired
+    );
+    let entry = entries
+        .rfind(|entry| entry_slot(entry) >= seed_slot)
+        .ok_or(SoladError::InvalidSlotHashes)?;
+    Ok(entry_hash(entry))
+}
+
+// Validates raw SlotHashes data and returns its (slot, hash) entries, newest first.
+fn slot_hash_entries(data: &[u8]) -> Result<std::slice::ChunksExact<'_, u8>> {
+    require!(data.len() >= 8, SoladError::InvalidSlotHashes);
+    let entry_count = u64::from_le_bytes(
+        data[..8]
+            .try_into()
+            .map_err(|_| SoladError::InvalidSlotHashes)?,
+    ) as usize;
+    require!(entry_count > 0, SoladError::InvalidSlotHashes);
+    require!(
+        data.len() >= 8 + entry_count * 40,
+        SoladError::InvalidSlotHashes
+    );
+    Ok(data[8..8 + entry_count * 40].chunks_exact(40))
+}
+
+fn entry_slot(entry: &[u8]) -> u64 {
+    let mut slot_bytes = [0u8; 8];
+    slot_bytes.copy_from_slice(&entry[..8]);
+    u64::from_le_bytes(slot_bytes)
+}
+
+fn entry_hash(entry: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&entry[8..]);
+    hash
+}
+
+// Hashes `entropy` with domain-separated, length-prefixed `parts` into a 32-byte seed.
+pub fn derive_seed(entropy: &[u8; 32], parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(SELECTION_DOMAIN);
+    hasher.update(entropy);
+    for part in parts {
+        // Length-prefix each part so ("ab", "c") and ("a", "bc") seed differently
+        hasher.update((part.len() as u32).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+// Selection seed of `upload`, drawn from the hash of its selection slot. Stored on the
+// upload, it fixes both the candidate window and the draw within it.
+pub fn upload_seed(slot_entropy: &[u8; 32], upload: &Pubkey) -> [u8; 32] {
+    derive_seed(slot_entropy, &[UPLOAD_WINDOW_DOMAIN, upload.as_ref()])
+}
+
+// Locates the registry window a seed selects: returns (page index, start offset seed). The
+// caller reduces the start seed modulo the page length and takes consecutive entries from
+// there, wrapping around.
+pub fn registry_window(seed: &[u8; 32], page_count: u32) -> (u32, u64) {
+    let mut page_bytes = [0u8; 8];
+    page_bytes.copy_from_slice(&seed[..8]);
+    let mut start_bytes = [0u8; 8];
+    start_bytes.copy_from_slice(&seed[8..16]);
+    let page = (u64::from_le_bytes(page_bytes) % page_count.max(1) as u64) as u32;
+    (page, u64::from_le_bytes(start_bytes))
+}
+
+// Xorshift generator seeded from a slot hash and domain-separated inputs, so each
+// shard (or replacement) draws from an independent stream.
+pub struct SelectionRng {
+    state: u64,
+}
+
+impl SelectionRng {
+    pub fn new(entropy: &[u8; 32], parts: &[&[u8]]) -> Self {
+        let digest = derive_seed(entropy, parts);
+        let mut state_bytes = [0u8; 8];
+        state_bytes.copy_from_slice(&digest[..8]);
+        let state = u64::from_le_bytes(state_bytes);
+        // Xorshift is stuck at zero forever, so never start there
+        Self {
+            state: if state == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                state
+            },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+// Seed of the `attempt`th window a successor to `exiting_node` on (`data_hash`, `shard_id`)
+// is drawn from. It derives from the upload's `selection_seed`, fixed at upload time, so
+// neither the exiting node nor a cranker can move it by choosing when to call. Pass it to
+// `registry_window` for the window, and to `SelectionRng` for the pick within it.
+pub fn replacement_seed(
+    selection_seed: &[u8; 32],
+    data_hash: &str,
+    shard_id: u8,
+    exiting_node: &Pubkey,
+    attempt: u8,
+) -> [u8; 32] {
+    derive_seed(
+        selection_seed,
+        &[
+            REPLACEMENT_WINDOW_DOMAIN,
+            data_hash.as_bytes(),
+            &[shard_id],
+            exiting_node.as_ref(),
+            &[attempt],
+        ],
+    )
+}
+
+// Picks up to `count` distinct candidates, weighted by stake, without replacement.
+// If every remaining candidate has zero stake, they are taken in order.
+pub fn select_weighted(
+    candidates: &[(Pubkey, u64)],
+    count: usize,
+    rng: &mut SelectionRng,
+) -> Result<Vec<Pubkey>> {
+    let mut remaining = candidates.to_vec();
+    let mut selected = Vec::with_capacity(count.min(remaining.len()));
+
+    while selected.len() < count && !remaining.is_empty() {
+        let mut total_stake = 0u64;
+        for (_, stake) in remaining.iter() {
+            total_stake = total_stake
+                .checked_add(*stake)
+                .ok_or(SoladError::MathOverflow)?;
+        }
+        if total_stake == 0 {
+            let (key, _) = remaining.remove(0);
+            selected.push(key);
+            continue;
+        }
+
+        let target = rng.next_u64() % total_stake;
+        let mut cumulative = 0u64;
+        let mut selected_index = 0;
+        for (j, (_, stake)) in remaining.iter().enumerate() {
+            cumulative = cumulative
+                .checked_add(*stake)
+                .ok_or(SoladError::MathOverflow)?;
+            if target < cumulative {
+                selected_index = j;
+                break;
+            }
+        }
+
+        let (key, _) = remaining.remove(selected_index);
+        selected.push(key);
+    }
+
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot_hashes_data(entries: &[(u64, [u8; 32])]) -> Vec<u8> {
+        let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+        for (slot, hash) in entries {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(hash);
+        }
+        data
+    }
+
+    fn candidates(stakes: &[u64]) -> Vec<(Pubkey, u64)> {
+        stakes.iter().map(|&s| (Pubkey::new_unique(), s)).collect()
+    }
+
+    #[test]
+    fn challenge_seed_is_the_first_hash_from_seed_slot() {
+        // Slot 11 was skipped, so a seed slot of 11 draws from slot 12
+        let data = slot_hashes_data(&[(13, [3u8; 32]), (12, [2u8; 32]), (10, [1u8; 32])]);
+
+        assert_eq!(challenge_seed_from(&data, 10).unwrap(), [1u8; 32]);
+        assert_eq!(challenge_seed_from(&data, 11).unwrap(), [2u8; 32]);
+        assert_eq!(challenge_seed_from(&data, 13).unwrap(), [3u8; 32]);
+    }
+
+    #[test]
+    fn challenge_seed_waits_for_seed_slot_and_expires() {
+        let data = slot_hashes_data(&[(13, [3u8; 32]), (12, [2u8; 32])]);
+
+        assert!(challenge_seed_from(&data, 14).is_err());
+        assert!(challenge_seed_from(&data, 11).is_err());
+    }
+
+    #[test]
+    fn upload_window_depends_on_slot_hash_and_upload() {
+        let upload = Pubkey::new_unique();
+        let a = upload_seed(&[1u8; 32], &upload);
+
+        assert_eq!(a, upload_seed(&[1u8; 32], &upload));
+        assert_ne!(a, upload_seed(&[2u8; 32], &upload));
+        assert_ne!(a, upload_seed(&[1u8; 32], &Pubkey::new_unique()));
+        for entropy in 0..32u8 {
+            let (page, _) = registry_window(&upload_seed(&[entropy; 32], &upload), 3);
+            assert!(page < 3);
+        }
+    }
+
+    #[test]
+    fn seed_depends_on_entropy_and_parts() {
+        let a = SelectionRng::new(&[1u8; 32], &[b"hash", &[0]]).next_u64();
+        let b = SelectionRng::new(&[2u8; 32], &[b"hash", &[0]]).next_u64();
+        let c = SelectionRng::new(&[1u8; 32], &[b"hash", &[1]]).next_u64();
+        let d = SelectionRng::new(&[1u8; 32], &[b"has", b"h", &[0]]).next_u64();
+        let again = SelectionRng::new(&[1u8; 32], &[b"hash", &[0]]).next_u64();
+
+        assert_eq!(a, again);
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+    }
+
+    #[test]
+    fn selects_distinct_nodes_up_to_count() {
+        let nodes = candidates(&[10, 20, 30, 40]);
+        let mut rng = SelectionRng::new(&[3u8; 32], &[]);
+        let selected = select_weighted(&nodes, 3, &mut rng).unwrap();
+
+        assert_eq!(selected.len(), 3);
+        for (i, key) in selected.iter().enumerate() {
+            assert!(nodes.iter().any(|(k, _)| k == key));
+            assert!(!selected[i + 1..].contains(key));
+        }
+    }
+
+    #[test]
+    fn selects_at_most_available_candidates() {
+        let nodes = candidates(&[10, 20]);
+        let mut rng = SelectionRng::new(&[3u8; 32], &[]);
+
+        assert_eq!(select_weighted(&nodes, 3, &mut rng).unwrap().len(), 2);
+        assert!(select_weighted(&[], 3, &mut rng).unwrap().is_empty());
+    }
+
+    #[test]
+    fn zero_stake_candidates_are_taken_in_order() {
+        let nodes = candidates(&[0, 0, 0]);
+        let mut rng = SelectionRng::new(&[3u8; 32], &[]);
+        let selected = select_weighted(&nodes, 2, &mut rng).unwrap();
+
+        assert_eq!(selected, vec![nodes[0].0, nodes[1].0]);
+    }
+
+    #[test]
+    fn selection_is_weighted_by_stake() {
+        let nodes = candidates(&[1, 99]);
+        let mut heavy_picks = 0;
+        for i in 0..1000u32 {
+            let mut rng = SelectionRng::new(&[5u8; 32], &[&i.to_le_bytes()]);
+            if select_weighted(&nodes, 1, &mut rng).unwrap()[0] == nodes[1].0 {
+                heavy_picks += 1;
+            }
+        }
+
+        assert!(heavy_picks > 900, "heavy node picked {} times", heavy_picks);
+    }
+
+    #[test]
+    fn replacement_windows_stay_within_pages() {
+        let exiting = Pubkey::new_unique();
+        for attempt in 0..32 {
+            let seed = replacement_seed(&[3u8; 32], "hash", 0, &exiting, attempt);
+            assert!(registry_window(&seed, 3).0 < 3);
+            assert_eq!(registry_window(&seed, 0).0, 0);
+        }
+    }
+
+    #[test]
+    fn replacement_seed_depends_on_upload_shard_and_attempt() {
+        let exiting = Pubkey::new_unique();
+        let a = replacement_seed(&[3u8; 32], "hash", 0, &exiting, 0);
+
+        assert_eq!(a, replacement_seed(&[3u8; 32], "hash", 0, &exiting, 0));
+        assert_ne!(a, replacement_seed(&[4u8; 32], "hash", 0, &exiting, 0));
+        assert_ne!(a, replacement_seed(&[3u8; 32], "hash", 1, &exiting, 0));
+        assert_ne!(a, replacement_seed(&[3u8; 32], "hash", 0, &exiting, 1));
+        assert_ne!(
+            a,
+            replacement_seed(&[3u8; 32], "hash", 0, &Pubkey::new_unique(), 0)
+        );
+    }
+}
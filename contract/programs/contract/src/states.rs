@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::SoladError;
+
 // PDA Seeds
 pub const STORAGE_CONFIG_SEED: &[u8] = b"storage_config";
 pub const UPLOAD_SEED: &[u8] = b"upload";
@@ -8,9 +10,59 @@ pub const NODE_SEED: &[u8] = b"node";
 pub const ESCROW_SEED: &[u8] = b"escrow";
 pub const STAKE_ESCROW_SEED: &[u8] = b"stake_escrow";
 pub const REPLACEMENT_SEED: &[u8] = b"replacement";
+pub const PENDING_CONFIG_SEED: &[u8] = b"pending_config";
+pub const CHALLENGE_SEED: &[u8] = b"challenge";
+pub const SHARD_CLAIM_SEED: &[u8] = b"shard_claim";
+pub const DELEGATION_SEED: &[u8] = b"delegation";
+pub const NODE_METADATA_SEED: &[u8] = b"node_metadata";
+pub const NODE_REGISTRY_SEED: &[u8] = b"node_registry";
+pub const REGISTRY_PAGE_SEED: &[u8] = b"registry_page";
+pub const MINT_PRICING_SEED: &[u8] = b"mint_pricing";
+
+pub const MAX_SHARD_COUNT: u8 = 15;
+pub const MAX_STORAGE_DURATION_DAYS: u64 = 365 * 2000;
+// Number of Merkle leaves a node must prove for each `Challenge`.
+pub const CHALLENGE_LEAF_COUNT: usize = 2;
+// Slots between issuing a challenge and the slot whose hash draws its leaves. Spans two
+// leader rotations, so the leader that lands the issue cannot also produce the seed slot.
+pub const CHALLENGE_SEED_DELAY: u64 = 8;
+// Fraction of an epoch a challenge must leave its nodes to respond once revealed. A
+// challenge is only issued if its seed slot is at least this far from the deadline.
+pub const CHALLENGE_RESPONSE_DIVISOR: u64 = 4;
+// Slots between paying for an upload and the slot whose hash draws its nodes, for the same
+// reason: the payer cannot know the draw before it is committed to the upload.
+pub const SELECTION_SEED_DELAY: u64 = 8;
+// Upper bound on committed chunks per upload. Caps the tree depth at 7 so the proofs
+// for every challenged leaf still fit in a single `submit_pos` transaction.
+pub const MAX_LEAF_COUNT: u32 = 128;
+// Bounds on `NodeMetadata` strings. The account is allocated at its maximum size so
+// `update_node_metadata` never needs to realloc.
+pub const MAX_ENDPOINT_LEN: usize = 128;
+pub const MAX_REGION_LEN: usize = 16;
+pub const MAX_VERSION_LEN: usize = 32;
+// Nodes per `RegistryPage`. Pages are allocated at full size, 33 bytes per entry, which
+// keeps them under the 10 KiB limit for accounts created by the program.
+pub const REGISTRY_PAGE_CAPACITY: usize = 256;
+// Upper bound on the candidate window `request_replacement` draws a successor from.
+pub const MAX_REPLACEMENT_CANDIDATES: usize = 8;
+// Candidate windows a successor is searched in, in order, before the search gives up.
+pub const MAX_REPLACEMENT_WINDOWS: u8 = 4;
+// Upper bound on the candidate window `assign_upload` assigns shards from.
+pub const MAX_UPLOAD_CANDIDATES: usize = 16;
+// Upper bound on nodes per shard; the `ShardInfo` release bitmaps keep one bit per node.
+pub const MAX_REPLICATION_FACTOR: u8 = 8;
+// Replicas per shard that `sol_per_gb` and `usd_micros_per_gb` are quoted for. Uploads
+// pay for the replicas they are actually assigned, pro rata to this baseline.
+pub const BASE_REPLICATION_FACTOR: u64 = 3;
+// Upper bound on `inactivity_epochs`; `slash_inactive` takes one challenge account per epoch.
+pub const MAX_INACTIVITY_EPOCHS: u64 = 16;
+// Fixed-point scale for `Node.reward_per_share`.
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
 
 #[account]
 pub struct StorageConfig {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey, // Pubkey::default() when no handover is in progress
     pub treasury: Pubkey,
     pub sol_per_gb: u64,
     pub treasury_fee_percent: u64,
@@ -20,53 +72,850 @@ pub struct StorageConfig {
     pub slash_penalty_percent: u64,
     pub min_shard_count: u8,
     pub max_shard_count: u8,
+    pub min_replication_factor: u8, // Fewest nodes a shard may be assigned
+    pub max_replication_factor: u8, // Most nodes an upload may request per shard
     pub slots_per_epoch: u64,
     pub min_node_stake: u64,
     pub replacement_timeout_epochs: u64,
+    pub inactivity_epochs: u64, // Consecutive missed challenges before `slash_inactive`
     pub min_lamports_per_upload: u64,
     pub user_slash_penalty_percent: u64,
     pub reporting_window: u64,
     pub max_user_uploads: u64,
     pub oversized_report_threshold: f64,
+    pub dispute_window_epochs: u64, // Epochs an invalidated shard's reports can be disputed
     pub max_submssions: u64,
+    pub config_timelock_epochs: u64,
+    pub unbonding_epochs: u64,
+    pub usd_micros_per_gb: u64, // USD per GB in millionths; 0 prices from `sol_per_gb`
+    pub price_feed: Pubkey,     // SOL/USD feed in the `PriceFeed` layout
+    pub max_price_age_secs: u64,
+    pub max_price_confidence_bps: u64, // Widest confidence interval, in bps of the price
+    pub price_fallback_to_sol: bool,   // Charge `sol_per_gb` on a stale or uncertain feed
     pub is_initialized: bool,
 }
 
+// Every tunable `StorageConfig` parameter. `initialize` and `update_config` both take
+// this struct so the two paths share one set of bounds checks in `validate`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ConfigParams {
+    pub treasury: Pubkey,
+    pub sol_per_gb: u64,
+    pub treasury_fee_percent: u64,
+    pub node_fee_percent: u64,
+    pub shard_min_mb: u64,
+    pub epochs_total: u64,
+    pub slash_penalty_percent: u64,
+    pub min_shard_count: u8,
+    pub max_shard_count: u8,
+    pub min_replication_factor: u8,
+    pub max_replication_factor: u8,
+    pub slots_per_epoch: u64,
+    pub min_node_stake: u64,
+    pub replacement_timeout_epochs: u64,
+    pub inactivity_epochs: u64,
+    pub min_lamports_per_upload: u64,
+    pub user_slash_penalty_percent: u64,
+    pub reporting_window: u64,
+    pub max_user_uploads: u64,
+    pub oversized_report_threshold: f64,
+    pub dispute_window_epochs: u64,
+    pub max_submssions: u64,
+    pub config_timelock_epochs: u64,
+    pub unbonding_epochs: u64,
+    pub usd_micros_per_gb: u64,
+    pub price_feed: Pubkey,
+    pub max_price_age_secs: u64,
+    pub max_price_confidence_bps: u64,
+    pub price_fallback_to_sol: bool,
+}
+
+impl ConfigParams {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.treasury != Pubkey::default(),
+            SoladError::InvalidTreasury
+        );
+        require!(self.sol_per_gb > 0, SoladError::InvalidPaymentRate);
+        require!(
+            self.treasury_fee_percent
+                .checked_add(self.node_fee_percent)
+                == Some(100),
+            SoladError::InvalidFeeSplit
+        );
+        require!(self.shard_min_mb > 0, SoladError::InvalidShardSize);
+        require!(
+            self.min_shard_count >= 1 && self.max_shard_count <= MAX_SHARD_COUNT,
+            SoladError::InvalidShardRange
+        );
+        require!(
+            self.min_shard_count <= self.max_shard_count,
+            SoladError::InvalidShardRange
+        );
+        // A PoS is attested by another node on the shard, so every shard needs two
+        require!(
+            self.min_replication_factor >= 2
+                && self.min_replication_factor <= self.max_replication_factor
+                && self.max_replication_factor <= MAX_REPLICATION_FACTOR,
+            SoladError::InvalidReplicationRange
+        );
+        require!(self.epochs_total > 0, SoladError::InvalidEpochs);
+        require!(self.slash_penalty_percent <= 50, SoladError::InvalidPenalty);
+        require!(self.slots_per_epoch > 0, SoladError::InvalidSlotsPerEpoch);
+        require!(self.min_node_stake >= 100_000_000, SoladError::InvalidStake);
+        require!(
+            self.replacement_timeout_epochs > 0,
+            SoladError::InvalidTimeout
+        );
+        require!(
+            (1..=MAX_INACTIVITY_EPOCHS).contains(&self.inactivity_epochs),
+            SoladError::InvalidInactivityWindow
+        );
+        require!(
+            self.min_lamports_per_upload >= 5000,
+            SoladError::InvalidMinFee
+        );
+        require!(
+            self.user_slash_penalty_percent <= 50,
+            SoladError::InvalidUserPenalty
+        );
+        require!(self.reporting_window > 0, SoladError::InvalidReportingWindow);
+        require!(self.max_user_uploads > 0, SoladError::InvalidUploadLimit);
+        require!(
+            self.oversized_report_threshold > 0.0 && self.oversized_report_threshold <= 100.0,
+            SoladError::InvalidReportThreshold
+        );
+        require!(
+            self.dispute_window_epochs > 0,
+            SoladError::InvalidDisputeWindow
+        );
+        require!(self.max_submssions > 0, SoladError::InvalidSubmissionLimit);
+        require!(self.config_timelock_epochs > 0, SoladError::InvalidTimelock);
+        require!(self.unbonding_epochs > 0, SoladError::InvalidUnbondingPeriod);
+        if self.usd_micros_per_gb > 0 {
+            require!(
+                self.price_feed != Pubkey::default()
+                    && self.max_price_age_secs > 0
+                    && (1..=10_000).contains(&self.max_price_confidence_bps),
+                SoladError::InvalidPriceFeedConfig
+            );
+        }
+        Ok(())
+    }
+}
+
+impl StorageConfig {
+    pub fn apply_params(&mut self, params: &ConfigParams) {
+        self.treasury = params.treasury;
+        self.sol_per_gb = params.sol_per_gb;
+        self.treasury_fee_percent = params.treasury_fee_percent;
+        self.node_fee_percent = params.node_fee_percent;
+        self.shard_min_mb = params.shard_min_mb;
+        self.epochs_total = params.epochs_total;
+        self.slash_penalty_percent = params.slash_penalty_percent;
+        self.min_shard_count = params.min_shard_count;
+        self.max_shard_count = params.max_shard_count;
+        self.min_replication_factor = params.min_replication_factor;
+        self.max_replication_factor = params.max_replication_factor;
+        self.slots_per_epoch = params.slots_per_epoch;
+        self.min_node_stake = params.min_node_stake;
+        self.replacement_timeout_epochs = params.replacement_timeout_epochs;
+        self.inactivity_epochs = params.inactivity_epochs;
+        self.min_lamports_per_upload = params.min_lamports_per_upload;
+        self.user_slash_penalty_percent = params.user_slash_penalty_percent;
+        self.reporting_window = params.reporting_window;
+        self.max_user_uploads = params.max_user_uploads;
+        self.oversized_report_threshold = params.oversized_report_threshold;
+        self.dispute_window_epochs = params.dispute_window_epochs;
+        self.max_submssions = params.max_submssions;
+        self.config_timelock_epochs = params.config_timelock_epochs;
+        self.unbonding_epochs = params.unbonding_epochs;
+        self.usd_micros_per_gb = params.usd_micros_per_gb;
+        self.price_feed = params.price_feed;
+        self.max_price_age_secs = params.max_price_age_secs;
+        self.max_price_confidence_bps = params.max_price_confidence_bps;
+        self.price_fallback_to_sol = params.price_fallback_to_sol;
+    }
+
+    // Slots a revealed challenge must leave before its deadline for a miss to count.
+    pub fn challenge_response_slots(&self) -> u64 {
+        (self.slots_per_epoch / CHALLENGE_RESPONSE_DIVISOR).max(1)
+    }
+
+    pub fn params(&self) -> ConfigParams {
+        ConfigParams {
+            treasury: self.treasury,
+            sol_per_gb: self.sol_per_gb,
+            treasury_fee_percent: self.treasury_fee_percent,
+            node_fee_percent: self.node_fee_percent,
+            shard_min_mb: self.shard_min_mb,
+            epochs_total: self.epochs_total,
+            slash_penalty_percent: self.slash_penalty_percent,
+            min_shard_count: self.min_shard_count,
+            max_shard_count: self.max_shard_count,
+            min_replication_factor: self.min_replication_factor,
+            max_replication_factor: self.max_replication_factor,
+            slots_per_epoch: self.slots_per_epoch,
+            min_node_stake: self.min_node_stake,
+            replacement_timeout_epochs: self.replacement_timeout_epochs,
+            inactivity_epochs: self.inactivity_epochs,
+            min_lamports_per_upload: self.min_lamports_per_upload,
+            user_slash_penalty_percent: self.user_slash_penalty_percent,
+            reporting_window: self.reporting_window,
+            max_user_uploads: self.max_user_uploads,
+            oversized_report_threshold: self.oversized_report_threshold,
+            dispute_window_epochs: self.dispute_window_epochs,
+            max_submssions: self.max_submssions,
+            config_timelock_epochs: self.config_timelock_epochs,
+            unbonding_epochs: self.unbonding_epochs,
+            usd_micros_per_gb: self.usd_micros_per_gb,
+            price_feed: self.price_feed,
+            max_price_age_secs: self.max_price_age_secs,
+            max_price_confidence_bps: self.max_price_confidence_bps,
+            price_fallback_to_sol: self.price_fallback_to_sol,
+        }
+    }
+}
+
+// A queued `update_config` change. Only one change can be pending at a time; it becomes
+// applicable once the current epoch reaches `activation_epoch`, giving node operators
+// time to react before pricing, slashing or epoch parameters move.
+#[account]
+pub struct PendingConfigChange {
+    pub proposer: Pubkey,
+    pub queued_epoch: u64,
+    pub activation_epoch: u64,
+    pub params: ConfigParams,
+}
+
 #[account]
 pub struct Node {
     pub owner: Pubkey,
-    pub stake_amount: u64,
-    pub upload_count: u64,
-    pub last_pos_time: i64,
-    pub last_claimed_epoch: u64,
+    pub stake_amount: u64, // Bonded stake; the selection weight in `assign_upload`
+    pub upload_count: u64, // Shard slots held; each is given back once, on a `ShardInfo` bitmap
+    pub last_pos_time: i64, // Unix time of the node's latest verified PoS
     pub is_active: bool,
+    pub unbonding_amount: u64, // Unstaked but still locked (and slashable) in the stake escrow
+    pub unbonding_release_epoch: u64, // Epoch from which `withdraw_unstaked` may release it
+    pub delegated_stake: u64,  // Lamports backing all delegator shares, after slashes
+    pub delegator_shares: u64, // Outstanding delegator shares, including unbonding ones
+    pub unbonding_shares: u64, // Shares being undelegated; no weight and no rewards
+    pub commission_percent: u8, // Operator cut of the rewards earned by delegated stake
+    pub reward_per_share: u128, // Delegator rewards per active share, scaled by REWARD_PRECISION
+    pub capacity_mb: u64,  // Storage the node declares, mirrored from `NodeMetadata`
+    pub committed_mb: u64, // Shard megabytes assigned to the node and not yet released
+    pub registry_page: u32, // Index of the `RegistryPage` listing this node
+    pub pending_commission_percent: u8, // Commission raise queued by `set_commission`
+    pub commission_effective_slot: u64, // Slot from which the queued raise applies
+}
+
+impl Node {
+    // Moves `amount` of bonded stake into the unbonding queue. There is a single queue per
+    // node, so adding to it restarts the window for the whole unbonding balance.
+    pub fn begin_unbonding(&mut self, amount: u64, release_epoch: u64) -> Result<()> {
+        self.stake_amount = self
+            .stake_amount
+            .checked_sub(amount)
+            .ok_or(SoladError::InvalidStake)?;
+        self.unbonding_amount = self
+            .unbonding_amount
+            .checked_add(amount)
+            .ok_or(SoladError::MathOverflow)?;
+        self.unbonding_release_epoch = release_epoch;
+        Ok(())
+    }
+
+    // Slashes `percent` of both bonded and unbonding stake, so unstaking never shields
+    // lamports from a penalty. Returns the total amount slashed.
+    pub fn slash(&mut self, percent: u64) -> Result<u64> {
+        let bonded_slash = self
+            .stake_amount
+            .checked_mul(percent)
+            .ok_or(SoladError::MathOverflow)?
+            / 100;
+        let unbonding_slash = self
+            .unbonding_amount
+            .checked_mul(percent)
+            .ok_or(SoladError::MathOverflow)?
+            / 100;
+        // Delegator shares are untouched, so each one loses the same fraction of its value
+        let delegated_slash = self
+            .delegated_stake
+            .checked_mul(percent)
+            .ok_or(SoladError::MathOverflow)?
+            / 100;
+        self.stake_amount -= bonded_slash;
+        self.unbonding_amount -= unbonding_slash;
+        self.delegated_stake -= delegated_slash;
+        bonded_slash
+            .checked_add(unbonding_slash)
+            .and_then(|total| total.checked_add(delegated_slash))
+            .ok_or(SoladError::MathOverflow.into())
+    }
+
+    // Lamport value of `shares` of this node's delegation pool.
+    pub fn shares_to_lamports(&self, shares: u64) -> Result<u64> {
+        if self.delegator_shares == 0 {
+            return Ok(0);
+        }
+        let lamports = (shares as u128)
+            .checked_mul(self.delegated_stake as u128)
+            .ok_or(SoladError::MathOverflow)?
+            / self.delegator_shares as u128;
+        u64::try_from(lamports).map_err(|_| SoladError::MathOverflow.into())
+    }
+
+    // Shares minted for a new delegation of `lamports` at the pool's current value.
+    pub fn lamports_to_shares(&self, lamports: u64) -> Result<u64> {
+        if self.delegator_shares == 0 || self.delegated_stake == 0 {
+            return Ok(lamports);
+        }
+        let shares = (lamports as u128)
+            .checked_mul(self.delegator_shares as u128)
+            .ok_or(SoladError::MathOverflow)?
+            / self.delegated_stake as u128;
+        u64::try_from(shares).map_err(|_| SoladError::MathOverflow.into())
+    }
+
+    // Delegated lamports that still count towards selection weight and rewards.
+    pub fn active_delegated_stake(&self) -> Result<u64> {
+        self.shares_to_lamports(self.delegator_shares - self.unbonding_shares)
+    }
+
+    // Megabytes the node can still take on; zero once commitments reach the declared capacity.
+    pub fn free_capacity_mb(&self) -> u64 {
+        self.capacity_mb.saturating_sub(self.committed_mb)
+    }
+
+    pub fn commit_capacity(&mut self, size_mb: u64) -> Result<()> {
+        self.committed_mb = self
+            .committed_mb
+            .checked_add(size_mb)
+            .ok_or(SoladError::MathOverflow)?;
+        Ok(())
+    }
+
+    // A shard is released either on close or once its rewards are fully claimed, whichever
+    // comes first, so the second release must not underflow.
+    pub fn release_capacity(&mut self, size_mb: u64) {
+        self.committed_mb = self.committed_mb.saturating_sub(size_mb);
+    }
+
+    // Stake-weighted selection uses the operator's bonded stake plus active delegations.
+    pub fn selection_weight(&self) -> Result<u64> {
+        self.stake_amount
+            .checked_add(self.active_delegated_stake()?)
+            .ok_or(SoladError::MathOverflow.into())
+    }
+
+    // Applies the commission raise queued by `set_commission` once its slot has come.
+    pub fn apply_pending_commission(&mut self, slot: u64) {
+        if self.pending_commission_percent != self.commission_percent
+            && slot >= self.commission_effective_slot
+        {
+            self.commission_percent = self.pending_commission_percent;
+        }
+    }
+
+    // Splits a reward paid to this node between the operator and its delegators, pro rata
+    // to bonded stake versus active delegated stake. The operator's commission is taken out
+    // of the delegators' part, and the rest is credited to `reward_per_share` for
+    // `claim_delegator_rewards`. Returns (delegator amount, commission).
+    pub fn distribute_reward(&mut self, reward: u64) -> Result<(u64, u64)> {
+        let active_shares = self.delegator_shares - self.unbonding_shares;
+        let active_delegated = self.active_delegated_stake()?;
+        let total_weight = self.selection_weight()?;
+        if active_shares == 0 || active_delegated == 0 || total_weight == 0 {
+            return Ok((0, 0));
+        }
+
+        let delegated_part = (reward as u128)
+            .checked_mul(active_delegated as u128)
+            .ok_or(SoladError::MathOverflow)?
+            / total_weight as u128;
+        let commission = delegated_part
+            .checked_mul(self.commission_percent as u128)
+            .ok_or(SoladError::MathOverflow)?
+            / 100;
+        let delegator_amount = delegated_part - commission;
+
+        self.reward_per_share = self
+            .reward_per_share
+            .checked_add(
+                delegator_amount
+                    .checked_mul(REWARD_PRECISION)
+                    .ok_or(SoladError::MathOverflow)?
+                    / active_shares as u128,
+            )
+            .ok_or(SoladError::MathOverflow)?;
+
+        Ok((delegator_amount as u64, commission as u64))
+    }
+}
+
+// One delegator's position in a node's delegation pool.
+// Rewards use the usual accumulator pattern: `reward_debt` is the share of
+// `Node.reward_per_share` already accounted for, and `settle` moves anything newer into
+// `pending_rewards` before the active share count changes.
+#[account]
+pub struct Delegation {
+    pub node: Pubkey,
+    pub delegator: Pubkey,
+    pub shares: u64,           // All shares held, including unbonding ones
+    pub unbonding_shares: u64, // Shares queued by `undelegate`
+    pub unbonding_release_epoch: u64,
+    pub reward_debt: u128,
+    pub pending_rewards: u64,
+}
+
+impl Delegation {
+    pub fn active_shares(&self) -> u64 {
+        self.shares - self.unbonding_shares
+    }
+
+    fn accrued(&self, reward_per_share: u128) -> Result<u128> {
+        (self.active_shares() as u128)
+            .checked_mul(reward_per_share)
+            .map(|total| total / REWARD_PRECISION)
+            .ok_or(SoladError::MathOverflow.into())
+    }
+
+    // Credits rewards earned since the last settlement to `pending_rewards`.
+    pub fn settle(&mut self, reward_per_share: u128) -> Result<()> {
+        let earned = self.accrued(reward_per_share)?.saturating_sub(self.reward_debt);
+        self.pending_rewards = self
+            .pending_rewards
+            .checked_add(u64::try_from(earned).map_err(|_| SoladError::MathOverflow)?)
+            .ok_or(SoladError::MathOverflow)?;
+        Ok(())
+    }
+
+    // Re-anchors `reward_debt` after the active share count changed.
+    pub fn reset_debt(&mut self, reward_per_share: u128) -> Result<()> {
+        self.reward_debt = self.accrued(reward_per_share)?;
+        Ok(())
+    }
+}
+
+// Endpoints and capabilities a node advertises, so clients and peers can find it from chain.
+#[account]
+pub struct NodeMetadata {
+    pub node: Pubkey,
+    pub owner: Pubkey,
+    pub http_url: String,  // Base URL of the node's HTTP API, e.g. https://node.example.com
+    pub multiaddr: String, // libp2p address, e.g. /ip4/1.2.3.4/tcp/4001/p2p/<peer id>
+    pub region: String,    // Free-form region code, e.g. eu-west
+    pub capacity_mb: u64,  // Storage the node offers, in megabytes
+    pub version: String,   // Node software version
+    pub updated_at: i64,
+}
+
+// Metadata supplied to `register_node` and `update_node_metadata`; both paths share the
+// bounds checks in `validate`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NodeMetadataParams {
+    pub http_url: String,
+    pub multiaddr: String,
+    pub region: String,
+    pub capacity_mb: u64,
+    pub version: String,
 }
 
+impl NodeMetadataParams {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            (self.http_url.starts_with("http://") || self.http_url.starts_with("https://"))
+                && self.http_url.len() <= MAX_ENDPOINT_LEN,
+            SoladError::InvalidNodeMetadata
+        );
+        require!(
+            self.multiaddr.starts_with('/') && self.multiaddr.len() <= MAX_ENDPOINT_LEN,
+            SoladError::InvalidNodeMetadata
+        );
+        require!(
+            self.region.len() <= MAX_REGION_LEN,
+            SoladError::InvalidNodeMetadata
+        );
+        require!(
+            self.version.len() <= MAX_VERSION_LEN,
+            SoladError::InvalidNodeMetadata
+        );
+        Ok(())
+    }
+}
+
+impl NodeMetadata {
+    pub const SPACE: usize = 8
+        + 32
+        + 32
+        + (4 + MAX_ENDPOINT_LEN) * 2
+        + (4 + MAX_REGION_LEN)
+        + 8
+        + (4 + MAX_VERSION_LEN)
+        + 8;
+
+    pub fn apply_params(&mut self, params: &NodeMetadataParams, updated_at: i64) {
+        self.http_url = params.http_url.clone();
+        self.multiaddr = params.multiaddr.clone();
+        self.region = params.region.clone();
+        self.capacity_mb = params.capacity_mb;
+        self.version = params.version.clone();
+        self.updated_at = updated_at;
+    }
+}
+
+// Header of the paged node registry. Nodes are listed in `RegistryPage` accounts so the
+// registry can grow to thousands of nodes without any single account or transaction
+// having to hold all of them.
 #[account]
 pub struct NodeRegistry {
-    pub nodes: Vec<Pubkey>, // List of registered node public keys
+    pub node_count: u32,   // Registered nodes across all pages
+    pub active_count: u32, // Registered nodes that can receive new shards
+    pub page_count: u32,   // Pages allocated so far; indexes 0..page_count exist
+    pub open_page: u32,    // Page new registrations are added to
+}
+
+impl NodeRegistry {
+    // Records a registration in `page`, moving `open_page` on to a fresh page once it is full.
+    pub fn add_node(&mut self, page: &mut RegistryPage, node: Pubkey) -> Result<()> {
+        require!(page.index == self.open_page, SoladError::InvalidRegistryPage);
+        require!(
+            page.entries.len() < REGISTRY_PAGE_CAPACITY,
+            SoladError::RegistryPageFull
+        );
+        if page.index == self.page_count {
+            self.page_count += 1;
+        }
+        page.entries.push(RegistryEntry {
+            node,
+            is_active: true,
+        });
+        page.active_count += 1;
+        self.node_count += 1;
+        self.active_count += 1;
+        if page.entries.len() == REGISTRY_PAGE_CAPACITY {
+            self.open_page = self.page_count;
+        }
+        Ok(())
+    }
+
+    pub fn remove_node(&mut self, page: &mut RegistryPage, node: &Pubkey) -> Result<()> {
+        let i = page
+            .entries
+            .iter()
+            .position(|entry| entry.node == *node)
+            .ok_or(SoladError::InvalidRegistryPage)?;
+        if page.entries.swap_remove(i).is_active {
+            page.active_count -= 1;
+            self.active_count -= 1;
+        }
+        self.node_count -= 1;
+        Ok(())
+    }
+
+    // Flags `node` as active again in its page, undoing `deactivate_node`.
+    pub fn activate_node(&mut self, page: &mut RegistryPage, node: &Pubkey) -> Result<()> {
+        let entry = page
+            .entries
+            .iter_mut()
+            .find(|entry| entry.node == *node)
+            .ok_or(SoladError::InvalidRegistryPage)?;
+        if !entry.is_active {
+            entry.is_active = true;
+            page.active_count += 1;
+            self.active_count += 1;
+        }
+        Ok(())
+    }
+
+    // Flags `node` as inactive in its page so the active indexes skip it.
+    pub fn deactivate_node(&mut self, page: &mut RegistryPage, node: &Pubkey) -> Result<()> {
+        let entry = page
+            .entries
+            .iter_mut()
+            .find(|entry| entry.node == *node)
+            .ok_or(SoladError::InvalidRegistryPage)?;
+        if entry.is_active {
+            entry.is_active = false;
+            page.active_count -= 1;
+            self.active_count -= 1;
+        }
+        Ok(())
+    }
+}
+
+// One page of the node registry, at `[REGISTRY_PAGE_SEED, index]`. Removal swaps the last
+// entry into the freed slot, so entry order within a page is not stable.
+#[account]
+pub struct RegistryPage {
+    pub index: u32,
+    pub active_count: u32, // Entries with `is_active` set
+    pub entries: Vec<RegistryEntry>,
+}
+
+impl RegistryPage {
+    pub const SPACE: usize = 8 + 4 + 4 + 4 + REGISTRY_PAGE_CAPACITY * (32 + 1);
+
+    // Up to `max_len` consecutive entries from `start_seed` modulo the page length,
+    // wrapping around.
+    pub fn window(&self, start_seed: u64, max_len: usize) -> Vec<&RegistryEntry> {
+        let len = self.entries.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let start = (start_seed % len as u64) as usize;
+        (0..len.min(max_len))
+            .map(|i| &self.entries[(start + i) % len])
+            .collect()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RegistryEntry {
+    pub node: Pubkey,
+    pub is_active: bool,
+}
+
+// Storage price for uploads paid in an SPL token, at `[MINT_PRICING_SEED, mint]`.
+// Prices are in the mint's base units per GB, charged with the same per-GB-day formula and
+// fee split as `sol_per_gb`. A repricing waits `config_timelock_epochs` like any other
+// config change; until then `price_per_gb` stays in force.
+#[account]
+pub struct MintPricing {
+    pub mint: Pubkey,
+    pub price_per_gb: u64,
+    pub pending_price_per_gb: u64,
+    pub activation_epoch: u64, // Epoch from which `pending_price_per_gb` applies
+    pub is_enabled: bool,
+}
+
+impl MintPricing {
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 1;
+
+    // Price charged for uploads made during `epoch`.
+    pub fn price_at(&self, epoch: u64) -> u64 {
+        if epoch >= self.activation_epoch {
+            self.pending_price_per_gb
+        } else {
+            self.price_per_gb
+        }
+    }
+}
+
+// Chunk Merkle tree committed by the uploader in `upload_data`.
+// PoS proofs are verified only against this root.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct DataCommitment {
+    pub merkle_root: [u8; 32],
+    pub chunk_size: u32, // Bytes per leaf chunk; the last chunk may be shorter
+    pub leaf_count: u32, // Number of chunks, ceil(size_bytes / chunk_size)
+}
+
+impl DataCommitment {
+    // Checks the commitment covers `size_bytes` in at most `MAX_LEAF_COUNT` chunks.
+    pub fn validate(&self, size_bytes: u64) -> Result<()> {
+        require!(self.merkle_root != [0u8; 32], SoladError::InvalidMerkleRoot);
+        require!(
+            self.chunk_size > 0
+                && (1..=MAX_LEAF_COUNT).contains(&self.leaf_count)
+                && size_bytes.div_ceil(self.chunk_size as u64) == self.leaf_count as u64,
+            SoladError::InvalidChunkLayout
+        );
+        Ok(())
+    }
+
+    // Most megabytes the commitment can cover: `leaf_count` full chunks, rounded up.
+    pub fn committed_mb(&self) -> u64 {
+        (self.leaf_count as u64 * self.chunk_size as u64).div_ceil(1024 * 1024)
+    }
+}
+
+// Reed-Solomon layout of an erasure-coded upload. The data is cut into `data_shards`
+// equal fragments, the last one zero-padded, and `parity_shards` parity fragments of the
+// same size are added; any `data_shards` of them rebuild the data. Every fragment is
+// committed with its own chunk Merkle tree over `chunk_size` chunks.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ErasureCoding {
+    pub data_shards: u8,
+    pub parity_shards: u8,
+    pub chunk_size: u32,
+    pub fragment_roots: Vec<[u8; 32]>, // One per fragment, data fragments first
+}
+
+impl ErasureCoding {
+    pub fn fragment_count(&self) -> u8 {
+        self.data_shards.saturating_add(self.parity_shards)
+    }
+
+    // Commitment of each fragment of `size_bytes` of data, in shard order.
+    pub fn fragment_commitments(&self, size_bytes: u64) -> Result<Vec<DataCommitment>> {
+        require!(
+            self.data_shards >= 1
+                && self.parity_shards >= 1
+                && self.fragment_count() <= MAX_SHARD_COUNT
+                && self.fragment_roots.len() == self.fragment_count() as usize,
+            SoladError::InvalidErasureLayout
+        );
+        let fragment_bytes = size_bytes.div_ceil(self.data_shards as u64);
+        let leaf_count = fragment_bytes.div_ceil(self.chunk_size.max(1) as u64);
+        let leaf_count = u32::try_from(leaf_count).map_err(|_| SoladError::InvalidChunkLayout)?;
+        self.fragment_roots
+            .iter()
+            .map(|&merkle_root| {
+                let commitment = DataCommitment {
+                    merkle_root,
+                    chunk_size: self.chunk_size,
+                    leaf_count,
+                };
+                commitment.validate(fragment_bytes)?;
+                Ok(commitment)
+            })
+            .collect()
+    }
+}
+
+// Digest algorithm that produced an upload's `data_hash`. Each one yields a 32-byte
+// digest, hex encoded in `data_hash`; the discriminator is stored as a single byte.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    Blake3,
+    Keccak256,
 }
 
 #[account]
 pub struct Upload {
     pub data_hash: String,
+    pub hash_algo: HashAlgo,
     pub size_bytes: u64,
+    pub merkle_root: [u8; 32],
+    pub chunk_size: u32,
+    pub leaf_count: u32,
     pub shard_count: u8,
-    pub node_lamports: u64,
-    pub payer: Pubkey,
+    pub replication_factor: u8, // Nodes requested per shard; shards may hold fewer
+    pub data_shards: u8,        // Reed-Solomon data fragments; 0 for a replicated upload
+    pub parity_shards: u8,      // Reed-Solomon parity fragments
+    pub node_lamports: u64,     // Node share held in escrow, in units of `payment_mint`
+    pub payer: Pubkey,          // Seeds the upload and escrow PDAs; never changes
+    pub owner: Pubkey,          // Holds the upload and its refunds; starts as the payer
+    pub delegate: Pubkey,       // May extend or close for the owner; Pubkey::default() if none
+    pub sponsor: Pubkey,        // Funded the upload and takes its refunds; default if the payer did
+    pub payment_mint: Pubkey,   // Pubkey::default() for uploads paid in lamports
     pub upload_time: i64,
     pub storage_duration_days: u64,
     pub expiry_time: i64,
+    pub term_start: i64, // `expiry_time` less the paid storage time; lapses move it forward
     pub current_slot: u64,
+    pub selection_slot: u64, // Slot whose hash seeds the node draw in `assign_upload`
+    pub nodes_assigned: bool,
+    pub selection_seed: [u8; 32], // Fixes the candidate window and draw; set on assignment
     pub shards: Vec<ShardInfo>,
 }
 
+impl Upload {
+    // Account refunds are paid to: the sponsor that funded the upload, or else the owner.
+    pub fn refund_recipient(&self) -> Pubkey {
+        if self.sponsor != Pubkey::default() {
+            self.sponsor
+        } else {
+            self.owner
+        }
+    }
+
+    // Whether `key` may extend or close the upload: its owner or the owner's delegate.
+    pub fn can_manage(&self, key: &Pubkey) -> bool {
+        *key == self.owner || (self.delegate != Pubkey::default() && *key == self.delegate)
+    }
+
+    // Reward owed over the whole term to each node holding `shard`: the shard's part of
+    // `node_lamports` by size, split evenly across the replicas it was assigned and paid for.
+    pub fn node_share(&self, shard: &ShardInfo) -> Result<u64> {
+        Ok(self
+            .shard_lamports(shard)?
+            .checked_div(shard.node_keys.len() as u64)
+            .ok_or(SoladError::MathOverflow)?)
+    }
+
+    // Part of `node_lamports` paid for `shard`, by its size against all shards.
+    pub fn shard_lamports(&self, shard: &ShardInfo) -> Result<u64> {
+        let total_mb: u64 = self.shards.iter().map(|s| s.size_mb).sum();
+        let lamports = (self.node_lamports as u128)
+            .checked_mul(shard.size_mb as u128)
+            .ok_or(SoladError::MathOverflow)?
+            .checked_div(total_mb as u128)
+            .ok_or(SoladError::MathOverflow)?;
+        Ok(lamports as u64)
+    }
+
+    // Bytes held across all replica slots: the whole data per slot, or one fragment per
+    // slot for an erasure-coded upload. Storage is priced on this encoded footprint.
+    pub fn stored_bytes(&self) -> Result<u64> {
+        Ok(self
+            .slot_bytes()
+            .checked_mul(self.replica_count())
+            .ok_or(SoladError::MathOverflow)?)
+    }
+
+    // Bytes the upload would hold with every shard at `replication_factor` nodes. Uploads
+    // are paid for this footprint before their nodes are drawn.
+    pub fn requested_bytes(&self) -> Result<u64> {
+        Ok(self
+            .slot_bytes()
+            .checked_mul(self.shard_count as u64 * self.replication_factor as u64)
+            .ok_or(SoladError::MathOverflow)?)
+    }
+
+    fn slot_bytes(&self) -> u64 {
+        if self.data_shards == 0 {
+            self.size_bytes
+        } else {
+            self.size_bytes.div_ceil(self.data_shards as u64)
+        }
+    }
+
+    // Nodes that vouch for `shard`: its own holders, or for a fragment the holders of any
+    // fragment of the upload. A PoS is attested by one of them, and replacements are drawn
+    // from outside the set so no node ever holds two fragments of one upload.
+    pub fn shard_peers(&self, shard: &ShardInfo) -> Vec<Pubkey> {
+        let shards = match shard.role {
+            ShardRole::Replica => std::slice::from_ref(shard),
+            ShardRole::Data | ShardRole::Parity => self.shards.as_slice(),
+        };
+        let mut peers: Vec<Pubkey> = Vec::new();
+        for key in shards.iter().flat_map(|s| s.node_keys.iter()) {
+            if *key != Pubkey::default() && !peers.contains(key) {
+                peers.push(*key);
+            }
+        }
+        peers
+    }
+
+    // Replica slots across all shards, vacated ones included; the upload is priced per slot.
+    pub fn replica_count(&self) -> u64 {
+        self.shards.iter().map(|s| s.node_keys.len() as u64).sum()
+    }
+}
+
 #[account]
 pub struct UserUploadKeys {
-    pub user: Pubkey,         // The user (payer) who owns the uploads
+    pub user: Pubkey,         // The user who owns the uploads
     pub uploads: Vec<Pubkey>, // List of all Upload PDA public keys
 }
 
+impl UserUploadKeys {
+    // Lists `upload` under `user`, who may hold at most `max_uploads`.
+    pub fn add(&mut self, user: Pubkey, upload: Pubkey, max_uploads: u64) -> Result<()> {
+        require!(
+            (self.uploads.len() as u64) < max_uploads,
+            SoladError::TooManyUploads
+        );
+        self.user = user;
+        self.uploads.push(upload);
+        Ok(())
+    }
+}
+
 // Defines the data structure for a single PoS submission in a batch.
 // This struct encapsulates all necessary data for validating a PoS or reporting oversized data
 // for a specific shard, allowing multiple submissions to be processed in a single transaction.
@@ -76,20 +925,26 @@ pub struct PoSSubmission {
     pub data_hash: String,
     /// ID of the shard being verified.
     pub shard_id: u8,
-    /// Optional Merkle root for PoS verification.
-    pub merkle_root: Option<[u8; 32]>,
-    /// Optional Merkle proof path for the leaf.
-    pub merkle_proof: Option<Vec<[u8; 32]>>,
-    /// Optional leaf hash being verified.
-    pub leaf: Option<[u8; 32]>,
-    /// Optional challenger signature for PoS verification.
-    pub challenger_signature: Option<[u8; 64]>,
-    /// Optional public key of the challenger.
+    /// Optional proofs for the leaves picked by the shard's `Challenge`, in challenge order.
+    pub proofs: Option<Vec<ChallengeProof>>,
+    /// Optional owner key of the challenging node; its attestation is checked through a
+    /// preceding Ed25519 program instruction.
     pub challenger_pubkey: Option<Pubkey>,
     /// Optional actual size in MB for oversized data reporting.
     pub actual_size_mb: Option<u64>,
 }
 
+// Merkle proof for a single challenged leaf.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ChallengeProof {
+    /// Index of the leaf in the shard's Merkle tree; must match the challenge.
+    pub leaf_index: u32,
+    /// Leaf hash being verified.
+    pub leaf: [u8; 32],
+    /// Sibling hashes from the leaf up to the root.
+    pub merkle_proof: Vec<[u8; 32]>,
+}
+
 #[account]
 pub struct Escrow {
     pub bump: u8,
@@ -106,6 +961,40 @@ pub struct Replacement {
     pub request_epoch: u64,
 }
 
+// Reward ledger for one node on one shard of an upload.
+// Claims on different shards are independent; `epochs_claimed` counts endowment epochs
+// already paid, so a claim can catch up every proven epoch not paid since the last one.
+#[account]
+pub struct ShardClaim {
+    pub upload: Pubkey,
+    pub shard_id: u8,
+    pub node: Pubkey,
+    pub initial_claimed: bool, // Whether the initial 25% reward has been paid
+    pub epochs_claimed: u64,   // Endowment epochs paid, at most config.epochs_total
+    pub last_claimed_epoch: u64,
+    pub lamports_paid: u64,
+}
+
+// Proof of Storage challenge for one shard in one epoch, issued by `issue_challenge`.
+// Its leaves are drawn by `reveal_challenge` once `seed_slot` has passed; nodes holding the
+// shard must then prove them before `deadline_slot`.
+#[account]
+pub struct Challenge {
+    pub upload: Pubkey,
+    pub shard_id: u8,
+    pub epoch: u64,
+    pub seed_slot: u64, // Slot whose hash draws the leaves; after the issuing slot
+    pub revealed: bool, // Set once `leaf_indices` are drawn
+    pub revealed_slot: u64, // Slot the leaves were drawn in; 0 until revealed
+    pub leaf_indices: [u32; CHALLENGE_LEAF_COUNT],
+    pub deadline_slot: u64,
+    pub issuer: Pubkey,
+    // Nodes holding the shard when the challenge was issued, in `node_keys` order
+    pub assigned: [Pubkey; MAX_REPLICATION_FACTOR as usize],
+    // Nodes that answered; Pubkey::default() for open slots
+    pub responders: [Pubkey; MAX_REPLICATION_FACTOR as usize],
+}
+
 /// Structure defining a shard replacement request with data hash and shard ID.
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct ShardReplacement {
@@ -113,15 +1002,107 @@ pub struct ShardReplacement {
     pub shard_id: u8,
 }
 
+// What a shard holds: a full copy of the data, or one Reed-Solomon fragment of it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum ShardRole {
+    #[default]
+    Replica,
+    Data,
+    Parity,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct ShardInfo {
     pub shard_id: u8,
-    pub node_keys: [Pubkey; 3],
+    pub role: ShardRole,
+    // What PoS proves: the upload's commitment, or the fragment's own
+    pub commitment: DataCommitment,
+    pub node_keys: Vec<Pubkey>, // One per replica; an exited node's slot is Pubkey::default()
     pub verified_count: u8,
     pub size_mb: u64,
     pub challenger: Pubkey,
-    pub oversized_reports: Vec<OversizedReport>,
-    pub rewarded_nodes: Vec<Pubkey>,
+    pub oversized_reports: Vec<OversizedReport>, // At most one per node
+    pub invalidated_epoch: u64, // Epoch the reports reached the threshold; opens the dispute window
+    pub capacity_released: u8,  // Bit i is set once node_keys[i] has released its capacity
+    pub upload_released: u8,    // Bit i is set once node_keys[i] has given back its upload slot
+    pub proven_epochs: Vec<u32>, // Challenges node_keys[i] has answered, at most one per epoch
+    pub user_slashed: bool,     // Set once `slash_user` has paid out the shard's escrow share
+}
+
+impl ShardInfo {
+    // Account space taken by a shard holding up to `replication_factor` nodes.
+    pub fn space(replication_factor: u8) -> usize {
+        let n = replication_factor as usize;
+        1 + 1 + 40 + 4 + 32 * n + 1 + 8 + 32 + 4 + 40 * n + 8 + 1 + 1 + 4 + 4 * n + 1
+    }
+
+    // Nodes currently holding the shard.
+    pub fn node_count(&self) -> usize {
+        self.node_keys
+            .iter()
+            .filter(|&&k| k != Pubkey::default())
+            .count()
+    }
+
+    // Oversized reports needed to invalidate the shard: `threshold_percent` of its
+    // nodes, rounded up, and never fewer than one.
+    pub fn required_reports(&self, threshold_percent: f64) -> u64 {
+        ((self.node_count() as f64 * threshold_percent / 100.0).ceil() as u64).max(1)
+    }
+
+    // Marks `node`'s capacity for this shard as released. Returns false if it already was,
+    // or if the node holds no slot, so each assignment is released at most once.
+    pub fn mark_capacity_released(&mut self, node: &Pubkey) -> bool {
+        mark_released(&self.node_keys, &mut self.capacity_released, node)
+    }
+
+    // Marks `node`'s upload slot for this shard as given back, with the same once-only
+    // guarantee, so `Node.upload_count` drops exactly once per assignment.
+    pub fn mark_upload_released(&mut self, node: &Pubkey) -> bool {
+        mark_released(&self.node_keys, &mut self.upload_released, node)
+    }
+
+    // Counts an answered challenge towards the epochs `node` can claim rewards for.
+    pub fn record_proof(&mut self, node: &Pubkey) -> Result<()> {
+        let i = self
+            .node_keys
+            .iter()
+            .position(|key| key == node)
+            .ok_or(SoladError::Unauthorized)?;
+        self.proven_epochs[i] = self.proven_epochs[i]
+            .checked_add(1)
+            .ok_or(SoladError::MathOverflow)?;
+        Ok(())
+    }
+
+    // Epochs `node` has proven while holding its slot.
+    pub fn proven_epochs_of(&self, node: &Pubkey) -> u64 {
+        self.node_keys
+            .iter()
+            .position(|key| key == node)
+            .map_or(0, |i| self.proven_epochs[i] as u64)
+    }
+
+    // Hands `old`'s slot to `new`, whose capacity and upload slot are held and not yet
+    // released, and which has proven nothing yet.
+    pub fn replace_node(&mut self, old: &Pubkey, new: Pubkey) {
+        if let Some(i) = self.node_keys.iter().position(|key| key == old) {
+            self.node_keys[i] = new;
+            self.capacity_released &= !(1 << i);
+            self.upload_released &= !(1 << i);
+            self.proven_epochs[i] = 0;
+        }
+    }
+}
+
+fn mark_released(node_keys: &[Pubkey], released: &mut u8, node: &Pubkey) -> bool {
+    match node_keys.iter().position(|key| key == node) {
+        Some(i) if *released & (1 << i) == 0 => {
+            *released |= 1 << i;
+            true
+        }
+        _ => false,
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
@@ -0,0 +1,130 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke_signed};
+
+use crate::errors::SoladError;
+
+// Minimal SPL Token support for uploads paid in a token instead of lamports.
+// Only the handful of token program calls the payment paths need are built here, so the
+// program does not pull in a full token client. Token escrows are ordinary token accounts
+// (normally the associated token account) whose owner is the upload's escrow PDA.
+
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+// SPL Token account layout: mint (0..32), owner (32..64), amount (64..72), delegate
+// (72..108), state (108). Accounts are 165 bytes.
+const TOKEN_ACCOUNT_LEN: usize = 165;
+pub const MINT_LEN: usize = 82;
+const STATE_OFFSET: usize = 108;
+const STATE_INITIALIZED: u8 = 1;
+
+// Token program instruction tags.
+const TRANSFER: u8 = 3;
+const CLOSE_ACCOUNT: u8 = 9;
+
+// Unwraps an optional token account and checks it is an initialized token account for
+// `mint` owned by `owner`. Returns the account together with its current balance.
+pub fn checked_token_account<'a, 'info>(
+    account: &'a Option<UncheckedAccount<'info>>,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<(&'a AccountInfo<'info>, u64)> {
+    let info: &AccountInfo<'info> = account.as_ref().ok_or(SoladError::MissingTokenAccount)?;
+    Ok((info, token_balance(info, mint, owner)?))
+}
+
+// Checks `info` is an initialized token account for `mint` owned by `owner` and returns
+// its balance. Used directly for token accounts passed in remaining accounts.
+pub fn token_balance(info: &AccountInfo, mint: &Pubkey, owner: &Pubkey) -> Result<u64> {
+    require_keys_eq!(
+        *info.owner,
+        TOKEN_PROGRAM_ID,
+        SoladError::InvalidTokenAccount
+    );
+    let data = info.try_borrow_data()?;
+    require!(
+        data.len() == TOKEN_ACCOUNT_LEN && data[STATE_OFFSET] == STATE_INITIALIZED,
+        SoladError::InvalidTokenAccount
+    );
+    require!(
+        &data[0..32] == mint.as_ref() && &data[32..64] == owner.as_ref(),
+        SoladError::InvalidTokenAccount
+    );
+    let mut amount = [0u8; 8];
+    amount.copy_from_slice(&data[64..72]);
+    Ok(u64::from_le_bytes(amount))
+}
+
+// Unwraps the optional token program account; its address is checked by the account constraints.
+pub fn token_program<'a, 'info>(
+    account: &'a Option<UncheckedAccount<'info>>,
+) -> Result<&'a AccountInfo<'info>> {
+    Ok(account.as_ref().ok_or(SoladError::MissingTokenAccount)?)
+}
+
+// Moves `amount` tokens from `from` to `to`. `authority` must own `from`; pass the escrow
+// PDA seeds as `signer_seeds` when paying out of a token escrow.
+pub fn transfer<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let mut data = Vec::with_capacity(9);
+    data.push(TRANSFER);
+    data.extend_from_slice(&amount.to_le_bytes());
+    let instruction = Instruction {
+        program_id: TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(from.key(), false),
+            AccountMeta::new(to.key(), false),
+            AccountMeta::new_readonly(authority.key(), true),
+        ],
+        data,
+    };
+    invoke_signed(
+        &instruction,
+        &[
+            from.clone(),
+            to.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+// Closes an empty token account owned by `authority`, returning its rent to `destination`.
+pub fn close_account<'info>(
+    token_program: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let instruction = Instruction {
+        program_id: TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(account.key(), false),
+            AccountMeta::new(destination.key(), false),
+            AccountMeta::new_readonly(authority.key(), true),
+        ],
+        data: vec![CLOSE_ACCOUNT],
+    };
+    invoke_signed(
+        &instruction,
+        &[
+            account.clone(),
+            destination.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
@@ -1,18 +1,159 @@
 pub use anchor_lang::prelude::*;
-use anchor_lang::solana_program::secp256k1_recover::secp256k1_recover;
+use anchor_lang::solana_program::{
+    ed25519_program,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
 use sha2::{Digest as _, Sha256};
 
-use crate::errors::SoladError;
+use crate::{
+    errors::SoladError,
+    states::{StorageConfig, BASE_REPLICATION_FACTOR},
+};
 
-// Utility functions for shard ID generation and cryptographic verification.
+// Utility functions for shard ID generation, pricing, and cryptographic verification.
 
-// Verifies a Merkle proof for a given leaf and root.
-// This function ensures data integrity by confirming the leaf is part of the Merkle tree.
-pub fn verify_merkle_proof(root: &[u8; 32], proof: &[[u8; 32]], leaf: &[u8; 32]) -> Result<()> {
+// Calculates the storage fee for `stored_bytes` held across all nodes for
+// `storage_duration_days`, split into the treasury share and the node share. The price is
+// quoted per GB of data at `BASE_REPLICATION_FACTOR` replicas, so each stored byte pays a
+// third of it: other replication factors and erasure-coded footprints pay pro rata.
+// Shared by `upload_data` and `extend_upload` so both charge the same per-GB-day rate.
+// `price_per_gb` is `config.sol_per_gb` for lamport payments or the mint's price for
+// token payments; the result is in the same units.
+pub fn calculate_storage_fee(
+    config: &StorageConfig,
+    price_per_gb: u64,
+    stored_bytes: u64,
+    storage_duration_days: u64,
+) -> Result<(u64, u64)> {
+    let total_lamports = stored_bytes
+        .checked_mul(price_per_gb)
+        .ok_or(SoladError::MathOverflow)?
+        .checked_div(1024 * 1024 * 1024)
+        .ok_or(SoladError::MathOverflow)?
+        .checked_mul(storage_duration_days)
+        .ok_or(SoladError::MathOverflow)?
+        .checked_div(7300 * BASE_REPLICATION_FACTOR)
+        .ok_or(SoladError::MathOverflow)?;
+    let treasury_lamports = total_lamports
+        .checked_mul(config.treasury_fee_percent)
+        .ok_or(SoladError::MathOverflow)?
+        / 100;
+    let node_lamports = total_lamports
+        .checked_mul(config.node_fee_percent)
+        .ok_or(SoladError::MathOverflow)?
+        / 100;
+    Ok((treasury_lamports, node_lamports))
+}
+
+// SOL/USD price read from `config.price_feed`. The account may be owned by any program
+// (an oracle adapter, or a mock on localnet) and must start with this little-endian layout:
+//   0..8    price         i64  USD per SOL, scaled by 10^expo
+//   8..16   conf          u64  confidence interval, same scale as price
+//   16..20  expo          i32  decimal exponent, e.g. -8
+//   20..28  publish_time  i64  unix timestamp of the observation
+pub struct PriceFeed {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+impl PriceFeed {
+    pub const LEN: usize = 28;
+
+    pub fn read(account: &AccountInfo) -> Result<Self> {
+        let data = account.try_borrow_data()?;
+        let bytes = data.get(..Self::LEN).ok_or(SoladError::InvalidPriceFeed)?;
+        let read_8 = |at: usize| {
+            let mut out = [0u8; 8];
+            out.copy_from_slice(&bytes[at..at + 8]);
+            out
+        };
+        let mut expo = [0u8; 4];
+        expo.copy_from_slice(&bytes[16..20]);
+        Ok(PriceFeed {
+            price: i64::from_le_bytes(read_8(0)),
+            conf: u64::from_le_bytes(read_8(8)),
+            expo: i32::from_le_bytes(expo),
+            publish_time: i64::from_le_bytes(read_8(20)),
+        })
+    }
+}
+
+// Returns the lamport price per GB in force at `now`. With `usd_micros_per_gb` unset this is
+// `sol_per_gb`. Otherwise the USD price is converted at the feed's SOL/USD rate; a feed older
+// than `max_price_age_secs` or with a confidence interval wider than
+// `max_price_confidence_bps` of the price falls back to `sol_per_gb` when
+// `price_fallback_to_sol` is set and fails the instruction otherwise. The feed account must
+// always be passed while USD pricing is on, so callers cannot opt into the fallback rate.
+pub fn lamports_per_gb(
+    config: &StorageConfig,
+    price_feed: Option<&AccountInfo>,
+    now: i64,
+) -> Result<u64> {
+    if config.usd_micros_per_gb == 0 {
+        return Ok(config.sol_per_gb);
+    }
+    let account = price_feed.ok_or(SoladError::InvalidPriceFeed)?;
+    require_keys_eq!(
+        account.key(),
+        config.price_feed,
+        SoladError::InvalidPriceFeed
+    );
+    let feed = PriceFeed::read(account)?;
+    require!(
+        feed.price > 0 && (-18..=18).contains(&feed.expo),
+        SoladError::InvalidPriceFeed
+    );
+    let price = feed.price as u128;
+
+    let age = now.saturating_sub(feed.publish_time);
+    let fresh = age >= 0 && (age as u64) <= config.max_price_age_secs;
+    let confident = (feed.conf as u128) * 10_000 <= price * config.max_price_confidence_bps as u128;
+    if !fresh || !confident {
+        require!(
+            config.price_fallback_to_sol,
+            if fresh {
+                SoladError::PriceConfidenceTooWide
+            } else {
+                SoladError::StalePrice
+            }
+        );
+        return Ok(config.sol_per_gb);
+    }
+
+    // lamports = usd_micros * 10^9 lamports/SOL / (price * 10^(expo + 6) micro-USD/SOL)
+    let scale = feed.expo + 6;
+    let mut numerator = (config.usd_micros_per_gb as u128) * 1_000_000_000;
+    let mut denominator = price;
+    if scale >= 0 {
+        denominator = denominator
+            .checked_mul(10u128.pow(scale as u32))
+            .ok_or(SoladError::MathOverflow)?;
+    } else {
+        numerator = numerator
+            .checked_mul(10u128.pow((-scale) as u32))
+            .ok_or(SoladError::MathOverflow)?;
+    }
+    let lamports = numerator / denominator;
+    require!(lamports > 0, SoladError::InvalidPaymentRate);
+    u64::try_from(lamports).map_err(|_| error!(SoladError::MathOverflow))
+}
+
+// Verifies a Merkle proof for the leaf at `leaf_index` against the given root.
+// Siblings are ordered by position (the index bit at each level decides left or right),
+// so a proof for one leaf cannot be replayed to answer a challenge for another index.
+pub fn verify_merkle_proof(
+    root: &[u8; 32],
+    proof: &[[u8; 32]],
+    leaf: &[u8; 32],
+    leaf_index: u32,
+) -> Result<()> {
     let mut computed_hash = *leaf;
+    let mut index = leaf_index as u64;
     for sibling in proof.iter() {
         let mut hasher = Sha256::new();
-        if computed_hash <= *sibling {
+        if index & 1 == 0 {
             hasher.update(computed_hash);
             hasher.update(sibling);
         } else {
@@ -20,31 +161,85 @@ pub fn verify_merkle_proof(root: &[u8; 32], proof: &[[u8; 32]], leaf: &[u8; 32])
             hasher.update(computed_hash);
         }
         computed_hash = hasher.finalize().into();
+        index >>= 1;
     }
     require!(
-        computed_hash.as_slice() == root.as_slice(),
+        index == 0 && computed_hash.as_slice() == root.as_slice(),
         SoladError::InvalidMerkleProof
     );
     Ok(())
 }
 
-// Verifies an ECDSA signature using the secp256k1 curve.
-// Used to authenticate challengers in the Proof of Storage process.
-pub fn verify_signature(
-    message: &str,
-    signature: &[u8; 64],
+// Domain tag for PoS attestations so the signed bytes cannot be confused with any
+// other message a node key might sign.
+pub const POS_ATTESTATION_DOMAIN: &[u8] = b"solad:pos-attestation:v1";
+
+// Builds the message a challenger signs to attest that `node` answered `challenge`:
+// domain || program id || challenge PDA || responding node PDA || committed Merkle root.
+pub fn pos_attestation_message(
+    challenge: &Pubkey,
+    node: &Pubkey,
+    merkle_root: &[u8; 32],
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(POS_ATTESTATION_DOMAIN.len() + 32 * 4);
+    message.extend_from_slice(POS_ATTESTATION_DOMAIN);
+    message.extend_from_slice(crate::ID.as_ref());
+    message.extend_from_slice(challenge.as_ref());
+    message.extend_from_slice(node.as_ref());
+    message.extend_from_slice(merkle_root);
+    message
+}
+
+// Checks that an Ed25519 native program instruction earlier in this transaction covered a
+// signature by `pubkey` over `message`. The runtime rejects the whole transaction if that
+// signature is invalid, so only the signed contents need matching here.
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
     pubkey: &Pubkey,
-    timestamp: i64,
+    message: &[u8],
 ) -> Result<()> {
-    let full_message = format!("{}:{}", message, timestamp);
-    let message_bytes = Sha256::digest(full_message.as_bytes());
-    let recovered = secp256k1_recover(&message_bytes[..], 0, signature)
-        .map_err(|_| SoladError::InvalidChallengerSignature)?;
-    let recovered_bytes = recovered.to_bytes();
-    let _provided_bytes = pubkey.to_bytes();
-    require!(
-        matches!(recovered_bytes, _provided_bytes),
-        SoladError::InvalidChallengerSignature
-    );
-    Ok(())
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    for index in 0..current_index {
+        let instruction = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if instruction.program_id == ed25519_program::ID
+            && ed25519_instruction_covers(&instruction.data, pubkey, message)
+        {
+            return Ok(());
+        }
+    }
+    err!(SoladError::InvalidChallengerSignature)
+}
+
+// Parses Ed25519 program instruction data: a signature count and padding byte, then one
+// 14-byte offsets entry per signature. Only entries whose key, signature, and message all
+// live in this instruction's own data (index u16::MAX) are accepted.
+fn ed25519_instruction_covers(data: &[u8], pubkey: &Pubkey, message: &[u8]) -> bool {
+    const HEADER_LEN: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    const THIS_INSTRUCTION: u16 = u16::MAX;
+
+    let signature_count = match data.first() {
+        Some(&count) => count as usize,
+        None => return false,
+    };
+    (0..signature_count).any(|i| {
+        let start = HEADER_LEN + i * OFFSETS_LEN;
+        let offsets = match data.get(start..start + OFFSETS_LEN) {
+            Some(offsets) => offsets,
+            None => return false,
+        };
+        let read = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+        let signature_ix = read(2);
+        let pubkey_offset = read(4) as usize;
+        let pubkey_ix = read(6);
+        let message_offset = read(8) as usize;
+        let message_size = read(10) as usize;
+        let message_ix = read(12);
+
+        signature_ix == THIS_INSTRUCTION
+            && pubkey_ix == THIS_INSTRUCTION
+            && message_ix == THIS_INSTRUCTION
+            && data.get(pubkey_offset..pubkey_offset + 32) == Some(pubkey.as_ref())
+            && data.get(message_offset..message_offset + message_size) == Some(message)
+    })
 }
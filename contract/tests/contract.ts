@@ -1,7 +1,7 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Contract } from "../target/types/contract";
-import { Keypair, LAMPORTS_PER_SOL, PublicKey, Signer } from "@solana/web3.js";
+import { Ed25519Program, Keypair, LAMPORTS_PER_SOL, PublicKey, Signer } from "@solana/web3.js";
 import { expect } from "chai";
 import { sha256 } from "@noble/hashes/sha256";
 // import { etc, sign } from "@noble/secp256k1";
@@ -54,6 +54,36 @@ const nodePrivateKey = process.env.NODE_PRIVATE_KEY;
 //   };
 // }
 
+// Chunk Merkle tree matching the program's PoS checks: parents are sha256(left || right),
+// and the last node of an odd-sized level is paired with itself
+function nextMerkleLevel(level: Uint8Array[]): Uint8Array[] {
+  const parents = [];
+  for (let i = 0; i < level.length; i += 2) {
+    parents.push(sha256(Buffer.concat([level[i], level[i + 1] ?? level[i]])));
+  }
+  return parents;
+}
+
+function merkleRoot(leaves: Uint8Array[]): number[] {
+  let level = leaves;
+  while (level.length > 1) {
+    level = nextMerkleLevel(level);
+  }
+  return [...level[0]];
+}
+
+// Sibling path for the leaf at `index`, ordered from the leaf up to the root
+function merkleProof(leaves: Uint8Array[], index: number): number[][] {
+  const proof = [];
+  let level = leaves;
+  while (level.length > 1) {
+    proof.push([...(index % 2 === 0 ? level[index + 1] ?? level[index] : level[index - 1])]);
+    level = nextMerkleLevel(level);
+    index = Math.floor(index / 2);
+  }
+  return proof;
+}
+
 // Function to derive a Solana keypair from a base58-encoded secret key string
 function deriveKeypairFromSecretKey(secretKeyString: string): Keypair {
   try {
@@ -69,6 +99,20 @@ function deriveKeypairFromSecretKey(secretKeyString: string): Keypair {
   }
 }
 
+// Mock SOL/USD price feed loaded by the local validator (see Anchor.toml)
+const MOCK_PRICE_FEED = new PublicKey("DVqMMJjecZxoDE8fB8r8oCMgJ2U7JghqxtjBPoLKPm83");
+
+// Endpoint metadata every test node registers with
+function testNodeMetadata(port: number) {
+  return {
+    httpUrl: `http://127.0.0.1:${port}`,
+    multiaddr: `/ip4/127.0.0.1/tcp/${port + 1000}`,
+    region: "local",
+    capacityMb: new anchor.BN(10240),
+    version: "0.1.0",
+  };
+}
+
 describe("contract", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
@@ -95,6 +139,131 @@ describe("contract", () => {
   let stakeEscrowPda: PublicKey;
   let uploadPda: PublicKey;
   let uploadEscrowPda: PublicKey;
+  // Epoch in which both nodes of "test_batch_pos" answered its challenge
+  let batchChallengeEpoch: number;
+  // Owner keys of the nodes registered so far, for tests that sign as a node
+  const testNodeOwners: Keypair[] = [];
+
+  // The test nodes all fit in registry page 0, so every upload's candidate window is the
+  // whole page and its accounts can be passed in page order
+  const uploadWindow = async () => {
+    const [candidatePage] = PublicKey.findProgramAddressSync(
+      [Buffer.from("registry_page"), Buffer.alloc(4)],
+      program.programId
+    );
+    const { entries } = await program.account.registryPage.fetch(candidatePage);
+    return {
+      candidatePage,
+      nodes: entries.map((entry) => ({ pubkey: entry.node, isWritable: true, isSigner: false })),
+    };
+  };
+
+  // Waits out an upload's selection slot, then draws its nodes. `refundRecipient` takes the
+  // fee of replicas that could not be assigned: the sponsor, or else the owner.
+  const assignUpload = async (dataHash: string, payer: PublicKey, refundRecipient = payer) => {
+    const [upload] = PublicKey.findProgramAddressSync(
+      [Buffer.from("upload"), Buffer.from(dataHash), payer.toBuffer()],
+      program.programId
+    );
+    const [escrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), Buffer.from(dataHash), payer.toBuffer()],
+      program.programId
+    );
+    const { selectionSlot } = await program.account.upload.fetch(upload);
+    await waitForSlot(selectionSlot.toNumber());
+    const window = await uploadWindow();
+    return program.methods
+      .assignUpload(dataHash)
+      .accounts({
+        upload,
+        escrow,
+        config: storageConfigPda,
+        candidatePage: window.candidatePage,
+        treasury,
+        refundRecipient,
+      })
+      .remainingAccounts(window.nodes)
+      .rpc();
+  };
+
+  // Owner key of a test node, looked up by its node PDA
+  const ownerOf = (node: PublicKey) =>
+    testNodeOwners.find((owner) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("node"), owner.publicKey.toBuffer()],
+        program.programId
+      )[0].equals(node)
+    );
+
+  // Polls until the cluster has moved past `slot`
+  const waitForSlot = async (slot: number) => {
+    while ((await program.provider.connection.getSlot()) <= slot) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+  };
+
+  // Waits for the next epoch unless at least half of the current one is left, so a challenge
+  // can be issued and answered, or an epoch-bound check made, before the epoch turns over
+  const waitForEpochRoom = async () => {
+    const { slotsPerEpoch } = await program.account.storageConfig.fetch(storageConfigPda);
+    const epochSlots = slotsPerEpoch.toNumber();
+    const slot = await program.provider.connection.getSlot();
+    const nextEpochStart = (Math.floor(slot / epochSlots) + 1) * epochSlots;
+    if (nextEpochStart - slot <= epochSlots / 2) {
+      await waitForSlot(nextEpochStart - 1);
+    }
+  };
+
+  // The windows `draw_successor` searches for a successor to `exitingNode`, each a registry
+  // page followed by its candidate accounts. Mirrors the program's `replacement_seed`.
+  const replacementWindows = async (
+    uploadPda: PublicKey,
+    shardId: number,
+    exitingNode: PublicKey
+  ) => {
+    const upload = await program.account.upload.fetch(uploadPda);
+    const { pageCount } = await program.account.nodeRegistry.fetch(nodeRegistryPda);
+    const lengthPrefixed = (part: Buffer) => {
+      const length = Buffer.alloc(4);
+      length.writeUInt32LE(part.length);
+      return [length, part];
+    };
+    const accounts = [];
+    for (let attempt = 0; attempt < 4; attempt++) {
+      const seed = Buffer.from(
+        sha256(
+          Buffer.concat([
+            Buffer.from("solad:node_selection"),
+            Buffer.from(upload.selectionSeed),
+            ...[
+              Buffer.from("solad:replacement_window"),
+              Buffer.from(upload.dataHash),
+              Buffer.from([shardId]),
+              exitingNode.toBuffer(),
+              Buffer.from([attempt]),
+            ].flatMap(lengthPrefixed),
+          ])
+        )
+      );
+      const pageBytes = Buffer.alloc(4);
+      pageBytes.writeUInt32LE(Number(seed.readBigUInt64LE(0) % BigInt(Math.max(pageCount, 1))));
+      const [page] = PublicKey.findProgramAddressSync(
+        [Buffer.from("registry_page"), pageBytes],
+        program.programId
+      );
+      const { entries } = await program.account.registryPage.fetch(page);
+      const start = Number(seed.readBigUInt64LE(8) % BigInt(entries.length));
+      accounts.push(
+        { pubkey: page, isWritable: false, isSigner: false },
+        ...Array.from({ length: Math.min(entries.length, 8) }, (_, i) => ({
+          pubkey: entries[(start + i) % entries.length].node,
+          isWritable: true,
+          isSigner: false,
+        }))
+      );
+    }
+    return accounts;
+  };
 
   before(async () => {
     // Derive PDAs
@@ -156,44 +325,41 @@ describe("contract", () => {
 
     if (!configAccountInfo) {
       const sol_per_gb = new anchor.BN(0.03 * LAMPORTS_PER_SOL);
-      const treasury_fee_percent = new anchor.BN(25);
-      const node_fee_percent = new anchor.BN(75);
-      const shard_min_mb = new anchor.BN(1);
-      const epochs_total = new anchor.BN(1);
-      const slash_penalty_percent = new anchor.BN(10);
-      const min_shard_count = 1;
-      const max_shard_count = 10;
-      const slots_per_epoch = new anchor.BN(42000);
-      const min_node_stake = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
-      const replacement_timeout_epochs = new anchor.BN(1);
-      const min_lamports_per_upload = new anchor.BN(0.03 * LAMPORTS_PER_SOL);
-      const max_user_uploads = new anchor.BN(100);
-      const user_slash_penalty_percent = new anchor.BN(10);
-      const reporting_window = new anchor.BN(1);
-      const oversized_report_threshold = 66.6
-      const max_submssions = new anchor.BN(100);
+      const params = {
+        treasury,
+        solPerGb: sol_per_gb,
+        treasuryFeePercent: new anchor.BN(25),
+        nodeFeePercent: new anchor.BN(75),
+        shardMinMb: new anchor.BN(1),
+        epochsTotal: new anchor.BN(1),
+        slashPenaltyPercent: new anchor.BN(10),
+        minShardCount: 1,
+        maxShardCount: 10,
+        minReplicationFactor: 2,
+        maxReplicationFactor: 5,
+        // Short epochs, so challenge deadlines and the inactivity window pass within a test
+        slotsPerEpoch: new anchor.BN(200),
+        minNodeStake: new anchor.BN(0.1 * LAMPORTS_PER_SOL),
+        replacementTimeoutEpochs: new anchor.BN(1),
+        inactivityEpochs: new anchor.BN(1),
+        minLamportsPerUpload: new anchor.BN(0.03 * LAMPORTS_PER_SOL),
+        userSlashPenaltyPercent: new anchor.BN(10),
+        reportingWindow: new anchor.BN(1),
+        maxUserUploads: new anchor.BN(100),
+        oversizedReportThreshold: 66.6,
+        disputeWindowEpochs: new anchor.BN(1),
+        maxSubmssions: new anchor.BN(100),
+        configTimelockEpochs: new anchor.BN(1),
+        unbondingEpochs: new anchor.BN(1),
+        usdMicrosPerGb: new anchor.BN(0),
+        priceFeed: PublicKey.default,
+        maxPriceAgeSecs: new anchor.BN(0),
+        maxPriceConfidenceBps: new anchor.BN(0),
+        priceFallbackToSol: false,
+      };
 
       const tx = await program.methods
-        .initialize(
-          treasury,
-          sol_per_gb,
-          treasury_fee_percent,
-          node_fee_percent,
-          shard_min_mb,
-          epochs_total,
-          slash_penalty_percent,
-          min_shard_count,
-          max_shard_count,
-          slots_per_epoch,
-          min_node_stake,
-          replacement_timeout_epochs,
-          min_lamports_per_upload,
-          max_user_uploads,
-          user_slash_penalty_percent,
-          reporting_window,
-          oversized_report_threshold,
-          max_submssions
-        )
+        .initialize(params)
         .accounts({
           authority: admin.publicKey,
         })
@@ -206,7 +372,8 @@ describe("contract", () => {
       expect(config.isInitialized).to.be.true;
 
       const nodeRegistry = await program.account.nodeRegistry.fetch(nodeRegistryPda);
-      expect(nodeRegistry.nodes).to.be.an("array").that.is.empty;
+      expect(nodeRegistry.nodeCount).to.equal(0);
+      expect(nodeRegistry.pageCount).to.equal(0);
 
       console.log("Program Initialized Successfully. Tx Hash:", tx);
     } else {
@@ -225,7 +392,7 @@ describe("contract", () => {
     }
 
     const tx = await program.methods
-      .registerNode(stake_amount)
+      .registerNode(stake_amount, testNodeMetadata(8080))
       .accounts({
         owner: user.publicKey,
         config: storageConfigPda,
@@ -240,11 +407,198 @@ describe("contract", () => {
     expect(nodeAccount.isActive).to.be.true;
 
     const nodeRegistry = await program.account.nodeRegistry.fetch(nodeRegistryPda);
-    expect(nodeRegistry.nodes.map(n => n.toBase58())).to.include(nodePda.toBase58());
+    expect(nodeRegistry.nodeCount).to.be.greaterThan(0);
+    const pageIndex = Buffer.alloc(4);
+    pageIndex.writeUInt32LE(nodeAccount.registryPage);
+    const [registryPagePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("registry_page"), pageIndex],
+      program.programId
+    );
+    const registryPage = await program.account.registryPage.fetch(registryPagePda);
+    const entry = registryPage.entries.find(e => e.node.toBase58() === nodePda.toBase58());
+    expect(entry.isActive).to.be.true;
 
     console.log("Node Registered Successfully. Tx Hash:", tx);
   });
 
+  it("Updates node metadata", async () => {
+    const [nodeMetadataPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("node_metadata"), nodePda.toBuffer()],
+      program.programId
+    );
+    let metadata = await program.account.nodeMetadata.fetch(nodeMetadataPda);
+    expect(metadata.node.toBase58()).to.equal(nodePda.toBase58());
+    expect(metadata.httpUrl).to.equal("http://127.0.0.1:8080");
+
+    await program.methods
+      .updateNodeMetadata({
+        ...testNodeMetadata(9090),
+        region: "eu-west",
+        capacityMb: new anchor.BN(20480),
+      })
+      .accounts({
+        owner: user.publicKey,
+      })
+      .signers([userSig])
+      .rpc();
+
+    metadata = await program.account.nodeMetadata.fetch(nodeMetadataPda);
+    expect(metadata.httpUrl).to.equal("http://127.0.0.1:9090");
+    expect(metadata.region).to.equal("eu-west");
+    expect(metadata.capacityMb.toNumber()).to.equal(20480);
+
+    // Endpoints must be URLs and multiaddrs
+    try {
+      await program.methods
+        .updateNodeMetadata({ ...testNodeMetadata(9090), httpUrl: "127.0.0.1:9090" })
+        .accounts({
+          owner: user.publicKey,
+        })
+        .signers([userSig])
+        .rpc();
+      expect.fail("Update should fail with an invalid HTTP URL");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidNodeMetadata");
+    }
+  });
+
+  it("Adds stake and starts unbonding it", async () => {
+    const amount = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+    const nodeBefore = await program.account.node.fetch(nodePda);
+    await waitForEpochRoom();
+
+    await program.methods
+      .addStake(amount)
+      .accounts({
+        owner: user.publicKey,
+      })
+      .signers([userSig])
+      .rpc();
+
+    let nodeAccount = await program.account.node.fetch(nodePda);
+    expect(nodeAccount.stakeAmount.toNumber()).to.equal(
+      nodeBefore.stakeAmount.toNumber() + amount.toNumber()
+    );
+
+    await program.methods
+      .requestUnstake(amount)
+      .accounts({
+        owner: user.publicKey,
+      })
+      .signers([userSig])
+      .rpc();
+
+    nodeAccount = await program.account.node.fetch(nodePda);
+    expect(nodeAccount.stakeAmount.toNumber()).to.equal(nodeBefore.stakeAmount.toNumber());
+    expect(nodeAccount.unbondingAmount.toNumber()).to.equal(amount.toNumber());
+    expect(nodeAccount.isActive).to.be.true;
+
+    // Unbonding stake cannot be withdrawn before the window ends
+    try {
+      await program.methods
+        .withdrawUnstaked()
+        .accounts({
+          owner: user.publicKey,
+        })
+        .signers([userSig])
+        .rpc();
+      expect.fail("Withdrawal should fail during the unbonding period");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("UnbondingNotComplete");
+    }
+  });
+
+  it("Delegates to a node and starts undelegating", async () => {
+    const amount = new anchor.BN(0.5 * LAMPORTS_PER_SOL);
+    const [delegationPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("delegation"), nodePda.toBuffer(), admin.publicKey.toBuffer()],
+      program.programId
+    );
+    const nodeBefore = await program.account.node.fetch(nodePda);
+
+    await program.methods
+      .delegate(amount)
+      .accounts({
+        node: nodePda,
+        delegator: admin.publicKey,
+      })
+      .signers([adminSig])
+      .rpc();
+
+    let nodeAccount = await program.account.node.fetch(nodePda);
+    let delegation = await program.account.delegation.fetch(delegationPda);
+    expect(nodeAccount.delegatedStake.toNumber()).to.equal(
+      nodeBefore.delegatedStake.toNumber() + amount.toNumber()
+    );
+    expect(delegation.shares.toNumber()).to.be.greaterThan(0);
+    expect(nodeAccount.delegatorShares.toNumber()).to.equal(
+      nodeBefore.delegatorShares.toNumber() + delegation.shares.toNumber()
+    );
+
+    await program.methods
+      .undelegate(delegation.shares)
+      .accounts({
+        node: nodePda,
+        delegator: admin.publicKey,
+      })
+      .signers([adminSig])
+      .rpc();
+
+    nodeAccount = await program.account.node.fetch(nodePda);
+    delegation = await program.account.delegation.fetch(delegationPda);
+    expect(delegation.unbondingShares.toNumber()).to.equal(delegation.shares.toNumber());
+    expect(nodeAccount.unbondingShares.toNumber()).to.equal(delegation.shares.toNumber());
+
+    // Nothing has accrued and the unbonding window is still open
+    try {
+      await program.methods
+        .claimDelegatorRewards()
+        .accounts({
+          node: nodePda,
+          delegator: admin.publicKey,
+        })
+        .signers([adminSig])
+        .rpc();
+      expect.fail("Claim should fail with nothing to pay out");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NothingToClaim");
+    }
+  });
+
+  it("Queues a commission raise and applies a cut at once", async () => {
+    const config = await program.account.storageConfig.fetch(storageConfigPda);
+    const slotsPerEpoch = config.slotsPerEpoch.toNumber();
+    const epoch = Math.floor((await program.provider.connection.getSlot()) / slotsPerEpoch);
+    const setCommission = (percent: number) =>
+      program.methods
+        .setCommission(percent)
+        .accounts({ owner: user.publicKey })
+        .signers([userSig])
+        .rpc();
+
+    // Delegators get the unbonding period to leave before a raise applies
+    await setCommission(10);
+    let nodeAccount = await program.account.node.fetch(nodePda);
+    expect(nodeAccount.commissionPercent).to.equal(0);
+    expect(nodeAccount.pendingCommissionPercent).to.equal(10);
+    expect(nodeAccount.commissionEffectiveSlot.toNumber()).to.be.at.least(
+      (epoch + config.unbondingEpochs.toNumber()) * slotsPerEpoch
+    );
+
+    // A cut applies straight away and drops the queued raise
+    await setCommission(0);
+    nodeAccount = await program.account.node.fetch(nodePda);
+    expect(nodeAccount.commissionPercent).to.equal(0);
+    expect(nodeAccount.pendingCommissionPercent).to.equal(0);
+
+    try {
+      await setCommission(101);
+      expect.fail("A commission above 100 percent should fail");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidCommission");
+    }
+  });
+
   it("Uploads data successfully", async () => {
     // Create and fund two nodes for the upload (to satisfy challenger requirement)
     const node1 = Keypair.generate();
@@ -258,15 +612,6 @@ describe("contract", () => {
       secretKey: node2.secretKey,
     };
 
-    const [node1Pda] = PublicKey.findProgramAddressSync(
-      [Buffer.from("node"), node1.publicKey.toBuffer()],
-      program.programId
-    );
-    const [node2Pda] = PublicKey.findProgramAddressSync(
-      [Buffer.from("node"), node2.publicKey.toBuffer()],
-      program.programId
-    );
-
     const [stakeEscrow1Pda] = PublicKey.findProgramAddressSync(
       [Buffer.from("stake_escrow"), node1.publicKey.toBuffer()],
       program.programId
@@ -292,7 +637,7 @@ describe("contract", () => {
     const stake_amount = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
     await Promise.all([
       program.methods
-        .registerNode(stake_amount)
+        .registerNode(stake_amount, testNodeMetadata(8081))
         .accounts({
           owner: node1.publicKey,
           config: storageConfigPda,
@@ -300,7 +645,7 @@ describe("contract", () => {
         .signers([node1Sig])
         .rpc(),
       program.methods
-        .registerNode(stake_amount)
+        .registerNode(stake_amount, testNodeMetadata(8082))
         .accounts({
           owner: node2.publicKey,
           config: storageConfigPda,
@@ -308,11 +653,17 @@ describe("contract", () => {
         .signers([node2Sig])
         .rpc(),
     ]);
+    testNodeOwners.push(user, node1, node2);
 
     const data_hash = "test_upload_123";
     const size_bytes = new anchor.BN(10000);
     const shard_count = 1;
+    const replication_factor = 2;
     const duration = new anchor.BN(1);
+    // Chunk Merkle commitment: 10 chunks of 1 KB cover the 10000 bytes
+    const merkle_root = [...sha256(Buffer.from("test_upload_root"))];
+    const chunk_size = 1024;
+    const leaf_count = 10;
 
     // Derive upload PDAs
     [uploadPda] = PublicKey.findProgramAddressSync(
@@ -335,16 +686,24 @@ describe("contract", () => {
     let tx;
     try {
       tx = await program.methods
-        .uploadData(data_hash, size_bytes, shard_count, duration)
+        .uploadData(
+          data_hash,
+          size_bytes,
+          shard_count,
+          duration,
+          {
+            merkleRoot: merkle_root,
+            chunkSize: chunk_size,
+            leafCount: leaf_count,
+          },
+          { blake3: {} },
+          replication_factor,
+          null
+        )
         .accounts({
           config: storageConfigPda,
           payer: user.publicKey,
-          treasury: treasury,
         })
-        .remainingAccounts([
-          { pubkey: node1Pda, isWritable: true, isSigner: false },
-          { pubkey: node2Pda, isWritable: true, isSigner: false },
-        ])
         .signers([userSig])
         .rpc();
       console.log("Upload transaction:", tx);
@@ -357,17 +716,850 @@ describe("contract", () => {
       throw error;
     }
 
+    // Nodes are only drawn once the selection slot, after the payment, has passed
+    const pending = await program.account.upload.fetch(uploadPda);
+    expect(pending.nodesAssigned).to.equal(false);
+    expect(pending.shards[0].nodeKeys.length).to.equal(0);
+    await assignUpload(data_hash, user.publicKey);
+    const window = await uploadWindow();
+
     const upload = await program.account.upload.fetch(uploadPda);
+    expect(upload.nodesAssigned).to.equal(true);
     expect(upload.dataHash).to.equal(data_hash);
     expect(upload.shardCount).to.equal(shard_count);
-    expect(upload.shards[0].nodeKeys.map(k => k.toBase58())).to.include.members([
-      node1Pda.toBase58(),
-      node2Pda.toBase58(),
-    ]);
+    expect(upload.merkleRoot).to.deep.equal(merkle_root);
+    expect(upload.chunkSize).to.equal(chunk_size);
+    expect(upload.leafCount).to.equal(leaf_count);
+    expect(upload.hashAlgo).to.deep.equal({ blake3: {} });
+    expect(upload.replicationFactor).to.equal(replication_factor);
+    expect(upload.shards[0].nodeKeys.length).to.equal(replication_factor);
+    // Shards only go to nodes of the window the program fixed
+    expect(window.nodes.map(n => n.pubkey.toBase58())).to.include.members(
+      upload.shards[0].nodeKeys.map(k => k.toBase58())
+    );
+
+    // Each assigned node commits the shard against its declared capacity
+    const assignedNode = await program.account.node.fetch(upload.shards[0].nodeKeys[0]);
+    expect(assignedNode.capacityMb.toNumber()).to.equal(10240);
+    expect(assignedNode.committedMb.toNumber()).to.equal(upload.shards[0].sizeMb.toNumber());
 
     console.log("Data Uploaded Successfully. Tx Hash:", tx);
   });
 
+  it("Extends an upload successfully", async () => {
+    const data_hash = "test_upload_123";
+    const additional_days = new anchor.BN(30);
+
+    const before = await program.account.upload.fetch(uploadPda);
+
+    const tx = await program.methods
+      .extendUpload(data_hash, additional_days)
+      .accounts({
+        upload: uploadPda,
+        escrow: uploadEscrowPda,
+        config: storageConfigPda,
+        payer: user.publicKey,
+        treasury: treasury,
+      })
+      .signers([userSig])
+      .rpc();
+
+    const after = await program.account.upload.fetch(uploadPda);
+    expect(after.storageDurationDays.toNumber()).to.equal(
+      before.storageDurationDays.toNumber() + additional_days.toNumber()
+    );
+    expect(after.expiryTime.toNumber()).to.be.at.least(
+      before.expiryTime.toNumber() + additional_days.toNumber() * 86400
+    );
+    expect(after.nodeLamports.toNumber()).to.be.at.least(before.nodeLamports.toNumber());
+
+    console.log("Upload Extended Successfully. Tx Hash:", tx);
+  });
+
+  it("Transfers an upload and lets its delegate extend it", async () => {
+    const data_hash = "test_upload_123";
+    const newOwner = Keypair.generate();
+    const newOwnerSig: Signer = { publicKey: newOwner.publicKey, secretKey: newOwner.secretKey };
+    await program.provider.connection.confirmTransaction(
+      await program.provider.connection.requestAirdrop(newOwner.publicKey, 2 * LAMPORTS_PER_SOL),
+      "confirmed"
+    );
+    const uploadKeysPda = (owner: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("upload_keys"), owner.toBuffer()],
+        program.programId
+      )[0];
+
+    await program.methods
+      .transferUpload(data_hash, newOwner.publicKey)
+      .accounts({
+        upload: uploadPda,
+        config: storageConfigPda,
+        ownerUploadKeys: uploadKeysPda(user.publicKey),
+        newOwnerUploadKeys: uploadKeysPda(newOwner.publicKey),
+        owner: user.publicKey,
+      })
+      .signers([userSig])
+      .rpc();
+
+    let upload = await program.account.upload.fetch(uploadPda);
+    expect(upload.owner.toBase58()).to.equal(newOwner.publicKey.toBase58());
+    expect(upload.payer.toBase58()).to.equal(user.publicKey.toBase58());
+    const oldKeys = await program.account.userUploadKeys.fetch(uploadKeysPda(user.publicKey));
+    const newKeys = await program.account.userUploadKeys.fetch(uploadKeysPda(newOwner.publicKey));
+    expect(oldKeys.uploads.map((key) => key.toBase58())).to.not.include(uploadPda.toBase58());
+    expect(newKeys.uploads.map((key) => key.toBase58())).to.deep.equal([uploadPda.toBase58()]);
+
+    // The previous owner can no longer extend until the new owner delegates to it
+    try {
+      await program.methods
+        .extendUpload(data_hash, new anchor.BN(1))
+        .accounts({
+          upload: uploadPda,
+          escrow: uploadEscrowPda,
+          config: storageConfigPda,
+          payer: user.publicKey,
+          treasury: treasury,
+        })
+        .signers([userSig])
+        .rpc();
+      expect.fail("Extension by a non-owner should fail");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    await program.methods
+      .setUploadDelegate(data_hash, user.publicKey)
+      .accounts({ upload: uploadPda, owner: newOwner.publicKey })
+      .signers([newOwnerSig])
+      .rpc();
+    await program.methods
+      .extendUpload(data_hash, new anchor.BN(1))
+      .accounts({
+        upload: uploadPda,
+        escrow: uploadEscrowPda,
+        config: storageConfigPda,
+        payer: user.publicKey,
+        treasury: treasury,
+      })
+      .signers([userSig])
+      .rpc();
+    upload = await program.account.upload.fetch(uploadPda);
+    expect(upload.delegate.toBase58()).to.equal(user.publicKey.toBase58());
+
+    // Hand the upload back; the transfer clears the delegate
+    await program.methods
+      .transferUpload(data_hash, user.publicKey)
+      .accounts({
+        upload: uploadPda,
+        config: storageConfigPda,
+        ownerUploadKeys: uploadKeysPda(newOwner.publicKey),
+        newOwnerUploadKeys: uploadKeysPda(user.publicKey),
+        owner: newOwner.publicKey,
+      })
+      .signers([newOwnerSig])
+      .rpc();
+    upload = await program.account.upload.fetch(uploadPda);
+    expect(upload.owner.toBase58()).to.equal(user.publicKey.toBase58());
+    expect(upload.delegate.toBase58()).to.equal(PublicKey.default.toBase58());
+  });
+
+  it("Issues a PoS challenge for a shard", async () => {
+    const data_hash = "test_upload_123";
+    const shard_id = 0;
+
+    const config = await program.account.storageConfig.fetch(storageConfigPda);
+    await waitForEpochRoom();
+    const slot = await program.provider.connection.getSlot();
+    const epoch = Math.floor(slot / config.slotsPerEpoch.toNumber());
+
+    const epochBytes = Buffer.alloc(8);
+    epochBytes.writeBigUInt64LE(BigInt(epoch));
+    const [challengePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("challenge"), uploadPda.toBuffer(), Buffer.from([shard_id]), epochBytes],
+      program.programId
+    );
+
+    const tx = await program.methods
+      .issueChallenge(data_hash, user.publicKey, shard_id, new anchor.BN(epoch))
+      .accounts({
+        upload: uploadPda,
+        challenge: challengePda,
+        config: storageConfigPda,
+        caller: admin.publicKey,
+      })
+      .signers([adminSig])
+      .rpc();
+
+    // The leaves stay hidden until the seed slot, after the issuing slot, has passed
+    let challenge = await program.account.challenge.fetch(challengePda);
+    expect(challenge.upload.toBase58()).to.equal(uploadPda.toBase58());
+    expect(challenge.epoch.toNumber()).to.equal(epoch);
+    expect(challenge.deadlineSlot.toNumber()).to.equal(
+      (epoch + 1) * config.slotsPerEpoch.toNumber()
+    );
+    expect(challenge.revealed).to.be.false;
+    expect(challenge.seedSlot.toNumber()).to.be.above(slot);
+
+    // A second issue cannot re-draw a seed that can still be revealed
+    try {
+      await program.methods
+        .issueChallenge(data_hash, user.publicKey, shard_id, new anchor.BN(epoch))
+        .accounts({
+          upload: uploadPda,
+          challenge: challengePda,
+          config: storageConfigPda,
+          caller: admin.publicKey,
+        })
+        .signers([adminSig])
+        .rpc();
+      expect.fail("Re-issuing should fail while the seed slot can be revealed");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ChallengeAlreadyIssued");
+    }
+
+    await waitForSlot(challenge.seedSlot.toNumber());
+    await program.methods
+      .revealChallenge()
+      .accounts({ challenge: challengePda, upload: uploadPda })
+      .rpc();
+
+    const upload = await program.account.upload.fetch(uploadPda);
+    const leafCount = upload.leafCount;
+    challenge = await program.account.challenge.fetch(challengePda);
+    expect(challenge.revealed).to.be.true;
+    // The reveal left a full response window before the deadline
+    expect(challenge.revealedSlot.toNumber()).to.be.at.least(challenge.seedSlot.toNumber());
+    expect(
+      challenge.revealedSlot.toNumber() + config.slotsPerEpoch.toNumber() / 4
+    ).to.be.at.most(challenge.deadlineSlot.toNumber());
+    challenge.leafIndices.forEach((index: number) => expect(index).to.be.below(leafCount));
+
+    // It stays open as inactivity evidence, so it cannot be closed yet
+    try {
+      await program.methods
+        .closeChallenge()
+        .accounts({ challenge: challengePda, issuer: admin.publicKey })
+        .rpc();
+      expect.fail("Closing should fail while the challenge is still evidence");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ChallengeStillOpen");
+    }
+
+    console.log("Challenge Issued Successfully. Tx Hash:", tx);
+  });
+
+  it("Closes an upload and refunds the unearned escrow", async () => {
+    const data_hash = "test_upload_123";
+    const upload = await program.account.upload.fetch(uploadPda);
+    const escrow = await program.account.escrow.fetch(uploadEscrowPda);
+    const balanceBefore = await program.provider.connection.getBalance(user.publicKey);
+
+    // Each assigned node is passed with its shard claim ledger; none has claimed yet
+    const nodeAccounts = upload.shards.flatMap((shard) =>
+      shard.nodeKeys
+        .filter((key) => !key.equals(PublicKey.default))
+        .flatMap((key) => [
+          { pubkey: key, isWritable: true, isSigner: false },
+          {
+            pubkey: PublicKey.findProgramAddressSync(
+              [
+                Buffer.from("shard_claim"),
+                uploadPda.toBuffer(),
+                Buffer.from([shard.shardId]),
+                key.toBuffer(),
+              ],
+              program.programId
+            )[0],
+            isWritable: false,
+            isSigner: false,
+          },
+        ])
+    );
+
+    const tx = await program.methods
+      .closeUpload(data_hash)
+      .accounts({
+        upload: uploadPda,
+        escrow: uploadEscrowPda,
+        owner: user.publicKey,
+        authority: user.publicKey,
+        program: program.programId,
+      })
+      .remainingAccounts(nodeAccounts)
+      .signers([userSig])
+      .rpc();
+
+    // No shard has a PoS, so nothing was earned and the whole node share comes back
+    expect(await program.account.upload.fetchNullable(uploadPda)).to.be.null;
+    const balanceAfter = await program.provider.connection.getBalance(user.publicKey);
+    expect(balanceAfter - balanceBefore).to.be.at.least(escrow.lamports.toNumber());
+
+    const node = await program.account.node.fetch(upload.shards[0].nodeKeys[0]);
+    expect(node.committedMb.toNumber()).to.equal(0);
+
+    console.log("Upload Closed Successfully. Tx Hash:", tx);
+  });
+
+  it("Submits a batch of PoS responses and releases each upload slot once", async () => {
+    const data_hash = "test_batch_pos";
+    const shard_id = 0;
+    // A real chunk tree, so the responses can prove the challenged leaves
+    const leaves = Array.from({ length: 10 }, (_, i) => sha256(Buffer.from(`batch_chunk_${i}`)));
+    const [batchUploadPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("upload"), Buffer.from(data_hash), user.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .uploadData(
+        data_hash,
+        new anchor.BN(10000),
+        1,
+        new anchor.BN(1),
+        { merkleRoot: merkleRoot(leaves), chunkSize: 1024, leafCount: leaves.length },
+        { blake3: {} },
+        2,
+        null
+      )
+      .accounts({
+        config: storageConfigPda,
+        payer: user.publicKey,
+      })
+      .signers([userSig])
+      .rpc();
+    await assignUpload(data_hash, user.publicKey);
+
+    const config = await program.account.storageConfig.fetch(storageConfigPda);
+    await waitForEpochRoom();
+    const epoch = Math.floor(
+      (await program.provider.connection.getSlot()) / config.slotsPerEpoch.toNumber()
+    );
+    batchChallengeEpoch = epoch;
+    const epochBytes = Buffer.alloc(8);
+    epochBytes.writeBigUInt64LE(BigInt(epoch));
+    const [challengePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("challenge"), batchUploadPda.toBuffer(), Buffer.from([shard_id]), epochBytes],
+      program.programId
+    );
+    await program.methods
+      .issueChallenge(data_hash, user.publicKey, shard_id, new anchor.BN(epoch))
+      .accounts({
+        upload: batchUploadPda,
+        challenge: challengePda,
+        config: storageConfigPda,
+        caller: admin.publicKey,
+      })
+      .signers([adminSig])
+      .rpc();
+    let challenge = await program.account.challenge.fetch(challengePda);
+    await waitForSlot(challenge.seedSlot.toNumber());
+    await program.methods
+      .revealChallenge()
+      .accounts({ challenge: challengePda, upload: batchUploadPda })
+      .rpc();
+    challenge = await program.account.challenge.fetch(challengePda);
+
+    const upload = await program.account.upload.fetch(batchUploadPda);
+    const shardNodes = upload.shards[shard_id].nodeKeys;
+    const countsBefore = await Promise.all(
+      shardNodes.map(async (node) => (await program.account.node.fetch(node)).uploadCount.toNumber())
+    );
+
+    // Each node answers in a one-item batch, attested by its peer's owner key
+    const submit = (responder: number) => {
+      const owner = ownerOf(shardNodes[responder]);
+      const challenger = ownerOf(shardNodes[1 - responder]);
+      const message = Buffer.concat([
+        Buffer.from("solad:pos-attestation:v1"),
+        program.programId.toBuffer(),
+        challengePda.toBuffer(),
+        shardNodes[responder].toBuffer(),
+        Buffer.from(upload.shards[shard_id].commitment.merkleRoot),
+      ]);
+      return program.methods
+        .submitPosBatch([
+          {
+            dataHash: data_hash,
+            shardId: shard_id,
+            proofs: challenge.leafIndices.map((leafIndex: number) => ({
+              leafIndex,
+              leaf: [...leaves[leafIndex]],
+              merkleProof: merkleProof(leaves, leafIndex),
+            })),
+            challengerPubkey: challenger.publicKey,
+            actualSizeMb: null,
+          },
+        ])
+        .accounts({ owner: owner.publicKey, config: storageConfigPda })
+        .remainingAccounts(
+          [batchUploadPda, challengePda, ...shardNodes].map((pubkey) => ({
+            pubkey,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .preInstructions([
+          Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: challenger.secretKey,
+            message,
+          }),
+        ])
+        .signers([owner])
+        .rpc();
+    };
+
+    await submit(0);
+    let shard = (await program.account.upload.fetch(batchUploadPda)).shards[shard_id];
+    expect(shard.verifiedCount).to.equal(1);
+    expect(shard.uploadReleased).to.equal(0);
+
+    // The response that completes the shard gives back both nodes' slots
+    await submit(1);
+    shard = (await program.account.upload.fetch(batchUploadPda)).shards[shard_id];
+    expect(shard.verifiedCount).to.equal(2);
+    expect(shard.uploadReleased).to.equal(0b11);
+    // Each answer counts one epoch its node can claim rewards for
+    expect(shard.provenEpochs).to.deep.equal([1, 1]);
+    challenge = await program.account.challenge.fetch(challengePda);
+    expect(challenge.responders.map((key) => key.toBase58())).to.include.members(
+      shardNodes.map((key) => key.toBase58())
+    );
+    const countsAfter = await Promise.all(
+      shardNodes.map(async (node) => (await program.account.node.fetch(node)).uploadCount.toNumber())
+    );
+    expect(countsAfter).to.deep.equal(countsBefore.map((count) => count - 1));
+
+    // A repeated answer fails on its own without touching the released slots
+    await submit(0);
+    const countsReplayed = await Promise.all(
+      shardNodes.map(async (node) => (await program.account.node.fetch(node)).uploadCount.toNumber())
+    );
+    expect(countsReplayed).to.deep.equal(countsAfter);
+    shard = (await program.account.upload.fetch(batchUploadPda)).shards[shard_id];
+    expect(shard.verifiedCount).to.equal(2);
+  });
+
+  it("Pays delegators their share of a node's reward", async () => {
+    const data_hash = "test_batch_pos";
+    const shard_id = 0;
+    const [batchUploadPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("upload"), Buffer.from(data_hash), user.publicKey.toBuffer()],
+      program.programId
+    );
+    const [batchEscrowPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), Buffer.from(data_hash), user.publicKey.toBuffer()],
+      program.programId
+    );
+    const upload = await program.account.upload.fetch(batchUploadPda);
+    const node = upload.shards[shard_id].nodeKeys[0];
+    const owner = ownerOf(node);
+    const delegator = Keypair.generate();
+    await program.provider.connection.confirmTransaction(
+      await program.provider.connection.requestAirdrop(delegator.publicKey, 2 * LAMPORTS_PER_SOL),
+      "confirmed"
+    );
+    const [delegationPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("delegation"), node.toBuffer(), delegator.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .delegate(new anchor.BN(0.5 * LAMPORTS_PER_SOL))
+      .accounts({ node, delegator: delegator.publicKey })
+      .signers([delegator])
+      .rpc();
+
+    // The node proved the shard in the PoS batch, so it can claim its initial reward
+    const rewardPerShareBefore = (await program.account.node.fetch(node)).rewardPerShare;
+    await program.methods
+      .claimRewards(data_hash, shard_id)
+      .accounts({
+        upload: batchUploadPda,
+        escrow: batchEscrowPda,
+        config: storageConfigPda,
+        treasury: treasury,
+        stakeEscrow: PublicKey.findProgramAddressSync(
+          [Buffer.from("stake_escrow"), owner.publicKey.toBuffer()],
+          program.programId
+        )[0],
+        owner: owner.publicKey,
+      })
+      .signers([owner])
+      .rpc();
+    const nodeAccount = await program.account.node.fetch(node);
+    expect(nodeAccount.rewardPerShare.gt(rewardPerShareBefore)).to.be.true;
+
+    const balanceBefore = await program.provider.connection.getBalance(delegator.publicKey);
+    await program.methods
+      .claimDelegatorRewards()
+      .accounts({ node, delegator: delegator.publicKey })
+      .signers([delegator])
+      .rpc();
+    const balanceAfter = await program.provider.connection.getBalance(delegator.publicKey);
+    expect(balanceAfter).to.be.above(balanceBefore);
+    const delegation = await program.account.delegation.fetch(delegationPda);
+    expect(delegation.pendingRewards.toNumber()).to.equal(0);
+
+    // Everything accrued has been paid
+    try {
+      await program.methods
+        .claimDelegatorRewards()
+        .accounts({ node, delegator: delegator.publicKey })
+        .signers([delegator])
+        .rpc();
+      expect.fail("A second claim should have nothing to pay out");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NothingToClaim");
+    }
+  });
+
+  it("Refuses to slash a node that has not missed the inactivity window", async () => {
+    const data_hash = "test_batch_pos";
+    const shard_id = 0;
+    const [batchUploadPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("upload"), Buffer.from(data_hash), user.publicKey.toBuffer()],
+      program.programId
+    );
+    const config = await program.account.storageConfig.fetch(storageConfigPda);
+    const epochBytes = Buffer.alloc(8);
+    epochBytes.writeBigUInt64LE(BigInt(batchChallengeEpoch));
+    const [challengePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("challenge"), batchUploadPda.toBuffer(), Buffer.from([shard_id]), epochBytes],
+      program.programId
+    );
+    const upload = await program.account.upload.fetch(batchUploadPda);
+    const node = upload.shards[shard_id].nodeKeys[0];
+    const owner = ownerOf(node);
+    const [stakeEscrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("stake_escrow"), owner.publicKey.toBuffer()],
+      program.programId
+    );
+    const stakeBefore = (await program.account.node.fetch(node)).stakeAmount.toNumber();
+
+    // Once the batch epoch has completed its challenge is the whole inactivity window, and the
+    // node answered it, so there is no missed challenge to slash it for
+    await waitForSlot((batchChallengeEpoch + 1) * config.slotsPerEpoch.toNumber() - 1);
+    try {
+      await program.methods
+        .slashInactive(data_hash, shard_id)
+        .accounts({
+          upload: batchUploadPda,
+          node,
+          stakeEscrow,
+          config: storageConfigPda,
+          caller: admin.publicKey,
+          treasury,
+        })
+        .remainingAccounts(
+          Array.from({ length: config.inactivityEpochs.toNumber() }, () => ({
+            pubkey: challengePda,
+            isWritable: false,
+            isSigner: false,
+          }))
+        )
+        .signers([adminSig])
+        .rpc();
+      expect.fail("A node that answered its challenges should not be slashed");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NodeNotInactive");
+    }
+
+    const nodeAccount = await program.account.node.fetch(node);
+    expect(nodeAccount.stakeAmount.toNumber()).to.equal(stakeBefore);
+    const shard = (await program.account.upload.fetch(batchUploadPda)).shards[shard_id];
+    expect(shard.nodeKeys[0].toBase58()).to.equal(node.toBase58());
+  });
+
+  it("Slashes and replaces a node that missed a revealed challenge", async () => {
+    const data_hash = "test_batch_pos";
+    const shard_id = 0;
+    const [batchUploadPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("upload"), Buffer.from(data_hash), user.publicKey.toBuffer()],
+      program.programId
+    );
+    const config = await program.account.storageConfig.fetch(storageConfigPda);
+    await waitForEpochRoom();
+    const epoch = Math.floor(
+      (await program.provider.connection.getSlot()) / config.slotsPerEpoch.toNumber()
+    );
+    const epochBytes = Buffer.alloc(8);
+    epochBytes.writeBigUInt64LE(BigInt(epoch));
+    const [challengePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("challenge"), batchUploadPda.toBuffer(), Buffer.from([shard_id]), epochBytes],
+      program.programId
+    );
+
+    // Issue and reveal this epoch's challenge, then let its deadline pass unanswered
+    await program.methods
+      .issueChallenge(data_hash, user.publicKey, shard_id, new anchor.BN(epoch))
+      .accounts({
+        upload: batchUploadPda,
+        challenge: challengePda,
+        config: storageConfigPda,
+        caller: admin.publicKey,
+      })
+      .signers([adminSig])
+      .rpc();
+    let challenge = await program.account.challenge.fetch(challengePda);
+    await waitForSlot(challenge.seedSlot.toNumber());
+    await program.methods
+      .revealChallenge()
+      .accounts({ challenge: challengePda, upload: batchUploadPda })
+      .rpc();
+    challenge = await program.account.challenge.fetch(challengePda);
+    await waitForSlot(challenge.deadlineSlot.toNumber());
+
+    const upload = await program.account.upload.fetch(batchUploadPda);
+    const shardNodes = upload.shards[shard_id].nodeKeys;
+    const node = shardNodes[0];
+    const owner = ownerOf(node);
+    const [stakeEscrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("stake_escrow"), owner.publicKey.toBuffer()],
+      program.programId
+    );
+    const windows = await replacementWindows(batchUploadPda, shard_id, node);
+    const candidates = [
+      ...new Set(windows.filter((meta) => meta.isWritable).map((meta) => meta.pubkey.toBase58())),
+    ];
+    const countsBefore = new Map(
+      await Promise.all(
+        candidates.map(
+          async (key) =>
+            [key, (await program.account.node.fetch(key)).uploadCount.toNumber()] as const
+        )
+      )
+    );
+    const stakeBefore = (await program.account.node.fetch(node)).stakeAmount.toNumber();
+
+    await program.methods
+      .slashInactive(data_hash, shard_id)
+      .accounts({
+        upload: batchUploadPda,
+        node,
+        stakeEscrow,
+        config: storageConfigPda,
+        caller: admin.publicKey,
+        treasury,
+      })
+      .remainingAccounts([
+        { pubkey: challengePda, isWritable: false, isSigner: false },
+        ...windows,
+      ])
+      .signers([adminSig])
+      .rpc();
+
+    // The node loses stake and a successor from outside the shard takes its slot
+    const nodeAccount = await program.account.node.fetch(node);
+    expect(nodeAccount.stakeAmount.toNumber()).to.be.below(stakeBefore);
+    const shard = (await program.account.upload.fetch(batchUploadPda)).shards[shard_id];
+    const successor = shard.nodeKeys[0];
+    expect(shardNodes.map((key) => key.toBase58())).to.not.include(successor.toBase58());
+    expect(shard.nodeKeys[1].toBase58()).to.equal(shardNodes[1].toBase58());
+    const successorAccount = await program.account.node.fetch(successor);
+    expect(successorAccount.uploadCount.toNumber()).to.equal(
+      countsBefore.get(successor.toBase58()) + 1
+    );
+  });
+
+  it("Uploads erasure-coded fragments to distinct nodes", async () => {
+    // Three fresh nodes hold two data fragments and one parity fragment
+    const owners = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    await Promise.all(
+      owners.map(async (owner) =>
+        program.provider.connection.confirmTransaction(
+          await program.provider.connection.requestAirdrop(owner.publicKey, 5 * LAMPORTS_PER_SOL),
+          "confirmed"
+        )
+      )
+    );
+    const stake_amount = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
+    await Promise.all(
+      owners.map((owner, i) =>
+        program.methods
+          .registerNode(stake_amount, testNodeMetadata(8090 + i))
+          .accounts({
+            owner: owner.publicKey,
+            config: storageConfigPda,
+          })
+          .signers([{ publicKey: owner.publicKey, secretKey: owner.secretKey }])
+          .rpc()
+      )
+    );
+
+    const data_hash = "test_erasure_upload";
+    const size_bytes = new anchor.BN(10000);
+    // Two 5000-byte data fragments, committed in five 1 KB chunks each like the parity
+    const erasure = {
+      dataShards: 2,
+      parityShards: 1,
+      chunkSize: 1024,
+      fragmentRoots: ["fragment_0", "fragment_1", "fragment_2"].map((tag) => [
+        ...sha256(Buffer.from(tag)),
+      ]),
+    };
+    const [erasureUploadPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("upload"), Buffer.from(data_hash), user.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .uploadData(
+        data_hash,
+        size_bytes,
+        3,
+        new anchor.BN(1),
+        {
+          merkleRoot: [...sha256(Buffer.from("test_erasure_root"))],
+          chunkSize: 1024,
+          leafCount: 10,
+        },
+        { sha256: {} },
+        1,
+        erasure
+      )
+      .accounts({
+        config: storageConfigPda,
+        payer: user.publicKey,
+      })
+      .signers([userSig])
+      .rpc();
+    const tx = await assignUpload(data_hash, user.publicKey);
+
+    const upload = await program.account.upload.fetch(erasureUploadPda);
+    expect(upload.dataShards).to.equal(2);
+    expect(upload.parityShards).to.equal(1);
+    expect(upload.shards.map((shard) => shard.role)).to.deep.equal([
+      { data: {} },
+      { data: {} },
+      { parity: {} },
+    ]);
+    upload.shards.forEach((shard, i) => {
+      expect(shard.nodeKeys.length).to.equal(1);
+      expect(shard.commitment.merkleRoot).to.deep.equal(erasure.fragmentRoots[i]);
+      expect(shard.commitment.leafCount).to.equal(5);
+    });
+    // No node holds two fragments of the upload
+    const holders = upload.shards.map((shard) => shard.nodeKeys[0].toBase58());
+    expect(new Set(holders).size).to.equal(3);
+
+    console.log("Erasure-coded Upload Successful. Tx Hash:", tx);
+  });
+
+  it("Sponsors an upload that another user owns", async () => {
+    // The member owns the upload and pays rent; the sponsor pays the fee and takes refunds
+    const member = Keypair.generate();
+    const sponsor = Keypair.generate();
+    const nodeOwners = [Keypair.generate(), Keypair.generate()];
+    await Promise.all(
+      [member, sponsor, ...nodeOwners].map(async (keypair) =>
+        program.provider.connection.confirmTransaction(
+          await program.provider.connection.requestAirdrop(keypair.publicKey, 5 * LAMPORTS_PER_SOL),
+          "confirmed"
+        )
+      )
+    );
+    const signer = (keypair: Keypair): Signer => ({
+      publicKey: keypair.publicKey,
+      secretKey: keypair.secretKey,
+    });
+    await Promise.all(
+      nodeOwners.map((owner, i) =>
+        program.methods
+          .registerNode(new anchor.BN(0.2 * LAMPORTS_PER_SOL), testNodeMetadata(8093 + i))
+          .accounts({ owner: owner.publicKey, config: storageConfigPda })
+          .signers([signer(owner)])
+          .rpc()
+      )
+    );
+
+    const data_hash = "test_sponsored_upload";
+    const [sponsoredUploadPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("upload"), Buffer.from(data_hash), member.publicKey.toBuffer()],
+      program.programId
+    );
+    const [sponsoredEscrowPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), Buffer.from(data_hash), member.publicKey.toBuffer()],
+      program.programId
+    );
+    const [memberUploadKeysPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("upload_keys"), member.publicKey.toBuffer()],
+      program.programId
+    );
+
+    const sponsorBefore = await program.provider.connection.getBalance(sponsor.publicKey);
+    await program.methods
+      .uploadData(
+        data_hash,
+        new anchor.BN(10000),
+        1,
+        new anchor.BN(30),
+        {
+          merkleRoot: [...sha256(Buffer.from("test_sponsored_root"))],
+          chunkSize: 1024,
+          leafCount: 10,
+        },
+        { sha256: {} },
+        2,
+        null
+      )
+      .accounts({
+        config: storageConfigPda,
+        payer: member.publicKey,
+        sponsor: sponsor.publicKey,
+      })
+      .signers([signer(member), signer(sponsor)])
+      .rpc();
+    await assignUpload(data_hash, member.publicKey, sponsor.publicKey);
+
+    const upload = await program.account.upload.fetch(sponsoredUploadPda);
+    const escrow = await program.account.escrow.fetch(sponsoredEscrowPda);
+    expect(upload.owner.toBase58()).to.equal(member.publicKey.toBase58());
+    expect(upload.sponsor.toBase58()).to.equal(sponsor.publicKey.toBase58());
+    const memberKeys = await program.account.userUploadKeys.fetch(memberUploadKeysPda);
+    expect(memberKeys.uploads.map((key) => key.toBase58())).to.include(
+      sponsoredUploadPda.toBase58()
+    );
+    const sponsorAfterUpload = await program.provider.connection.getBalance(sponsor.publicKey);
+    expect(sponsorBefore - sponsorAfterUpload).to.be.at.least(escrow.lamports.toNumber());
+
+    // Closing refunds the unearned escrow to the sponsor
+    const nodeAccounts = upload.shards.flatMap((shard) =>
+      shard.nodeKeys.flatMap((key) => [
+        { pubkey: key, isWritable: true, isSigner: false },
+        {
+          pubkey: PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("shard_claim"),
+              sponsoredUploadPda.toBuffer(),
+              Buffer.from([shard.shardId]),
+              key.toBuffer(),
+            ],
+            program.programId
+          )[0],
+          isWritable: false,
+          isSigner: false,
+        },
+      ])
+    );
+    await program.methods
+      .closeUpload(data_hash)
+      .accounts({
+        upload: sponsoredUploadPda,
+        escrow: sponsoredEscrowPda,
+        owner: member.publicKey,
+        authority: member.publicKey,
+        program: program.programId,
+        sponsor: sponsor.publicKey,
+      })
+      .remainingAccounts(nodeAccounts)
+      .signers([signer(member)])
+      .rpc();
+
+    const sponsorAfterClose = await program.provider.connection.getBalance(sponsor.publicKey);
+    expect(sponsorAfterClose - sponsorAfterUpload).to.equal(escrow.lamports.toNumber());
+  });
+
   // it("Submits Proof of Storage successfully", async () => {
   //   // Setup nodes
   //   const node1 = Keypair.generate();
@@ -686,52 +1878,285 @@ describe("contract", () => {
   //   console.log("Node Exit Requested Successfully. Tx Hash:", tx);
   // });
 
-  it("Updates configuration successfully", async () => {
-    const newSolPerGb = new anchor.BN(0.05 * LAMPORTS_PER_SOL);
-    const newTreasuryFeePercent = new anchor.BN(30);
-    const newNodeFeePercent = new anchor.BN(70);
-    const newShardMinMb = new anchor.BN(2);
-    const newEpochsTotal = new anchor.BN(2);
-    const newSlashPenaltyPercent = new anchor.BN(20);
-    const newMinShardCount = 2;
-    const newMaxShardCount = 12;
-    const newSlotsPerEpoch = new anchor.BN(50000);
-    const newMinNodeStake = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
-    const newReplacementTimeoutEpochs = new anchor.BN(3);
+  it("Rejects configuration update from non-authority", async () => {
+    const outsider = Keypair.generate();
+    const configBefore = await program.account.storageConfig.fetch(storageConfigPda);
+    await program.provider.connection.confirmTransaction(
+      await program.provider.connection.requestAirdrop(outsider.publicKey, 1 * LAMPORTS_PER_SOL),
+      "confirmed"
+    );
 
-    const tx = await program.methods
-      .updateConfig(
-        newSolPerGb,
-        newTreasuryFeePercent,
-        newNodeFeePercent,
-        newShardMinMb,
-        newEpochsTotal,
-        newSlashPenaltyPercent,
-        newMinShardCount,
-        newMaxShardCount,
-        newSlotsPerEpoch,
-        newMinNodeStake,
-        newReplacementTimeoutEpochs
+    try {
+      await program.methods
+        .updateConfig(configBefore)
+        .accounts({
+          authority: outsider.publicKey,
+        })
+        .signers([outsider])
+        .rpc();
+      expect.fail("update_config should reject a non-authority signer");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    const config = await program.account.storageConfig.fetch(storageConfigPda);
+    expect(config.authority.toBase58()).to.equal(admin.publicKey.toBase58());
+  });
+
+  it("Hands over the config authority in two steps", async () => {
+    const successor = Keypair.generate();
+    const stale = Keypair.generate();
+    await Promise.all(
+      [successor, stale].map(async (kp) =>
+        program.provider.connection.confirmTransaction(
+          await program.provider.connection.requestAirdrop(kp.publicKey, 1 * LAMPORTS_PER_SOL),
+          "confirmed"
+        )
       )
+    );
+
+    // A second proposal replaces the first, which can no longer be accepted
+    await program.methods
+      .proposeAuthority(stale.publicKey)
+      .accounts({ authority: admin.publicKey })
+      .signers([adminSig])
+      .rpc();
+    await program.methods
+      .proposeAuthority(successor.publicKey)
+      .accounts({ authority: admin.publicKey })
+      .signers([adminSig])
+      .rpc();
+    let config = await program.account.storageConfig.fetch(storageConfigPda);
+    expect(config.pendingAuthority.toBase58()).to.equal(successor.publicKey.toBase58());
+
+    for (const signer of [stale, user]) {
+      try {
+        await program.methods
+          .acceptAuthority()
+          .accounts({ pendingAuthority: signer.publicKey })
+          .signers([signer])
+          .rpc();
+        expect.fail("accept_authority should reject a signer other than the pending authority");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("Unauthorized");
+      }
+    }
+    config = await program.account.storageConfig.fetch(storageConfigPda);
+    expect(config.authority.toBase58()).to.equal(admin.publicKey.toBase58());
+
+    await program.methods
+      .acceptAuthority()
+      .accounts({ pendingAuthority: successor.publicKey })
+      .signers([successor])
+      .rpc();
+    config = await program.account.storageConfig.fetch(storageConfigPda);
+    expect(config.authority.toBase58()).to.equal(successor.publicKey.toBase58());
+    expect(config.pendingAuthority.toBase58()).to.equal(PublicKey.default.toBase58());
+
+    // Nothing is pending once the handover completes
+    try {
+      await program.methods
+        .acceptAuthority()
+        .accounts({ pendingAuthority: successor.publicKey })
+        .signers([successor])
+        .rpc();
+      expect.fail("accept_authority should fail without a pending handover");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("NoPendingAuthority");
+    }
+
+    // Hand the authority back for the remaining tests
+    await program.methods
+      .proposeAuthority(admin.publicKey)
+      .accounts({ authority: successor.publicKey })
+      .signers([successor])
+      .rpc();
+    await program.methods
+      .acceptAuthority()
+      .accounts({ pendingAuthority: admin.publicKey })
+      .signers([adminSig])
+      .rpc();
+    config = await program.account.storageConfig.fetch(storageConfigPda);
+    expect(config.authority.toBase58()).to.equal(admin.publicKey.toBase58());
+  });
+
+  it("Queues and cancels a configuration change", async () => {
+    const [pendingConfigPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_config")],
+      program.programId
+    );
+
+    const configBefore = await program.account.storageConfig.fetch(storageConfigPda);
+    await waitForEpochRoom();
+    const newParams = {
+      treasury: configBefore.treasury,
+      solPerGb: new anchor.BN(0.05 * LAMPORTS_PER_SOL),
+      treasuryFeePercent: new anchor.BN(30),
+      nodeFeePercent: new anchor.BN(70),
+      shardMinMb: new anchor.BN(2),
+      epochsTotal: new anchor.BN(2),
+      slashPenaltyPercent: new anchor.BN(20),
+      minShardCount: 2,
+      maxShardCount: 12,
+      minReplicationFactor: 2,
+      maxReplicationFactor: 6,
+      slotsPerEpoch: new anchor.BN(50000),
+      minNodeStake: new anchor.BN(0.2 * LAMPORTS_PER_SOL),
+      replacementTimeoutEpochs: new anchor.BN(3),
+      inactivityEpochs: new anchor.BN(3),
+      minLamportsPerUpload: new anchor.BN(0.05 * LAMPORTS_PER_SOL),
+      userSlashPenaltyPercent: new anchor.BN(15),
+      reportingWindow: new anchor.BN(2),
+      maxUserUploads: new anchor.BN(200),
+      oversizedReportThreshold: 75,
+      disputeWindowEpochs: new anchor.BN(2),
+      maxSubmssions: new anchor.BN(50),
+      configTimelockEpochs: new anchor.BN(2),
+      unbondingEpochs: new anchor.BN(2),
+      usdMicrosPerGb: new anchor.BN(6_000_000),
+      priceFeed: MOCK_PRICE_FEED,
+      maxPriceAgeSecs: new anchor.BN(60),
+      maxPriceConfidenceBps: new anchor.BN(100),
+      priceFallbackToSol: true,
+    };
+
+    const tx = await program.methods
+      .updateConfig(newParams)
       .accounts({
         authority: admin.publicKey,
       })
       .signers([adminSig])
       .rpc();
 
+    // The change is queued, not applied
+    const pending = await program.account.pendingConfigChange.fetch(pendingConfigPda);
+    expect(pending.proposer.toBase58()).to.equal(admin.publicKey.toBase58());
+    expect(pending.activationEpoch.toNumber()).to.equal(
+      pending.queuedEpoch.toNumber() + configBefore.configTimelockEpochs.toNumber()
+    );
+    expect(pending.params.solPerGb.toNumber()).to.equal(newParams.solPerGb.toNumber());
+    expect(pending.params.minShardCount).to.equal(newParams.minShardCount);
+    expect(pending.params.maxShardCount).to.equal(newParams.maxShardCount);
+    expect(pending.params.maxReplicationFactor).to.equal(newParams.maxReplicationFactor);
+    expect(pending.params.reportingWindow.toNumber()).to.equal(newParams.reportingWindow.toNumber());
+    expect(pending.params.maxSubmssions.toNumber()).to.equal(newParams.maxSubmssions.toNumber());
+    expect(pending.params.inactivityEpochs.toNumber()).to.equal(3);
+    expect(pending.params.disputeWindowEpochs.toNumber()).to.equal(2);
+    expect(pending.params.usdMicrosPerGb.toNumber()).to.equal(6_000_000);
+    expect(pending.params.priceFeed.toBase58()).to.equal(MOCK_PRICE_FEED.toBase58());
+
     const config = await program.account.storageConfig.fetch(storageConfigPda);
-    expect(config.solPerGb.toNumber()).to.equal(newSolPerGb.toNumber());
-    expect(config.treasuryFeePercent.toNumber()).to.equal(newTreasuryFeePercent.toNumber());
-    expect(config.nodeFeePercent.toNumber()).to.equal(newNodeFeePercent.toNumber());
-    expect(config.shardMinMb.toNumber()).to.equal(newShardMinMb.toNumber());
-    expect(config.epochsTotal.toNumber()).to.equal(newEpochsTotal.toNumber());
-    expect(config.slashPenaltyPercent.toNumber()).to.equal(newSlashPenaltyPercent.toNumber());
-    expect(config.minShardCount).to.equal(newMinShardCount);
-    expect(config.maxShardCount).to.equal(newMaxShardCount);
-    expect(config.slotsPerEpoch.toNumber()).to.equal(newSlotsPerEpoch.toNumber());
-    expect(config.minNodeStake.toNumber()).to.equal(newMinNodeStake.toNumber());
-    expect(config.replacementTimeoutEpochs.toNumber()).to.equal(newReplacementTimeoutEpochs.toNumber());
-
-    console.log("Configuration Updated Successfully. Tx Hash:", tx);
+    expect(config.solPerGb.toNumber()).to.equal(configBefore.solPerGb.toNumber());
+
+    // Applying before the activation epoch is rejected
+    try {
+      await program.methods
+        .applyConfigChange()
+        .accounts({
+          proposer: admin.publicKey,
+          caller: user.publicKey,
+        })
+        .signers([userSig])
+        .rpc();
+      expect.fail("apply_config_change should wait for the activation epoch");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("TimelockNotExpired");
+    }
+
+    await program.methods
+      .cancelConfigChange()
+      .accounts({
+        authority: admin.publicKey,
+      })
+      .signers([adminSig])
+      .rpc();
+
+    const pendingAccountInfo = await program.provider.connection.getAccountInfo(pendingConfigPda);
+    expect(pendingAccountInfo).to.be.null;
+
+    console.log("Configuration Change Queued and Cancelled Successfully. Tx Hash:", tx);
+  });
+
+  it("Lists a payment mint and timelocks its repricing", async () => {
+    // Create a bare SPL token mint with InitializeMint2 (tag 20)
+    const tokenProgramId = new PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    const mint = Keypair.generate();
+    const initializeMint = new anchor.web3.TransactionInstruction({
+      programId: tokenProgramId,
+      keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+      data: Buffer.concat([Buffer.from([20, 6]), admin.publicKey.toBuffer(), Buffer.from([0])]),
+    });
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.createAccount({
+          fromPubkey: provider.wallet.publicKey,
+          newAccountPubkey: mint.publicKey,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(82),
+          space: 82,
+          programId: tokenProgramId,
+        }),
+        initializeMint
+      ),
+      [mint]
+    );
+
+    const [mintPricingPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_pricing"), mint.publicKey.toBuffer()],
+      program.programId
+    );
+
+    // Only the config authority may list a mint
+    try {
+      await program.methods
+        .setMintPricing(new anchor.BN(1_000_000), true)
+        .accounts({
+          mint: mint.publicKey,
+          authority: user.publicKey,
+        })
+        .signers([userSig])
+        .rpc();
+      expect.fail("set_mint_pricing should reject a non-authority signer");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    // A new listing is priced immediately
+    await program.methods
+      .setMintPricing(new anchor.BN(1_000_000), true)
+      .accounts({
+        mint: mint.publicKey,
+        authority: admin.publicKey,
+      })
+      .signers([adminSig])
+      .rpc();
+
+    let pricing = await program.account.mintPricing.fetch(mintPricingPda);
+    expect(pricing.mint.toBase58()).to.equal(mint.publicKey.toBase58());
+    expect(pricing.pricePerGb.toNumber()).to.equal(1_000_000);
+    expect(pricing.pendingPricePerGb.toNumber()).to.equal(1_000_000);
+    expect(pricing.isEnabled).to.be.true;
+
+    // A repricing waits for the config timelock
+    const config = await program.account.storageConfig.fetch(storageConfigPda);
+    await waitForEpochRoom();
+    await program.methods
+      .setMintPricing(new anchor.BN(2_000_000), true)
+      .accounts({
+        mint: mint.publicKey,
+        authority: admin.publicKey,
+      })
+      .signers([adminSig])
+      .rpc();
+
+    pricing = await program.account.mintPricing.fetch(mintPricingPda);
+    const currentEpoch = Math.floor(
+      (await provider.connection.getSlot()) / config.slotsPerEpoch.toNumber()
+    );
+    expect(pricing.pricePerGb.toNumber()).to.equal(1_000_000);
+    expect(pricing.pendingPricePerGb.toNumber()).to.equal(2_000_000);
+    expect(pricing.activationEpoch.toNumber()).to.be.greaterThan(currentEpoch);
+    expect(pricing.activationEpoch.toNumber()).to.be.at.most(
+      currentEpoch + config.configTimelockEpochs.toNumber()
+    );
   });
 });
@@ -5,10 +5,10 @@
 /// data as locally stored.
 use crate::db::Database;
 use crate::error::ApiError;
+use crate::models::HashAlgo;
 use std::sync::Arc;
 use tokio::sync::Mutex as TokioMutex;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use solana_sdk::pubkey::Pubkey;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -22,7 +22,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 pub struct DataMetadata {
     key: String,           // Unique identifier for the data
     format: String,        // Data format (e.g., JSON, binary)
-    hash: String,          // SHA-256 hash of the data for integrity verification
+    hash: String,          // Hex digest of the data for integrity verification
+    #[serde(default)]
+    hash_algo: HashAlgo,   // Algorithm that produced `hash`
     timestamp: u64,        // Unix timestamp of when the data was stored
     origin_pubkey: String, // Public key of the data originator
     upload_pda: String,    // Solana program-derived address for upload tracking
@@ -71,7 +73,7 @@ impl DataStore {
 
     /// Stores data and its metadata in the database.
     ///
-    /// This method computes a SHA-256 hash of the data, generates metadata with the
+    /// This method hashes the data with the upload's algorithm, generates metadata with the
     /// provided key, format, origin public key, and upload PDA, and stores both the data
     /// and serialized metadata in RocksDB. It also marks the key as locally stored.
     ///
@@ -79,6 +81,7 @@ impl DataStore {
     ///
     /// * `key` - The unique identifier for the data.
     /// * `data` - The raw data to store (as a byte slice).
+    /// * `hash_algo` - The hash algorithm recorded on the upload.
     /// * `format` - The format of the data (e.g., "text", "json").
     /// * `origin_pubkey` - The Solana public key of the data originator.
     /// * `upload_pda` - The Solana program-derived address for the upload.
@@ -90,10 +93,10 @@ impl DataStore {
     ///
     /// # Workflow
     ///
-    /// 1. **Hash Computation**: Calculates the SHA-256 hash of the input data.
+    /// 1. **Hash Computation**: Calculates the `hash_algo` digest of the input data.
     /// 2. **Timestamp Generation**: Retrieves the current Unix timestamp.
     /// 3. **Metadata Creation**: Constructs a `DataMetadata` struct with the key,
-    ///    format, hash, timestamp, origin public key, and upload PDA.
+    ///    format, hash, hash algorithm, timestamp, origin public key, and upload PDA.
     /// 4. **Serialization**: Serializes the metadata to JSON.
     /// 5. **Storage**: Stores the data under `data:{key}` and metadata under
     ///    `metadata:{key}` in RocksDB.
@@ -113,6 +116,7 @@ impl DataStore {
     /// use solana_sdk::signature::Keypair;
     /// use crate::db::Database;
     /// use crate::data_store::DataStore;
+    /// use crate::models::HashAlgo;
     ///
     /// #[tokio::main]
     /// async fn main() {
@@ -124,7 +128,7 @@ impl DataStore {
     ///     let origin_pubkey = Keypair::new().pubkey();
     ///     let upload_pda = "7b8f4a2e9c1d4b3e8f5c3a7b9e2d1f4a";
     ///
-    ///     data_store.store_data(key, data, format, origin_pubkey, upload_pda)
+    ///     data_store.store_data(key, data, HashAlgo::Sha256, format, origin_pubkey, upload_pda)
     ///         .await
     ///         .unwrap();
     /// }
@@ -133,11 +137,12 @@ impl DataStore {
         &self,
         key: &str,
         data: &[u8],
+        hash_algo: HashAlgo,
         format: &str,
         origin_pubkey: Pubkey,
         upload_pda: &str,
     ) -> Result<(), ApiError> {
-        let hash = format!("{:x}", Sha256::digest(data));
+        let hash = hash_algo.digest_hex(data);
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map_err(|e| ApiError::InternalError(e.to_string()))?
@@ -147,6 +152,7 @@ impl DataStore {
             key: key.to_string(),
             format: format.to_string(),
             hash: hash.clone(),
+            hash_algo,
             timestamp,
             origin_pubkey: origin_pubkey.to_string(),
             upload_pda: upload_pda.to_string(),
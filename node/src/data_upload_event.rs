@@ -4,6 +4,7 @@
 /// parse upload events, and verify payments. The module includes the `UploadEventListener` for
 /// capturing events and the `UploadEventConsumer` for validating and managing them.
 use crate::error::ApiError;
+use crate::models::HashAlgo;
 use base64::Engine;
 use dashmap::DashMap;
 use log::{debug, error, info, trace, warn};
@@ -25,10 +26,15 @@ use tokio::time::timeout;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UploadEvent {
     pub upload_pda: Pubkey,         // Program-derived address for the upload
-    pub data_hash: String,          // SHA-256 hash of the uploaded data
+    pub data_hash: String,          // Hex digest of the uploaded data
+    pub hash_algo: HashAlgo,        // Algorithm that produced `data_hash`
     pub size_bytes: u64,            // Size of the data in bytes
     pub shard_count: u8,            // Number of shards for the data
+    pub replication_factor: u8,     // Nodes requested per shard
+    pub data_shards: u8,            // Reed-Solomon data fragments, 0 when replicated
+    pub parity_shards: u8,          // Reed-Solomon parity fragments
     pub payer: Pubkey,              // Public key of the payer
+    pub sponsor: Pubkey,            // Fee sponsor, or default for none
     pub nodes: Vec<Pubkey>,         // List of node public keys assigned to store the data
     pub storage_duration_days: u64, // Duration for which the data should be stored
     pub timestamp: i64,             // Unix timestamp of the event
@@ -12,7 +12,6 @@ use tokio::sync::Mutex as TokioMutex;
 use borsh::BorshDeserialize;
 use log::{debug, error, info, trace, warn};
 use rocksdb::DB;
-use sha2::{Digest, Sha256};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Keypair;
@@ -113,12 +112,12 @@ pub async fn set_value(
         payload.key
     );
     debug!(
-        "Payload details: key={}, hash={}, format={}, upload_pda={}",
-        payload.key, payload.hash, payload.format, payload.upload_pda
+        "Payload details: key={}, hash={}, hash_algo={:?}, format={}, upload_pda={}",
+        payload.key, payload.hash, payload.hash_algo, payload.format, payload.upload_pda
     );
 
-    // Verify the provided hash matches the computed SHA-256 hash of the data
-    let computed_hash = format!("{:x}", Sha256::digest(payload.data.clone()));
+    // Verify the provided hash matches the data under the payload's hash algorithm
+    let computed_hash = payload.hash_algo.digest_hex(&payload.data);
     if computed_hash != payload.hash {
         warn!(
             "Hash verification failed: computed={}, provided={}",
@@ -164,12 +163,12 @@ pub async fn set_value(
         })?;
     debug!("Retrieved upload event for PDA: {}", upload_pda);
 
-    // Verify the event's data hash matches the provided hash
-    if event.data_hash != payload.hash {
+    // Verify the event's data hash and hash algorithm match the provided ones
+    if event.data_hash != payload.hash || event.hash_algo != payload.hash_algo {
         event_map.insert(upload_pda, event.clone());
         warn!(
-            "Event data hash mismatch: event_hash={}, provided_hash={}",
-            event.data_hash, payload.hash
+            "Event data hash mismatch: event_hash={} ({:?}), provided_hash={} ({:?})",
+            event.data_hash, event.hash_algo, payload.hash, payload.hash_algo
         );
         return Err(ApiError::InvalidHash);
     }
@@ -192,6 +191,7 @@ pub async fn set_value(
         .store_data(
             &payload.key,
             &payload.data,
+            payload.hash_algo,
             &payload.format,
             config.node_pubkey,
             &payload.upload_pda,
@@ -215,13 +215,14 @@ pub async fn set_value(
         let key = payload.key.clone();
         let data = payload.data.clone();
         let format = payload.format.clone();
+        let hash_algo = payload.hash_algo;
         let origin_pubkey = config.node_pubkey;
         let upload_pda = payload.upload_pda.clone();
         async move {
             trace!("Acquiring network manager lock for gossiping key: {}", key);
             let mut network_manager = network_manager.lock().await;
             network_manager
-                .gossip_data(&key, &data, origin_pubkey, &upload_pda, &format)
+                .gossip_data(&key, &data, hash_algo, origin_pubkey, &upload_pda, &format)
                 .await;
             info!("Gossiped data for key: {}", key);
         }
@@ -332,6 +333,7 @@ pub async fn set_value(
             payload.hash.clone(),
             shard_id,
             upload_pda,
+            upload_account.payment_mint,
             storage_config_pubkey,
             treasury_pubkey,
         )
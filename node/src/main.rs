@@ -8,7 +8,7 @@
 /// HTTP request handling, ensuring robust operation of the decentralized storage node.
 /// Logs are written to `./logs/node.log.txt` in JSON format with rotation for audit purposes,
 /// and colored console output is preserved for real-time debugging.
-use ::libp2p::{identity, PeerId};
+use ::libp2p::{identity, multiaddr::Protocol, Multiaddr, PeerId};
 use actix_web::{web, App, HttpServer};
 use std::sync::Arc;
 use tokio::sync::Mutex as TokioMutex;
@@ -18,7 +18,7 @@ use dashmap::DashMap;
 use data_upload_event::UploadEventConsumer;
 use dotenv::dotenv;
 use env_logger::Builder;
-use log::{error, info, LevelFilter};
+use log::{error, info, warn, LevelFilter};
 use serde_json::json;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::commitment_config::CommitmentConfig;
@@ -36,6 +36,7 @@ use crate::data_upload_event::{EventListenerConfig, UploadEvent, UploadEventList
 use crate::db::Database;
 use crate::handlers::{get_value, health, set_value};
 use crate::network_manager::{NetworkManager, PeerInfo};
+use crate::solad_client::SoladClient;
 
 mod data_store;
 mod data_upload_event;
@@ -118,8 +119,60 @@ fn setup_logging() -> std::io::Result<()> {
 
 /// Sets up the `NetworkManager` for peer-to-peer communication and gossip handling.
 ///
-/// This function initializes a Solana RPC client, generates a libp2p keypair, creates
-/// placeholder peer information, and constructs a `NetworkManager` instance. It also
+/// Loads bootstrap peers from the `NodeMetadata` accounts registered nodes publish on chain.
+///
+/// The node's own entry and entries with unparsable multiaddrs are skipped. When a multiaddr
+/// ends in `/p2p/<peer id>` that id is used; otherwise a placeholder id is generated, as for
+/// `SEED_NODES`.
+///
+/// # Arguments
+///
+/// * `config` - Configuration holding the Solana RPC URL and program ID.
+/// * `payer` - Keypair of this node, used to build the client and skip its own entry.
+/// * `now` - Timestamp recorded as each peer's `last_seen`.
+///
+/// # Returns
+///
+/// * `Vec<PeerInfo>` - The peers found on chain; empty if the query fails.
+async fn peers_from_chain(config: &EventListenerConfig, payer: Keypair, now: u64) -> Vec<PeerInfo> {
+    let node_pubkey = payer.pubkey();
+    let metadata = match SoladClient::new(&config.http_url, Arc::new(payer), config.program_id).await {
+        Ok(client) => client.fetch_node_metadata().await,
+        Err(e) => Err(e),
+    };
+    let metadata = match metadata {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            warn!("Failed to load node metadata from chain: {}", e);
+            return Vec::new();
+        }
+    };
+
+    metadata
+        .into_iter()
+        .filter(|metadata| metadata.owner != node_pubkey)
+        .filter_map(|metadata| {
+            let multiaddr: Multiaddr = metadata.multiaddr.parse().ok()?;
+            let peer_id = multiaddr
+                .iter()
+                .find_map(|protocol| match protocol {
+                    Protocol::P2p(hash) => PeerId::from_multihash(hash).ok(),
+                    _ => None,
+                })
+                .unwrap_or_else(|| PeerId::from_public_key(&identity::Keypair::generate_ed25519().public()));
+            Some(PeerInfo {
+                pubkey: metadata.owner,
+                multiaddr,
+                peer_id,
+                last_seen: now,
+            })
+        })
+        .collect()
+}
+
+/// This function initializes a Solana RPC client, generates a libp2p keypair, loads peers
+/// from on-chain node metadata (falling back to `SEED_NODES`, then to a placeholder peer),
+/// and constructs a `NetworkManager` instance. It also
 /// spawns an asynchronous task to handle receiving and processing gossiped data.
 ///
 /// # Arguments
@@ -151,11 +204,18 @@ async fn setup_network_manager(
     // Load NODE_SOLANA_PRIVKEY as a Pubkey for peers
     let node_pubkey_str =
         env::var("NODE_SOLANA_PRIVKEY").expect("NODE_SOLANA_PRIVKEY environment variable not set");
-    let node_pubkey = Keypair::from_base58_string(&node_pubkey_str).pubkey();
+    let node_keypair = Keypair::from_base58_string(&node_pubkey_str);
+    let node_pubkey = node_keypair.pubkey();
+
+    // Peers advertised on chain take precedence over SEED_NODES
+    let chain_peers = peers_from_chain(config, node_keypair, now).await;
+    info!("Loaded {} peers from on-chain node metadata", chain_peers.len());
 
     // Peers (using NODE_SOLANA_PRIVKEY as pubkey)
     let seed_nodes = env::var("SEED_NODES").unwrap_or_default();
-    let peers = if seed_nodes.is_empty() {
+    let peers = if !chain_peers.is_empty() {
+        chain_peers
+    } else if seed_nodes.is_empty() {
         // Standalone mode with placeholder peer
         vec![PeerInfo {
             pubkey: node_pubkey,
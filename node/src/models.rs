@@ -1,7 +1,10 @@
 /// This module defines data structures for API request payloads in a decentralized
 /// storage network. It includes structs for querying keys and submitting key-value
 /// data with validation rules to ensure data integrity.
+use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
 use validator::Validate;
 
 /// Represents a query parameter for retrieving a value by key.
@@ -12,6 +15,31 @@ pub struct KeyQuery {
     pub key: String, // The key to query for data retrieval
 }
 
+/// Digest algorithm that produced an upload's data hash.
+///
+/// Mirrors `HashAlgo` in the contract, where it is recorded on the upload. Every algorithm
+/// yields a 32-byte digest, carried as a lowercase hex string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    Blake3,
+    Keccak256,
+}
+
+impl HashAlgo {
+    /// Returns the lowercase hex digest of `data`.
+    pub fn digest_hex(&self, data: &[u8]) -> String {
+        match self {
+            HashAlgo::Sha256 => format!("{:x}", Sha256::digest(data)),
+            HashAlgo::Blake3 => blake3::hash(data).to_hex().to_string(),
+            HashAlgo::Keccak256 => format!("{:x}", Keccak256::digest(data)),
+        }
+    }
+}
+
 /// Represents the payload for storing a key-value pair in the storage network.
 ///
 /// This struct is used in POST requests to submit data for storage, including metadata
@@ -25,12 +53,18 @@ pub struct KeyValuePayload {
     #[validate(length(min = 1, message = "key cannot be empty"))]
     pub key: String,
 
-    /// The SHA-256 hash of the data for integrity verification.
+    /// The hex digest of the data for integrity verification.
     ///
     /// Must be a non-empty string.
     #[validate(length(min = 1, message = "hash cannot be empty"))]
     pub hash: String,
 
+    /// The algorithm that produced `hash`; must match the one recorded on the upload.
+    ///
+    /// Defaults to SHA-256.
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
+
     /// The raw data to be stored.
     ///
     /// Must be a non-empty byte vector.
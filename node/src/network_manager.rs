@@ -33,12 +33,30 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use crate::data_store::DataStore;
 use crate::db::Database;
 use crate::error::ApiError;
-use crate::solad_client::SoladClient;
+use crate::models::HashAlgo;
+use crate::solad_client::{node_metadata_from_env, SoladClient};
 
-// Node registry just like in contract
+// Node registry header just like in contract
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
 pub struct NodeRegistry {
-    pub nodes: Vec<Pubkey>, // List of registered node public keys
+    pub node_count: u32,   // Registered nodes across all pages
+    pub active_count: u32, // Registered nodes that can receive new shards
+    pub page_count: u32,   // Pages allocated so far
+    pub open_page: u32,    // Page new registrations are added to
+}
+
+// One page of the node registry, at [b"registry_page", index as u32 LE]
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct RegistryPage {
+    pub index: u32,
+    pub active_count: u32,
+    pub entries: Vec<RegistryEntry>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct RegistryEntry {
+    pub node: Pubkey,
+    pub is_active: bool,
 }
 
 // Structure to hold peer information with public key, address, and activity tracking
@@ -59,7 +77,9 @@ struct GossipMessage {
     origin_pubkey: String, // Public key of the data originator
     upload_pda: String,    // Program-derived address for upload tracking
     timestamp: u64,        // Timestamp for replay protection
-    hash: String,          // SHA-256 hash of data for integrity
+    hash: String,          // Hex digest of data for integrity
+    #[serde(default)]
+    hash_algo: HashAlgo,   // Algorithm that produced `hash`, from the upload
 }
 
 // Message structure for peer discovery
@@ -152,7 +172,7 @@ impl NetworkManager {
                 info!("Registering node with stake at PDA: {}", node_pda);
                 let (storage_config_pubkey, _) = Pubkey::find_program_address(&[b"storage_config".as_ref()], &program_id);
                 trace!("Registering node with storage config pubkey: {}", storage_config_pubkey);
-                solad_client.register_node(1_000_000_000, storage_config_pubkey)
+                solad_client.register_node(1_000_000_000, storage_config_pubkey, node_metadata_from_env())
                     .await
                     .map_err(|e| {
                         error!("Failed to register node for PDA {}: {}", node_pda, e);
@@ -589,7 +609,7 @@ impl NetworkManager {
                         // Process gossip message
                         else if let Ok(gossip_msg) = serde_json::from_slice::<GossipMessage>(&message.data) {
                             trace!("Processing gossip message for key: {}", gossip_msg.key);
-                            let computed_hash = format!("{:x}", Sha256::digest(&gossip_msg.data));
+                            let computed_hash = gossip_msg.hash_algo.digest_hex(&gossip_msg.data);
                             if computed_hash != gossip_msg.hash {
                                 peer_reputation_clone.lock().await.entry(source)
                                     .and_modify(|r| *r -= 10)
@@ -721,6 +741,7 @@ impl NetworkManager {
         &mut self,
         key: &str,             // Data identifier
         data: &[u8],           // Data payload
+        hash_algo: HashAlgo,   // Hash algorithm recorded on the upload
         origin_pubkey: Pubkey, // Originator's public key
         upload_pda: &str,      // Upload PDA
         format: &str,          // Data format
@@ -742,7 +763,7 @@ impl NetworkManager {
 
         // Prepare gossip message
         let topic = gossipsub::IdentTopic::new("network-shard");
-        let hash = format!("{:x}", Sha256::digest(data));
+        let hash = hash_algo.digest_hex(data);
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -755,6 +776,7 @@ impl NetworkManager {
             upload_pda: upload_pda.to_string(),
             timestamp,
             hash,
+            hash_algo,
         };
         trace!("Prepared gossip message for key: {}", key);
         let message_bytes = serde_json::to_vec(&message).expect("Serialize gossip message");
@@ -865,6 +887,7 @@ impl NetworkManager {
                 .store_data(
                     &message.key,
                     &message.data,
+                    message.hash_algo,
                     &message.format,
                     origin_pubkey,
                     &message.upload_pda,
@@ -898,29 +921,10 @@ impl NetworkManager {
     ) -> Result<Vec<PeerInfo>, ApiError> {
         trace!("Validating active peers");
         // Fetch node registry
-        let (registry_pda, _bump) = Pubkey::find_program_address(&[b"node_registry"], program_id);
-        trace!("Fetching node registry for PDA: {}", registry_pda);
-        let registry_account = rpc_client.get_account(&registry_pda).await.map_err(|e| {
-            error!("Failed to fetch node registry: {}", e);
-            ApiError::NetworkError(anyhow::anyhow!("Failed to fetch node registry: {}", e))
-        })?;
-        let mut account_data = &registry_account.data[8..];
-        let node_registry: NodeRegistry =
-            NodeRegistry::deserialize(&mut account_data).map_err(|e| {
-                error!("Failed to deserialize node registry: {}", e);
-                ApiError::NetworkError(anyhow::anyhow!(
-                    "Failed to deserialize node registry: {}",
-                    e
-                ))
-            })?;
-        debug!(
-            "Fetched node registry with {} nodes",
-            node_registry.nodes.len()
-        );
+        let registered_nodes = Self::fetch_registered_nodes(&rpc_client, program_id).await?;
 
         // Fetch node accounts
-        let node_pdas: Vec<Pubkey> = node_registry
-            .nodes
+        let node_pdas: Vec<Pubkey> = registered_nodes
             .iter()
             .map(|pubkey| Pubkey::find_program_address(&[b"node", pubkey.as_ref()], program_id).0)
             .collect();
@@ -935,7 +939,7 @@ impl NetworkManager {
 
         // Identify active nodes
         let mut active_nodes = HashSet::new();
-        for (pubkey, account_opt) in node_registry.nodes.iter().zip(node_accounts.iter()) {
+        for (pubkey, account_opt) in registered_nodes.iter().zip(node_accounts.iter()) {
             if let Some(account) = account_opt {
                 if let Ok(node_data) = serde_json::from_slice::<Node>(&account.data) {
                     if node_data.is_active {
@@ -989,6 +993,58 @@ impl NetworkManager {
         Ok(valid_peers)
     }
 
+    // Reads every node listed in the paged registry: the header gives the page count, then
+    // all pages are fetched in one batched RPC call
+    async fn fetch_registered_nodes(
+        rpc_client: &RpcClient,
+        program_id: &Pubkey,
+    ) -> Result<Vec<Pubkey>, ApiError> {
+        let (registry_pda, _bump) = Pubkey::find_program_address(&[b"node_registry"], program_id);
+        trace!("Fetching node registry for PDA: {}", registry_pda);
+        let registry_account = rpc_client.get_account(&registry_pda).await.map_err(|e| {
+            error!("Failed to fetch node registry: {}", e);
+            ApiError::NetworkError(anyhow::anyhow!("Failed to fetch node registry: {}", e))
+        })?;
+        let mut account_data = &registry_account.data[8..];
+        let node_registry: NodeRegistry =
+            NodeRegistry::deserialize(&mut account_data).map_err(|e| {
+                error!("Failed to deserialize node registry: {}", e);
+                ApiError::NetworkError(anyhow::anyhow!(
+                    "Failed to deserialize node registry: {}",
+                    e
+                ))
+            })?;
+
+        let page_pdas: Vec<Pubkey> = (0..node_registry.page_count)
+            .map(|index| {
+                Pubkey::find_program_address(&[b"registry_page", &index.to_le_bytes()], program_id).0
+            })
+            .collect();
+        let page_accounts = rpc_client
+            .get_multiple_accounts(&page_pdas)
+            .await
+            .map_err(|e| {
+                error!("Failed to fetch registry pages: {}", e);
+                ApiError::NetworkError(anyhow::anyhow!("Failed to fetch registry pages: {}", e))
+            })?;
+
+        let mut nodes = Vec::with_capacity(node_registry.node_count as usize);
+        for account in page_accounts.into_iter().flatten() {
+            // Pages are allocated at full size, so only the serialized prefix is read
+            let mut page_data = &account.data[8..];
+            match RegistryPage::deserialize(&mut page_data) {
+                Ok(page) => nodes.extend(page.entries.into_iter().map(|entry| entry.node)),
+                Err(e) => warn!("Skipping unreadable registry page: {}", e),
+            }
+        }
+        debug!(
+            "Fetched node registry with {} nodes across {} pages",
+            nodes.len(),
+            node_registry.page_count
+        );
+        Ok(nodes)
+    }
+
     // Computes hash for discovery message
     fn compute_message_hash(peers: &[(Pubkey, Multiaddr, String)], timestamp: u64) -> Vec<u8> {
         trace!("Computing message hash for discovery message");
@@ -1011,25 +1067,7 @@ impl NetworkManager {
         program_id: &Pubkey,
     ) -> Result<Pubkey, ApiError> {
         trace!("Verifying discovery message signature");
-        let (registry_pda, _bump) = Pubkey::find_program_address(&[b"node_registry"], program_id);
-        trace!("Fetching node registry for PDA: {}", registry_pda);
-        let registry_account = rpc_client.get_account(&registry_pda).await.map_err(|e| {
-            error!("Failed to fetch node registry: {}", e);
-            ApiError::NetworkError(anyhow::anyhow!("Failed to fetch node registry: {}", e))
-        })?;
-        let mut account_data = &registry_account.data[8..];
-        let node_registry: NodeRegistry =
-            NodeRegistry::deserialize(&mut account_data).map_err(|e| {
-                error!("Failed to deserialize node registry: {}", e);
-                ApiError::NetworkError(anyhow::anyhow!(
-                    "Failed to deserialize node registry: {}",
-                    e
-                ))
-            })?;
-        debug!(
-            "Fetched node registry with {} nodes",
-            node_registry.nodes.len()
-        );
+        let registered_nodes = Self::fetch_registered_nodes(&rpc_client, program_id).await?;
 
         let hash = Self::compute_message_hash(&message.peers, message.timestamp);
         if message.signature.len() != 64 {
@@ -1047,7 +1085,7 @@ impl NetworkManager {
         signature_bytes.copy_from_slice(&message.signature);
         let signature = Signature::from(signature_bytes);
 
-        for pubkey in node_registry.nodes {
+        for pubkey in registered_nodes {
             if signature.verify(&pubkey.to_bytes(), &hash) {
                 debug!("Signature verified for pubkey: {}", pubkey);
                 return Ok(pubkey);
@@ -1068,6 +1106,17 @@ struct Node {
     stake_amount: u64,       // Staked amount in lamports
     upload_count: u64,       // Number of uploads
     last_pos_time: i64,      // Last proof-of-storage time
-    last_claimed_epoch: u64, // Last epoch rewards claimed
     is_active: bool,         // Node active status
+    unbonding_amount: u64,   // Unstaked lamports still locked in the stake escrow
+    unbonding_release_epoch: u64, // Epoch the unbonding stake becomes withdrawable
+    delegated_stake: u64,    // Lamports backing delegator shares
+    delegator_shares: u64,   // Outstanding delegator shares
+    unbonding_shares: u64,   // Delegator shares being undelegated
+    commission_percent: u8,  // Operator cut of delegator rewards
+    reward_per_share: u128,  // Scaled delegator reward accumulator
+    capacity_mb: u64,        // Declared storage capacity in megabytes
+    committed_mb: u64,       // Shard megabytes currently assigned
+    registry_page: u32,      // Registry page listing the node
+    pending_commission_percent: u8, // Commission raise queued by set_commission
+    commission_effective_slot: u64, // Slot the queued raise applies from
 }
@@ -11,9 +11,15 @@ use anchor_client::{
     },
     Client, Cluster, Program,
 };
-use anchor_lang::{prelude::AccountMeta, solana_program::system_program};
+use anchor_lang::{
+    prelude::{pubkey, AccountMeta},
+    solana_program::system_program,
+};
 use anyhow::Result;
 use borsh::{BorshDeserialize, BorshSerialize};
+use contract::states::{NodeMetadata, NodeMetadataParams, NodeRegistry};
+use crate::models::HashAlgo;
+use std::env;
 use std::sync::Arc;
 
 // Constants for seed values (must match the Solad program)
@@ -21,6 +27,30 @@ const NODE_REGISTRY_SEED: &[u8] = b"node_registry";
 const ESCROW_SEED: &[u8] = b"escrow";
 const NODE_SEED: &[u8] = b"node";
 const STAKE_ESCROW_SEED: &[u8] = b"stake_escrow";
+const SHARD_CLAIM_SEED: &[u8] = b"shard_claim";
+const NODE_METADATA_SEED: &[u8] = b"node_metadata";
+const REGISTRY_PAGE_SEED: &[u8] = b"registry_page";
+const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xQSX6rsE3Xgd3BjA8f");
+
+/// Builds the metadata this node advertises on chain from the environment.
+///
+/// Reads `NODE_HTTP_URL`, `NODE_MULTIADDR`, `NODE_REGION` and `NODE_CAPACITY_MB`,
+/// falling back to local defaults, and reports the crate version as the node version.
+pub fn node_metadata_from_env() -> NodeMetadataParams {
+    NodeMetadataParams {
+        http_url: env::var("NODE_HTTP_URL").unwrap_or_else(|_| "http://127.0.0.1:8080".to_string()),
+        multiaddr: env::var("NODE_MULTIADDR")
+            .unwrap_or_else(|_| "/ip4/127.0.0.1/tcp/4001".to_string()),
+        region: env::var("NODE_REGION").unwrap_or_default(),
+        capacity_mb: env::var("NODE_CAPACITY_MB")
+            .ok()
+            .and_then(|capacity| capacity.parse().ok())
+            .unwrap_or(10_240),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
 
 /// Represents an upload account in the Solad program.
 ///
@@ -28,15 +58,30 @@ const STAKE_ESCROW_SEED: &[u8] = b"stake_escrow";
 /// shard count, payment details, and shard assignments.
 #[derive(Debug, BorshDeserialize, BorshSerialize)]
 pub struct Upload {
-    pub data_hash: String,          // SHA-256 hash of the uploaded data
+    pub data_hash: String,          // Hex digest of the uploaded data
+    pub hash_algo: HashAlgo,        // Algorithm that produced `data_hash`
     pub size_bytes: u64,            // Size of the data in bytes
+    pub merkle_root: [u8; 32],      // Root of the chunk Merkle tree committed at upload
+    pub chunk_size: u32,            // Bytes per Merkle leaf chunk
+    pub leaf_count: u32,            // Number of Merkle leaves
     pub shard_count: u8,            // Number of shards for the data
-    pub node_lamports: u64,         // Lamports allocated per node
-    pub payer: Pubkey,              // Public key of the payer
+    pub replication_factor: u8,     // Nodes requested per shard
+    pub data_shards: u8,            // Reed-Solomon data fragments, 0 when replicated
+    pub parity_shards: u8,          // Reed-Solomon parity fragments
+    pub node_lamports: u64,         // Node share in escrow, in units of the payment mint
+    pub payer: Pubkey,              // Public key of the payer, seeds the upload PDA
+    pub owner: Pubkey,              // Current owner, receives refunds
+    pub delegate: Pubkey,           // Owner's delegate, or default for none
+    pub sponsor: Pubkey,            // Fee sponsor taking refunds, or default for none
+    pub payment_mint: Pubkey,       // SPL token mint paid in, or default for lamports
     pub upload_time: i64,           // Unix timestamp of the upload
     pub storage_duration_days: u64, // Duration for which the data should be stored
     pub expiry_time: i64,           // Unix timestamp when the storage expires
+    pub term_start: i64,            // Start of the paid term, excluding lapses
     pub current_slot: u64,          // Current Solana slot at upload time
+    pub selection_slot: u64,        // Slot whose hash draws the upload's nodes
+    pub nodes_assigned: bool,       // Whether `assign_upload` has drawn the nodes
+    pub selection_seed: [u8; 32],   // Seed that fixed the candidate window and draw
     pub shards: Vec<ShardInfo>,     // List of shard assignments
 }
 
@@ -47,12 +92,34 @@ pub struct Upload {
 #[derive(Debug, BorshDeserialize, BorshSerialize)]
 pub struct ShardInfo {
     pub shard_id: u8,                            // Unique identifier for the shard
-    pub node_keys: [Pubkey; 3],                  // Public keys of nodes assigned to the shard
+    pub role: ShardRole,                         // Full copy, or data or parity fragment
+    pub commitment: DataCommitment,              // Merkle commitment PoS proves against
+    pub node_keys: Vec<Pubkey>,                  // Public keys of nodes assigned to the shard
     pub verified_count: u8,                      // Number of verified nodes
     pub size_mb: u64,                            // Size of the shard in megabytes
     pub challenger: Pubkey,                      // Public key of the challenger (if any)
     pub oversized_reports: Vec<OversizedReport>, // Reports of oversized data
-    pub rewarded_nodes: Vec<Pubkey>,             // Nodes that have claimed rewards
+    pub invalidated_epoch: u64,                  // Epoch the reports invalidated the shard
+    pub capacity_released: u8,                   // Bit i set once node_keys[i] released capacity
+    pub upload_released: u8,                     // Bit i set once node_keys[i] released its slot
+    pub proven_epochs: Vec<u32>,                 // Challenges node_keys[i] has answered
+    pub user_slashed: bool,                      // Whether the uploader was slashed for it
+}
+
+/// What a shard holds: a full copy of the data, or one Reed-Solomon fragment of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub enum ShardRole {
+    Replica,
+    Data,
+    Parity,
+}
+
+/// Chunk Merkle commitment of a shard's contents.
+#[derive(Debug, Clone, Copy, BorshDeserialize, BorshSerialize)]
+pub struct DataCommitment {
+    pub merkle_root: [u8; 32], // Root of the chunk Merkle tree
+    pub chunk_size: u32,       // Bytes per leaf chunk
+    pub leaf_count: u32,       // Number of leaves
 }
 
 /// Represents a report of oversized data for a shard.
@@ -119,11 +186,13 @@ impl SoladClient {
     ///
     /// Sends a transaction to the Solad program to register a node, staking the specified
     /// amount of lamports and associating it with a storage configuration account.
+    /// The node's endpoints, region and capacity are published in its metadata account.
     ///
     /// # Arguments
     ///
     /// * `stake_amount` - The amount of lamports to stake for the node.
     /// * `config_pubkey` - The public key of the storage configuration account.
+    /// * `metadata` - Endpoints, region, capacity and version the node advertises.
     ///
     /// # Returns
     ///
@@ -133,12 +202,12 @@ impl SoladClient {
     /// # Workflow
     ///
     /// 1. **PDA Derivation**: Derives program-derived addresses (PDAs) for the node,
-    ///    stake escrow, and node registry using predefined seeds.
+    ///    stake escrow, node metadata, node registry, and the registry's open page.
     /// 2. **Account Setup**: Constructs the account metas for the transaction, including
-    ///    the node PDA, stake escrow PDA, node registry PDA, payer, configuration public
-    ///    key, and system program.
+    ///    the node PDA, stake escrow PDA, node metadata PDA, node registry PDA, page PDA, payer,
+    ///    configuration public key, and system program.
     /// 3. **Instruction Building**: Creates a `RegisterNode` instruction with the stake
-    ///    amount.
+    ///    amount and metadata.
     /// 4. **Transaction Submission**: Sends the transaction to the Solana network, signed
     ///    by the payer.
     ///
@@ -157,7 +226,8 @@ impl SoladClient {
     ///     let client = SoladClient::new(rpc_url, payer, program_id).await?;
     ///     let stake_amount = 1_000_000_000;
     ///     let config_pubkey = Pubkey::new_unique();
-    ///     let signature = client.register_node(stake_amount, config_pubkey).await?;
+    ///     let metadata = node_metadata_from_env();
+    ///     let signature = client.register_node(stake_amount, config_pubkey, metadata).await?;
     ///     println!("Node registered with signature: {}", signature);
     ///     Ok(())
     /// }
@@ -166,6 +236,7 @@ impl SoladClient {
         &self,
         stake_amount: u64,
         config_pubkey: Pubkey,
+        metadata: NodeMetadataParams,
     ) -> Result<Signature> {
         // Derive PDAs
         let (node_pda, _node_bump) = Pubkey::find_program_address(
@@ -176,20 +247,39 @@ impl SoladClient {
             &[STAKE_ESCROW_SEED, self.payer.pubkey().as_ref()],
             &self.program.id(),
         );
+        let (node_metadata_pda, _metadata_bump) = Pubkey::find_program_address(
+            &[NODE_METADATA_SEED, node_pda.as_ref()],
+            &self.program.id(),
+        );
         let (node_registry_pda, _registry_bump) =
             Pubkey::find_program_address(&[NODE_REGISTRY_SEED], &self.program.id());
+        // New nodes are listed in the registry's open page
+        let open_page = self
+            .program
+            .account::<NodeRegistry>(node_registry_pda)
+            .await?
+            .open_page;
+        let (registry_page_pda, _page_bump) = Pubkey::find_program_address(
+            &[REGISTRY_PAGE_SEED, &open_page.to_le_bytes()],
+            &self.program.id(),
+        );
 
         // Build instruction
         let accounts = vec![
             AccountMeta::new(node_pda, false),
             AccountMeta::new(stake_escrow_pda, false),
+            AccountMeta::new(node_metadata_pda, false),
             AccountMeta::new(node_registry_pda, false),
+            AccountMeta::new(registry_page_pda, false),
             AccountMeta::new(self.payer.pubkey(), true),
             AccountMeta::new(config_pubkey, false),
             AccountMeta::new_readonly(system_program::ID, false),
         ];
 
-        let instruction_data = contract::instruction::RegisterNode { stake_amount };
+        let instruction_data = contract::instruction::RegisterNode {
+            stake_amount,
+            metadata,
+        };
 
         let signature = self
             .program
@@ -203,6 +293,20 @@ impl SoladClient {
         Ok(signature)
     }
 
+    /// Fetches the metadata every registered node advertises on chain.
+    ///
+    /// Used to bootstrap peers: each entry carries the node's libp2p multiaddr and
+    /// HTTP URL.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<NodeMetadata>>` - Returns the metadata accounts on success, or an
+    ///   error if the RPC query fails.
+    pub async fn fetch_node_metadata(&self) -> Result<Vec<NodeMetadata>> {
+        let accounts = self.program.accounts::<NodeMetadata>(vec![]).await?;
+        Ok(accounts.into_iter().map(|(_, metadata)| metadata).collect())
+    }
+
     /// Claims rewards for a node based on a data upload.
     ///
     /// Sends a transaction to the Solad program to claim rewards for a node assigned to
@@ -214,6 +318,8 @@ impl SoladClient {
     /// * `data_hash` - The SHA-256 hash of the uploaded data.
     /// * `shard_id` - The ID of the shard for which to claim rewards.
     /// * `upload_pda` - The program-derived address of the upload account.
+    /// * `payment_mint` - The upload's payment mint; token rewards go to the node's
+    ///   associated token account for it. `Pubkey::default()` for lamport uploads.
     /// * `config_pubkey` - The public key of the storage configuration account.
     /// * `treasury_pubkey` - The public key of the treasury account.
     ///
@@ -224,11 +330,11 @@ impl SoladClient {
     ///
     /// # Workflow
     ///
-    /// 1. **PDA Derivation**: Derives PDAs for the node, escrow, and stake escrow using
-    ///    the data hash, payer public key, and predefined seeds.
+    /// 1. **PDA Derivation**: Derives PDAs for the node, shard claim ledger, escrow, and
+    ///    stake escrow using the data hash, shard ID, payer public key, and predefined seeds.
     /// 2. **Account Setup**: Constructs the account metas for the transaction, including
-    ///    the upload PDA, node PDA, escrow PDA, configuration public key, treasury public
-    ///    key, stake escrow PDA, and system program.
+    ///    the upload PDA, node PDA, shard claim PDA, escrow PDA, configuration public key,
+    ///    treasury public key, stake escrow PDA, payer, and system program.
     /// 3. **Instruction Building**: Creates a `ClaimRewards` instruction with the data
     ///    hash and shard ID.
     /// 4. **Transaction Submission**: Sends the transaction to the Solana network, signed
@@ -252,7 +358,7 @@ impl SoladClient {
     ///     let upload_pda = Pubkey::new_unique();
     ///     let config_pubkey = Pubkey::new_unique();
     ///     let treasury_pubkey = Pubkey::new_unique();
-    ///     let signature = client.claim_rewards(data_hash, shard_id, upload_pda, config_pubkey, treasury_pubkey).await?;
+    ///     let signature = client.claim_rewards(data_hash, shard_id, upload_pda, Pubkey::default(), config_pubkey, treasury_pubkey).await?;
     ///     println!("Rewards claimed with signature: {}", signature);
     ///     Ok(())
     /// }
@@ -262,6 +368,7 @@ impl SoladClient {
         data_hash: String,
         shard_id: u8,
         upload_pda: Pubkey,
+        payment_mint: Pubkey,
         config_pubkey: Pubkey,
         treasury_pubkey: Pubkey,
     ) -> Result<Signature> {
@@ -270,6 +377,15 @@ impl SoladClient {
             &[NODE_SEED, self.payer.pubkey().as_ref()],
             &self.program.id(),
         );
+        let (shard_claim_pda, _claim_bump) = Pubkey::find_program_address(
+            &[
+                SHARD_CLAIM_SEED,
+                upload_pda.as_ref(),
+                &[shard_id],
+                node_pda.as_ref(),
+            ],
+            &self.program.id(),
+        );
         let (escrow_pda, _escrow_bump) = Pubkey::find_program_address(
             &[
                 ESCROW_SEED,
@@ -284,15 +400,31 @@ impl SoladClient {
         );
 
         // Build instruction
-        let accounts = vec![
-            AccountMeta::new_readonly(upload_pda, false),
+        let mut accounts = vec![
+            AccountMeta::new(upload_pda, false),
             AccountMeta::new(node_pda, false),
+            AccountMeta::new(shard_claim_pda, false),
             AccountMeta::new(escrow_pda, false),
             AccountMeta::new(config_pubkey, false),
             AccountMeta::new(treasury_pubkey, false),
             AccountMeta::new(stake_escrow_pda, false),
+            AccountMeta::new(self.payer.pubkey(), true),
             AccountMeta::new_readonly(system_program::ID, false),
         ];
+        // Token payout accounts; the program ID stands in for each one on lamport uploads
+        if payment_mint == Pubkey::default() {
+            accounts.extend((0..3).map(|_| AccountMeta::new_readonly(self.program.id(), false)));
+        } else {
+            accounts.push(AccountMeta::new(
+                associated_token_address(&escrow_pda, &payment_mint),
+                false,
+            ));
+            accounts.push(AccountMeta::new(
+                associated_token_address(&self.payer.pubkey(), &payment_mint),
+                false,
+            ));
+            accounts.push(AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false));
+        }
 
         let instruction_data = contract::instruction::ClaimRewards {
             data_hash,
@@ -311,3 +443,12 @@ impl SoladClient {
         Ok(signature)
     }
 }
+
+/// Derives the associated token account of `owner` for `mint`, where token rewards are paid.
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
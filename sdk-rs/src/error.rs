@@ -14,6 +14,8 @@ pub enum UserApiError {
     SolanaError(String),
     #[error("Upload PDA mismatch")]
     PdaMismatch,
+    #[error("Data hash does not match the data")]
+    HashMismatch,
 }
 
 impl UserApiError {
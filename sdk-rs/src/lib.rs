@@ -10,12 +10,14 @@ The `DataClient` is designed to communicate with Solad node endpoints via HTTP,
 ## Key Features
 
 - **Data Upload**: The `set_data` method performs a two-phase process:
-  1. On-chain: Creates and confirms an upload instruction using the Solad program.
+  1. On-chain: Creates and confirms an upload instruction using the Solad program, then
+     assigns the upload's nodes once its selection slot has passed.
   2. Off-chain: Sends data to a Solad node via HTTP POST.
 - **Data Retrieval**: The `get_data` method fetches data from a Solad node by key.
-- **PDA Management**: Derives and verifies Program-Derived Addresses (PDAs) for uploads, user keys, escrow, and node registry.
+- **Node Discovery**: `find_upload_targets` lists the nodes advertising endpoints and capacity on chain, and `DataClient::from_metadata` connects to one of them.
+- **PDA Management**: Derives and verifies Program-Derived Addresses (PDAs) for uploads, user keys, and escrow.
 - **Error Handling**: Defines `UserApiError` for handling failures in HTTP requests, Solana transactions, and PDA mismatches.
-- **Sharding Support**: Supports data sharding across multiple nodes, specified by `shard_count`.
+- **Sharding Support**: Supports data sharding across multiple nodes, specified by `shard_count`, each stored on `replication_factor` nodes.
 - **Flexible Configuration**: Allows customization of storage duration and treasury accounts.
 
 ## Usage
@@ -27,7 +29,7 @@ To use this module, initialize a `SoladClient` with a Solana RPC URL, payer keyp
 ```rust
 use std::sync::Arc;
 use solana_sdk::{pubkey::Pubkey, signature::Keypair};
-use solad_client::{DataClient, SoladClient, model::SetData};
+use solad_client::{DataClient, SoladClient, model::{HashAlgo, SetData}};
 use base64::prelude::*;
 
 #[tokio::main]
@@ -38,28 +40,29 @@ async fn main() -> anyhow::Result<()> {
     let program_id = Pubkey::new_unique();
     let solad_client = SoladClient::new(rpc_url, payer, program_id).await?;
 
-    // Initialize DataClient
-    let base_url = "http://localhost:8080";
-    let data_client = DataClient::new(base_url);
+    // Pick upload targets from on-chain node metadata and connect to the first
+    let targets = solad_client.find_upload_targets(1).await?;
+    let data_client = DataClient::from_metadata(&targets[0]);
 
     // Prepare data
     let data_bytes = b"Hello, Solad!".to_vec();
     let data_b64 = BASE64_STANDARD.encode(&data_bytes);
-    let data_hash = "sample_hash".to_string(); // Replace with actual hash
+    let data_hash = HashAlgo::Blake3.digest_hex(&data_bytes);
     let set_data = SetData {
         key: "example_key".to_string(),
         data: data_b64,
         hash: data_hash.clone(),
+        hash_algo: HashAlgo::Blake3,
         format: "text/plain".to_string(),
         upload_pda: Pubkey::new_unique().to_string(), // Replace with actual PDA
         shard: 3,
+        replication_factor: 3,
     };
 
     // Upload data
-    let nodes = vec![Pubkey::new_unique()];
     let treasury = Pubkey::new_unique();
     let result = data_client
-        .set_data(&set_data, &solad_client, 30, nodes, treasury)
+        .set_data(&set_data, &solad_client, 30, treasury)
         .await?;
 
     println!("Upload response: {:?}", result);
@@ -118,18 +121,26 @@ use anchor_client::{
         signature::Keypair,
         signer::Signer,
         system_program,
+        sysvar::slot_hashes,
     },
     Client, Cluster, Program,
 };
 use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
 use base64::prelude::*;
-use contract::instruction::UploadData;
+use contract::instruction::{AssignUpload, UploadData};
+use contract::selection::{challenge_seed_from, registry_window, upload_seed};
+use contract::states::{
+    DataCommitment, NodeMetadata, NodeRegistry, RegistryPage, StorageConfig, Upload,
+    MAX_UPLOAD_CANDIDATES, NODE_METADATA_SEED, NODE_REGISTRY_SEED, REGISTRY_PAGE_SEED,
+    USER_UPLOAD_KEYS_SEED,
+};
 use serde_json::Value;
 
 // Public modules
 pub mod error;
 pub mod event;
+pub mod merkle;
 pub mod model;
 
 /// Client for interacting with Solad nodes via HTTP.
@@ -153,15 +164,26 @@ impl DataClient {
         }
     }
 
+    /// Creates a new `DataClient` for the HTTP endpoint a node advertises on chain.
+    ///
+    /// # Arguments
+    /// * `metadata` - The node's metadata, e.g. from `SoladClient::find_upload_targets`.
+    ///
+    /// # Returns
+    /// A new `DataClient` instance.
+    pub fn from_metadata(metadata: &NodeMetadata) -> Self {
+        Self::new(&metadata.http_url)
+    }
+
     /// Uploads data to the Solad network and sets it on the specified node endpoint.
-    /// First, it creates and confirms an upload instruction on the Solad program,
-    /// then sends the data to the node endpoint via HTTP POST.
+    /// First, it creates and confirms an upload instruction on the Solad program, waits for
+    /// the upload's selection slot and assigns its nodes, then sends the data to the node
+    /// endpoint via HTTP POST.
     ///
     /// # Arguments
     /// * `data` - The data to upload, including key, data (base64-encoded), hash, format, and upload_pda.
     /// * `solad_client` - Reference to the SoladClient for on-chain interactions.
     /// * `storage_duration_days` - Duration to store the data in days.
-    /// * `nodes` - List of node public keys to assign shards to.
     /// * `treasury_pubkey` - Public key of the treasury account.
     ///
     /// # Returns
@@ -171,6 +193,7 @@ impl DataClient {
     /// # Errors
     /// Returns `UserApiError` for:
     /// - Invalid base64-encoded data.
+    /// - A `hash` that is not the `hash_algo` digest of the data.
     /// - PDA mismatch between derived and provided `upload_pda`.
     /// - Solana transaction failures (creation, sending, or confirmation).
     /// - HTTP request failures or non-success status codes.
@@ -179,7 +202,6 @@ impl DataClient {
         data: &SetData,
         solad_client: &SoladClient,
         storage_duration_days: u64,
-        nodes: Vec<Pubkey>,
         treasury_pubkey: Pubkey,
     ) -> Result<Value, UserApiError> {
         // Extract shard count from data
@@ -189,7 +211,12 @@ impl DataClient {
         // Decode base64-encoded data
         let data_bytes = BASE64_STANDARD.decode(&data.data)?;
         let data_hash = data.hash.clone();
+        if data.hash_algo.digest_hex(&data_bytes) != data_hash {
+            return Err(UserApiError::HashMismatch);
+        }
         let size_bytes = data_bytes.len() as u64;
+        // Commit the chunk Merkle root that nodes will later prove against
+        let commitment = merkle::commit(&data_bytes);
 
         // Derive upload PDA for the data
         let (upload_pda, _bump) = Pubkey::find_program_address(
@@ -214,8 +241,9 @@ impl DataClient {
                 size_bytes,
                 shard_count,
                 storage_duration_days,
-                treasury_pubkey,
-                nodes,
+                commitment,
+                data.hash_algo,
+                data.replication_factor,
             )
             .await
             .map_err(|e| UserApiError::SolanaError(format!("Failed to create upload instruction: {}", e)))?;
@@ -237,6 +265,26 @@ impl DataClient {
             .confirm_transaction(&signature)
             .map_err(|e| UserApiError::SolanaError(format!("Transaction confirmation failed: {}", e)))?;
 
+        // Draw the upload's nodes from the hash of its selection slot
+        let (assign_args, assign_accounts) = self
+            .create_assign_instruction(solad_client, data_hash.clone(), treasury_pubkey)
+            .await
+            .map_err(|e| UserApiError::SolanaError(format!("Failed to create assign instruction: {}", e)))?;
+        let signature = solad_client
+            .program
+            .request()
+            .accounts(assign_accounts)
+            .args(assign_args)
+            .signer(&solad_client.payer)
+            .send()
+            .await
+            .map_err(|e| UserApiError::SolanaError(format!("Failed to send transaction: {}", e)))?;
+        solad_client
+            .program
+            .rpc()
+            .confirm_transaction(&signature)
+            .map_err(|e| UserApiError::SolanaError(format!("Transaction confirmation failed: {}", e)))?;
+
         // Phase 2: Off-chain upload to node endpoint
         // Construct the API endpoint URL
         let url = format!("{}/api/set", self.base_url);
@@ -290,19 +338,22 @@ impl DataClient {
     ///
     /// # Arguments
     /// * `solad_client` - Reference to the SoladClient for program and payer access.
-    /// * `data_hash` - SHA-256 hash of the data.
+    /// * `data_hash` - Hex digest of the data.
     /// * `size_bytes` - Size of the data in bytes.
     /// * `shard_count` - Number of shards for the data.
     /// * `storage_duration_days` - Duration to store the data in days.
-    /// * `treasury_pubkey` - Public key of the treasury account.
-    /// * `nodes` - List of node public keys to assign shards to.
+    /// * `commitment` - Chunk Merkle root, chunk size, and leaf count of the data.
+    /// * `hash_algo` - Algorithm that produced `data_hash`.
+    /// * `replication_factor` - Nodes to store each shard.
     ///
     /// # Returns
     /// * `Result<UploadData, anyhow::Error>` - The constructed upload instruction or an error.
     ///
     /// # Notes
-    /// Derives PDAs for upload, user upload keys, escrow, node registry, and storage config.
-    /// Constructs account metadata for the instruction, including node accounts.
+    /// Derives PDAs for upload, user upload keys, escrow, and storage config, and reads the
+    /// config's price feed so USD-priced uploads can be converted on chain.
+    /// Constructs account metadata for the instruction. No nodes are passed: they are drawn
+    /// later by `assign_upload` (see `create_assign_instruction`).
     async fn create_upload_instruction(
         &self,
         solad_client: &SoladClient,
@@ -310,8 +361,9 @@ impl DataClient {
         size_bytes: u64,
         shard_count: u8,
         storage_duration_days: u64,
-        treasury_pubkey: Pubkey,
-        nodes: Vec<Pubkey>,
+        commitment: DataCommitment,
+        hash_algo: HashAlgo,
+        replication_factor: u8,
     ) -> Result<UploadData, anyhow::Error> {
         // Derive PDA for upload
         let (upload_pda, _upload_bump) = Pubkey::find_program_address(
@@ -325,7 +377,7 @@ impl DataClient {
 
         // Derive PDA for user upload keys
         let (user_upload_keys_pda, _user_upload_keys_bump) = Pubkey::find_program_address(
-            &[b"user_upload_keys", solad_client.payer.pubkey().as_ref()],
+            &[USER_UPLOAD_KEYS_SEED, solad_client.payer.pubkey().as_ref()],
             &solad_client.program.id(),
         );
 
@@ -339,10 +391,6 @@ impl DataClient {
             &solad_client.program.id(),
         );
 
-        // Derive PDA for node registry
-        let (node_registry_pda, _node_registry_bump) =
-            Pubkey::find_program_address(&[b"node_registry"], &solad_client.program.id());
-
         // Derive PDA for storage config
         let (config_pubkey, _config_bump) =
             Pubkey::find_program_address(&[b"storage_config"], &solad_client.program.id());
@@ -352,18 +400,28 @@ impl DataClient {
             AccountMeta::new(user_upload_keys_pda, false),
             AccountMeta::new(upload_pda, false),
             AccountMeta::new(config_pubkey, false),
-            AccountMeta::new(node_registry_pda, false),
             AccountMeta::new(solad_client.payer.pubkey(), true),
-            AccountMeta::new(treasury_pubkey, false),
             AccountMeta::new(escrow_pda, false),
             AccountMeta::new_readonly(solad_client.program.id(), false),
             AccountMeta::new_readonly(system_program::ID, false),
         ];
-
-        // Add node accounts to the instruction
-        for node in nodes.iter() {
-            accounts.push(AccountMeta::new(*node, false));
-        }
+        // Token payment accounts; the program ID stands in for each one when paying in lamports
+        accounts.extend(
+            (0..4).map(|_| AccountMeta::new_readonly(solad_client.program.id(), false)),
+        );
+        // SOL/USD price feed, read when the config prices storage in USD
+        let config = solad_client
+            .program
+            .account::<StorageConfig>(config_pubkey)
+            .await?;
+        let price_feed = if config.usd_micros_per_gb > 0 {
+            config.price_feed
+        } else {
+            solad_client.program.id()
+        };
+        accounts.push(AccountMeta::new_readonly(price_feed, false));
+        // No sponsor: the payer funds its own upload
+        accounts.push(AccountMeta::new_readonly(solad_client.program.id(), false));
 
         // Create the upload instruction data
         let instruction_data = contract::instruction::UploadData {
@@ -371,10 +429,94 @@ impl DataClient {
             size_bytes,
             shard_count,
             storage_duration_days,
+            commitment,
+            hash_algo: hash_algo.into(),
+            replication_factor,
+            erasure: None,
         };
 
         Ok(instruction_data)
     }
+
+    /// Creates the instruction that assigns an upload's nodes.
+    ///
+    /// # Arguments
+    /// * `solad_client` - Reference to the SoladClient for program and payer access.
+    /// * `data_hash` - Hex digest of the uploaded data.
+    /// * `treasury_pubkey` - Public key of the treasury account.
+    ///
+    /// # Returns
+    /// * `Result<(AssignUpload, Vec<AccountMeta>), anyhow::Error>` - The instruction and its
+    ///   accounts, or an error.
+    ///
+    /// # Notes
+    /// Waits until the upload's selection slot has passed, then derives the registry window
+    /// the program fixes from that slot's hash and passes the window's node accounts. The
+    /// slot hash stays in SlotHashes for about 512 slots, so the instruction must land by then.
+    async fn create_assign_instruction(
+        &self,
+        solad_client: &SoladClient,
+        data_hash: String,
+        treasury_pubkey: Pubkey,
+    ) -> Result<(AssignUpload, Vec<AccountMeta>), anyhow::Error> {
+        let program_id = solad_client.program.id();
+        let payer = solad_client.payer.pubkey();
+        let (upload_pda, _) = Pubkey::find_program_address(
+            &[b"upload", data_hash.as_bytes(), payer.as_ref()],
+            &program_id,
+        );
+        let (escrow_pda, _) = Pubkey::find_program_address(
+            &[b"escrow", data_hash.as_bytes(), payer.as_ref()],
+            &program_id,
+        );
+        let (config_pubkey, _) = Pubkey::find_program_address(&[b"storage_config"], &program_id);
+        let upload = solad_client.program.account::<Upload>(upload_pda).await?;
+
+        // The selection slot's hash only exists once the slot has passed
+        let rpc = solad_client.program.rpc();
+        while rpc.get_slot()? <= upload.selection_slot {
+            std::thread::sleep(std::time::Duration::from_millis(400));
+        }
+        let slot_hashes_data = rpc.get_account_data(&slot_hashes::ID)?;
+        let entropy = challenge_seed_from(&slot_hashes_data, upload.selection_slot)
+            .map_err(|e| anyhow::anyhow!("Selection slot hash unavailable: {}", e))?;
+        let selection_seed = upload_seed(&entropy, &upload_pda);
+        let (node_registry_pda, _) =
+            Pubkey::find_program_address(&[NODE_REGISTRY_SEED], &program_id);
+        let node_registry = solad_client
+            .program
+            .account::<NodeRegistry>(node_registry_pda)
+            .await?;
+        let (page_index, start_seed) = registry_window(&selection_seed, node_registry.page_count);
+        let (candidate_page_pda, _) = Pubkey::find_program_address(
+            &[REGISTRY_PAGE_SEED, &page_index.to_le_bytes()],
+            &program_id,
+        );
+        let candidate_page = solad_client
+            .program
+            .account::<RegistryPage>(candidate_page_pda)
+            .await?;
+
+        let mut accounts = vec![
+            AccountMeta::new(upload_pda, false),
+            AccountMeta::new(escrow_pda, false),
+            AccountMeta::new_readonly(config_pubkey, false),
+            AccountMeta::new_readonly(node_registry_pda, false),
+            AccountMeta::new_readonly(candidate_page_pda, false),
+            AccountMeta::new_readonly(slot_hashes::ID, false),
+            AccountMeta::new(treasury_pubkey, false),
+            // No sponsor: the payer takes back the fee of replicas that were not assigned
+            AccountMeta::new(payer, false),
+        ];
+        // Token accounts; the program ID stands in for each one when paid in lamports
+        accounts.extend((0..4).map(|_| AccountMeta::new_readonly(program_id, false)));
+        // Add the window's node accounts to the instruction
+        for entry in candidate_page.window(start_seed, MAX_UPLOAD_CANDIDATES) {
+            accounts.push(AccountMeta::new(entry.node, false));
+        }
+
+        Ok((AssignUpload { data_hash }, accounts))
+    }
 }
 
 /// Represents the Solad client for interacting with the Solad program on Solana.
@@ -406,4 +548,43 @@ impl SoladClient {
         let program = client.program(program_id)?;
         Ok(SoladClient { program, payer })
     }
+
+    /// Fetches the endpoints, region and capacity a node advertises.
+    ///
+    /// # Arguments
+    /// * `node` - The node account (PDA) whose metadata to fetch.
+    ///
+    /// # Returns
+    /// * `Result<NodeMetadata>` - The node's metadata or an error.
+    ///
+    /// # Errors
+    /// Returns an error if the metadata account does not exist or cannot be deserialized.
+    pub async fn fetch_node_metadata(&self, node: Pubkey) -> Result<NodeMetadata> {
+        let (metadata_pda, _) =
+            Pubkey::find_program_address(&[NODE_METADATA_SEED, node.as_ref()], &self.program.id());
+        Ok(self.program.account::<NodeMetadata>(metadata_pda).await?)
+    }
+
+    /// Lists nodes that can take new uploads, ordered by advertised capacity, largest first.
+    ///
+    /// # Arguments
+    /// * `min_capacity_mb` - Nodes advertising less capacity than this are skipped.
+    ///
+    /// # Returns
+    /// * `Result<Vec<NodeMetadata>>` - Metadata of the nodes; connect a `DataClient` to one.
+    ///
+    /// # Errors
+    /// Returns an error if the RPC query fails.
+    pub async fn find_upload_targets(&self, min_capacity_mb: u64) -> Result<Vec<NodeMetadata>> {
+        let mut targets: Vec<NodeMetadata> = self
+            .program
+            .accounts::<NodeMetadata>(vec![])
+            .await?
+            .into_iter()
+            .map(|(_, metadata)| metadata)
+            .filter(|metadata| metadata.capacity_mb >= min_capacity_mb)
+            .collect();
+        targets.sort_by(|a, b| b.capacity_mb.cmp(&a.capacity_mb));
+        Ok(targets)
+    }
 }
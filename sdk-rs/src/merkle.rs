@@ -0,0 +1,80 @@
+//! Chunk Merkle tree helpers matching the Solad program's Proof of Storage checks.
+//!
+//! Data is split into fixed-size chunks (the last may be shorter) and each chunk is
+//! hashed with SHA-256 to form a leaf. Parents are `sha256(left || right)`; when a level
+//! has an odd number of nodes the last one is paired with itself. The root, chunk size,
+//! and leaf count are committed on-chain by `upload_data`.
+
+use contract::states::DataCommitment;
+pub use contract::states::MAX_LEAF_COUNT;
+use sha2::{Digest, Sha256};
+
+/// Smallest chunk size used when committing an upload.
+pub const MIN_CHUNK_SIZE: u32 = 1024;
+
+/// Picks the smallest chunk size that keeps `size_bytes` within `MAX_LEAF_COUNT` chunks.
+pub fn chunk_size_for(size_bytes: u64) -> u32 {
+    let chunk_size = size_bytes
+        .div_ceil(MAX_LEAF_COUNT as u64)
+        .max(MIN_CHUNK_SIZE as u64);
+    u32::try_from(chunk_size).unwrap_or(u32::MAX)
+}
+
+/// Hashes each `chunk_size` chunk of `data` into a leaf.
+pub fn leaf_hashes(data: &[u8], chunk_size: u32) -> Vec<[u8; 32]> {
+    data.chunks(chunk_size as usize)
+        .map(|chunk| Sha256::digest(chunk).into())
+        .collect()
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+/// Computes the Merkle root of `leaves`.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level.first().copied().unwrap_or_default()
+}
+
+/// Builds the sibling path for the leaf at `index`, ordered from the leaf up to the root.
+pub fn merkle_proof(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut index = index;
+    while level.len() > 1 {
+        let sibling = if index % 2 == 0 {
+            *level.get(index + 1).unwrap_or(&level[index])
+        } else {
+            level[index - 1]
+        };
+        proof.push(sibling);
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+/// Builds the commitment `upload_data` expects for `data`.
+pub fn commit(data: &[u8]) -> DataCommitment {
+    let chunk_size = chunk_size_for(data.len() as u64);
+    let leaves = leaf_hashes(data, chunk_size);
+    DataCommitment {
+        merkle_root: merkle_root(&leaves),
+        chunk_size,
+        leaf_count: leaves.len() as u32,
+    }
+}
@@ -1,11 +1,56 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+/// Digest algorithm that produces an upload's data hash.
+///
+/// Every algorithm yields a 32-byte digest, hex encoded as the data hash. The algorithm is
+/// recorded on the upload so nodes verify the data with the same one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    Blake3,
+    Keccak256,
+}
+
+impl HashAlgo {
+    /// Returns the lowercase hex digest of `data`.
+    pub fn digest_hex(&self, data: &[u8]) -> String {
+        match self {
+            HashAlgo::Sha256 => format!("{:x}", Sha256::digest(data)),
+            HashAlgo::Blake3 => blake3::hash(data).to_hex().to_string(),
+            HashAlgo::Keccak256 => format!("{:x}", Keccak256::digest(data)),
+        }
+    }
+}
+
+impl From<HashAlgo> for contract::states::HashAlgo {
+    fn from(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha256 => contract::states::HashAlgo::Sha256,
+            HashAlgo::Blake3 => contract::states::HashAlgo::Blake3,
+            HashAlgo::Keccak256 => contract::states::HashAlgo::Keccak256,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SetData {
     pub key: String,
     pub hash: String,
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
     pub data: Vec<u8>,
     pub shard: u32,
+    /// Nodes to store each shard; must fall within the config's replication range.
+    #[serde(default = "default_replication_factor")]
+    pub replication_factor: u8,
     pub upload_pda: String,
     pub format: String,
 }
+
+fn default_replication_factor() -> u8 {
+    3
+}
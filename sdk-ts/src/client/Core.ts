@@ -1,15 +1,38 @@
 import * as anchor from "@coral-xyz/anchor";
-import { TransactionInstruction, SystemProgram } from "@solana/web3.js";
+import {
+  Ed25519Program,
+  PublicKey,
+  TransactionInstruction,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  SYSVAR_SLOT_HASHES_PUBKEY,
+} from "@solana/web3.js";
 import {
   StorageConfig,
   UploadRequest,
+  AssignUploadRequest,
   PreparedUpload,
   RequestReplacement,
   PoSSubmissionRequest,
+  IssueChallengeRequest,
+  PoSAttestation,
+  PoSBatchRequest,
+  NodeMetadataParams,
 } from "../types";
 import { PDAHelper } from "../utils/pda-helper";
 import { StateHelper } from "../utils/state-helper";
 import { StorageSDK } from "../client";
+import {
+  MAX_REPLACEMENT_WINDOWS,
+  MAX_UPLOAD_CANDIDATES,
+  candidateWindow,
+  registryWindow,
+  replacementSeed,
+  slotSeed,
+  uploadSeed,
+} from "../utils/registry";
+import { associatedTokenAddress, TOKEN_PROGRAM_ID } from "../utils/token";
+import { hashAlgoArg } from "../utils/hash";
 
 // ====================================
 // Core - Define instruction builders.
@@ -34,25 +57,37 @@ export class Core {
     const pdas = new PDAHelper(this.client.programId);
 
     return this.client.program.methods
-      .initialize(
-        new anchor.BN(params.solPerGb),
-        new anchor.BN(params.treasuryFeePercent),
-        new anchor.BN(params.nodeFeePercent),
-        new anchor.BN(params.shardMinMb),
-        new anchor.BN(params.epochsTotal),
-        new anchor.BN(params.slashPenaltyPercent),
-        params.minShardCount,
-        params.maxShardCount,
-        new anchor.BN(params.slotsPerEpoch),
-        new anchor.BN(params.minNodeStake),
-        new anchor.BN(params.replacementTimeoutEpochs),
-        new anchor.BN(params.minLamportsPerUpload),
-        new anchor.BN(params.maxUserUploads),
-        new anchor.BN(params.userSlashPenaltyPercent),
-        new anchor.BN(params.reportingWindow),
-        params.oversizedReportThreshold,
-        new anchor.BN(params.maxSubmissions)
-      )
+      .initialize({
+        treasury: params.treasury,
+        solPerGb: new anchor.BN(params.solPerGb),
+        treasuryFeePercent: new anchor.BN(params.treasuryFeePercent),
+        nodeFeePercent: new anchor.BN(params.nodeFeePercent),
+        shardMinMb: new anchor.BN(params.shardMinMb),
+        epochsTotal: new anchor.BN(params.epochsTotal),
+        slashPenaltyPercent: new anchor.BN(params.slashPenaltyPercent),
+        minShardCount: params.minShardCount,
+        maxShardCount: params.maxShardCount,
+        minReplicationFactor: params.minReplicationFactor,
+        maxReplicationFactor: params.maxReplicationFactor,
+        slotsPerEpoch: new anchor.BN(params.slotsPerEpoch),
+        minNodeStake: new anchor.BN(params.minNodeStake),
+        replacementTimeoutEpochs: new anchor.BN(params.replacementTimeoutEpochs),
+        inactivityEpochs: new anchor.BN(params.inactivityEpochs),
+        minLamportsPerUpload: new anchor.BN(params.minLamportsPerUpload),
+        userSlashPenaltyPercent: new anchor.BN(params.userSlashPenaltyPercent),
+        reportingWindow: new anchor.BN(params.reportingWindow),
+        maxUserUploads: new anchor.BN(params.maxUserUploads),
+        oversizedReportThreshold: params.oversizedReportThreshold,
+        disputeWindowEpochs: new anchor.BN(params.disputeWindowEpochs),
+        maxSubmssions: new anchor.BN(params.maxSubmissions),
+        configTimelockEpochs: new anchor.BN(params.configTimelockEpochs),
+        unbondingEpochs: new anchor.BN(params.unbondingEpochs),
+        usdMicrosPerGb: new anchor.BN(params.usdMicrosPerGb),
+        priceFeed: params.priceFeed,
+        maxPriceAgeSecs: new anchor.BN(params.maxPriceAgeSecs),
+        maxPriceConfidenceBps: new anchor.BN(params.maxPriceConfidenceBps),
+        priceFallbackToSol: params.priceFallbackToSol,
+      })
       .accounts({
         storageConfig: pdas.storageConfig,
         authority: this.client.wallet.publicKey,
@@ -61,33 +96,289 @@ export class Core {
       .instruction();
   }
 
+  /**
+   * Constructs a transaction instruction that lists, reprices or delists an SPL token
+   * mint as a storage payment option. Only the config authority may send it; repricing a
+   * listed mint takes effect after the config timelock.
+   *
+   * @param {PublicKey} mint - The SPL token mint.
+   * @param {number} pricePerGb - Price in the mint's base units per GB.
+   * @param {boolean} isEnabled - Whether new uploads may be paid in the mint.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createSetMintPricingIx(
+    mint: PublicKey,
+    pricePerGb: number,
+    isEnabled: boolean
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+
+    return this.client.program.methods
+      .setMintPricing(new anchor.BN(pricePerGb), isEnabled)
+      .accounts({
+        mintPricing: pdas.mintPricing(mint),
+        mint,
+        config: pdas.storageConfig(),
+        authority: this.client.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+  }
+
   /**
    * Node mgmt: create node registration instruction
    *
    * This instruction registers a new node in the Solad network, staking the
    * specified amount of lamports and associating it with a storage configuration account.
+   * The node's endpoints, region and capacity are published in its metadata account.
    *
    * @param {number} stakeAmount - The amount of lamports to stake for the node.
+   * @param {NodeMetadataParams} metadata - Endpoints, region, capacity and version the node advertises.
    * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
    */
   async createRegisterNodeIx(
-    stakeAmount: number
+    stakeAmount: number,
+    metadata: NodeMetadataParams
   ): Promise<TransactionInstruction> {
     const pdas = new PDAHelper(this.client.programId);
     const nodePda = pdas.nodeAccount(this.client.wallet.publicKey);
     const stakeEscrow = pdas.stakeEscrow(this.client.wallet.publicKey);
+    const registry = await (this.client.program.account as any).nodeRegistry.fetch(
+      pdas.nodeRegistry()
+    );
 
     return this.client.program.methods
-      .registerNode(new anchor.BN(stakeAmount))
+      .registerNode(new anchor.BN(stakeAmount), {
+        ...metadata,
+        capacityMb: new anchor.BN(metadata.capacityMb),
+      })
       .accounts({
         owner: this.client.wallet.publicKey,
         node: nodePda,
         stakeEscrow: stakeEscrow,
+        nodeMetadata: pdas.nodeMetadata(nodePda),
+        nodeRegistry: pdas.nodeRegistry(),
+        registryPage: pdas.registryPage(registry.openPage),
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+  }
+
+  /**
+   * Node Ops: create update node metadata instruction
+   *
+   * Creates a transaction instruction that replaces the endpoints, region, capacity and
+   * version the wallet's node advertises.
+   *
+   * @param {NodeMetadataParams} metadata - The new metadata; every field is replaced.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createUpdateNodeMetadataIx(
+    metadata: NodeMetadataParams
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+    const nodePda = pdas.nodeAccount(this.client.wallet.publicKey);
+
+    return this.client.program.methods
+      .updateNodeMetadata({
+        ...metadata,
+        capacityMb: new anchor.BN(metadata.capacityMb),
+      })
+      .accounts({
+        owner: this.client.wallet.publicKey,
+        node: nodePda,
+        nodeMetadata: pdas.nodeMetadata(nodePda),
+        config: pdas.storageConfig(),
+      })
+      .instruction();
+  }
+
+  /**
+   * Node Ops: create add stake instruction
+   *
+   * Creates a transaction instruction to top up the bonded stake of the wallet's node.
+   * A node that unstaked below the minimum is reactivated once its stake is back above it.
+   *
+   * @param {number} amount - The amount of lamports to add to the node's stake.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createAddStakeIx(amount: number): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+    const nodePda = pdas.nodeAccount(this.client.wallet.publicKey);
+    const node = await (this.client.program.account as any).node.fetch(nodePda);
+
+    return this.client.program.methods
+      .addStake(new anchor.BN(amount))
+      .accounts({
+        owner: this.client.wallet.publicKey,
+        node: nodePda,
+        stakeEscrow: pdas.stakeEscrow(this.client.wallet.publicKey),
+        nodeRegistry: pdas.nodeRegistry(),
+        registryPage: pdas.registryPage(node.registryPage),
+        config: pdas.storageConfig(),
         systemProgram: SystemProgram.programId,
       })
       .instruction();
   }
 
+  /**
+   * Node Ops: create request unstake instruction
+   *
+   * Creates a transaction instruction that moves part of the node's bonded stake into
+   * unbonding. The lamports stay slashable until `unbondingEpochs` have passed.
+   *
+   * @param {number} amount - The amount of lamports to unbond.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createRequestUnstakeIx(amount: number): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+    const nodePda = pdas.nodeAccount(this.client.wallet.publicKey);
+    const node = await (this.client.program.account as any).node.fetch(nodePda);
+
+    return this.client.program.methods
+      .requestUnstake(new anchor.BN(amount))
+      .accounts({
+        owner: this.client.wallet.publicKey,
+        node: nodePda,
+        nodeRegistry: pdas.nodeRegistry(),
+        registryPage: pdas.registryPage(node.registryPage),
+        config: pdas.storageConfig(),
+      })
+      .instruction();
+  }
+
+  /**
+   * Node Ops: create withdraw unstaked instruction
+   *
+   * Creates a transaction instruction that pays out the node's unbonding stake once the
+   * unbonding period has ended.
+   *
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createWithdrawUnstakedIx(): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+
+    return this.client.program.methods
+      .withdrawUnstaked()
+      .accounts({
+        owner: this.client.wallet.publicKey,
+        node: pdas.nodeAccount(this.client.wallet.publicKey),
+        stakeEscrow: pdas.stakeEscrow(this.client.wallet.publicKey),
+        config: pdas.storageConfig(),
+      })
+      .instruction();
+  }
+
+  /**
+   * Node Ops: create set commission instruction
+   *
+   * Creates a transaction instruction that sets the wallet's node commission on the
+   * rewards earned by delegated stake. A cut applies at once; a raise is queued for the
+   * unbonding period so delegators can leave before it applies.
+   *
+   * @param {number} commissionPercent - Operator cut of delegator rewards, 0-100.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createSetCommissionIx(
+    commissionPercent: number
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+
+    return this.client.program.methods
+      .setCommission(commissionPercent)
+      .accounts({
+        owner: this.client.wallet.publicKey,
+        node: pdas.nodeAccount(this.client.wallet.publicKey),
+        config: pdas.storageConfig(),
+      })
+      .instruction();
+  }
+
+  /**
+   * Delegation Ops: create delegate instruction
+   *
+   * Creates a transaction instruction that delegates lamports from the wallet to a node.
+   *
+   * @param {PublicKey} nodeOwner - The owner of the node being delegated to.
+   * @param {number} amount - The amount of lamports to delegate.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createDelegateIx(
+    nodeOwner: PublicKey,
+    amount: number
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+    const nodePda = pdas.nodeAccount(nodeOwner);
+
+    return this.client.program.methods
+      .delegate(new anchor.BN(amount))
+      .accounts({
+        node: nodePda,
+        delegation: pdas.delegation(nodePda, this.client.wallet.publicKey),
+        stakeEscrow: pdas.stakeEscrow(nodeOwner),
+        delegator: this.client.wallet.publicKey,
+        config: pdas.storageConfig(),
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+  }
+
+  /**
+   * Delegation Ops: create undelegate instruction
+   *
+   * Creates a transaction instruction that starts unbonding the wallet's shares in a
+   * node's pool. The stake can be collected with `createClaimDelegatorRewardsIx` once
+   * `unbondingEpochs` have passed.
+   *
+   * @param {PublicKey} nodeOwner - The owner of the node delegated to.
+   * @param {number} shares - The number of shares to undelegate.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createUndelegateIx(
+    nodeOwner: PublicKey,
+    shares: number
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+    const nodePda = pdas.nodeAccount(nodeOwner);
+
+    return this.client.program.methods
+      .undelegate(new anchor.BN(shares))
+      .accounts({
+        node: nodePda,
+        delegation: pdas.delegation(nodePda, this.client.wallet.publicKey),
+        delegator: this.client.wallet.publicKey,
+        config: pdas.storageConfig(),
+      })
+      .instruction();
+  }
+
+  /**
+   * Delegation Ops: create claim delegator rewards instruction
+   *
+   * Creates a transaction instruction that pays out the wallet's accrued delegator rewards
+   * and any undelegated stake whose unbonding period has ended.
+   *
+   * @param {PublicKey} nodeOwner - The owner of the node delegated to.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createClaimDelegatorRewardsIx(
+    nodeOwner: PublicKey
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+    const nodePda = pdas.nodeAccount(nodeOwner);
+
+    return this.client.program.methods
+      .claimDelegatorRewards()
+      .accounts({
+        node: nodePda,
+        delegation: pdas.delegation(nodePda, this.client.wallet.publicKey),
+        stakeEscrow: pdas.stakeEscrow(nodeOwner),
+        delegator: this.client.wallet.publicKey,
+        config: pdas.storageConfig(),
+      })
+      .instruction();
+  }
+
   /**
    * Data Ops: create upload instruction
    *
@@ -95,11 +386,23 @@ export class Core {
    * specified size, shard count, and duration.
    *
    * @param {UploadRequest} params - The parameters for the upload:
-   *  - dataHash: The SHA-256 hash of the data to be uploaded.
+   *  - dataHash: The hex digest of the data to be uploaded.
+   *  - hashAlgo: Optional algorithm that produced dataHash (sha256, blake3 or keccak256).
    *  - sizeBytes: The size of the data in bytes.
    *  - shardCount: The number of shards to split the data into.
+   *  - replicationFactor: Optional number of nodes to store each shard (3 by default),
+   *    within the config's replication range. The fee for every requested replica is
+   *    held in escrow, and `createAssignUploadIx` refunds that of replicas not assigned.
    *  - duration: The duration of the upload in seconds.
-   *  - nodes: The public keys of the nodes to which the shards will be uploaded.
+   *  - commitment: The chunk Merkle root, chunk size, and leaf count (see utils/merkle).
+   *  - paymentMint: Optional listed SPL token to pay in. The escrow's associated token
+   *    account must exist first (see `createAssociatedTokenAccountIdempotentIx`).
+   *  - erasure: Optional Reed-Solomon layout. shardCount must equal dataShards + parityShards
+   *    and replicationFactor must be 1; each shard then holds one fragment on one node.
+   *  - sponsor: Optional key that pays the fee while the wallet owns the upload. The sponsor
+   *    must also sign the transaction, and refunds go back to it.
+   * No nodes are assigned yet: once the upload's selection slot has passed, send
+   * `createAssignUploadIx` to draw them.
    * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
    */
   async createUploadIx(params: UploadRequest): Promise<TransactionInstruction> {
@@ -107,34 +410,117 @@ export class Core {
     const storageConfig = await new StateHelper(
       this.client.programId
     ).getStorageConfig(this.client.program);
+    const escrow = pdas.uploadEscrow(
+      params.dataHash,
+      this.client.wallet.publicKey
+    );
 
     return this.client.program.methods
       .uploadData(
         params.dataHash,
         new anchor.BN(params.sizeBytes),
         params.shardCount,
-        new anchor.BN(params.duration)
+        new anchor.BN(params.duration),
+        params.commitment,
+        hashAlgoArg(params.hashAlgo),
+        params.replicationFactor ?? 3,
+        params.erasure ?? null
       )
       .accounts({
         userUploadKeys: pdas.uploadKeys(this.client.wallet.publicKey),
         upload: pdas.upload(params.dataHash, this.client.wallet.publicKey),
-        escrow: pdas.uploadEscrow(
-          params.dataHash,
-          this.client.wallet.publicKey
-        ),
-        nodeRegistry: pdas.nodeRegistry(),
+        escrow,
         config: pdas.storageConfig(),
         payer: this.client.wallet.publicKey, // The payer of the transaction (signer)
-        treasury: storageConfig.treasury,
         program: this.client.programId, // The program account (self)
         systemProgram: SystemProgram.programId,
+        ...this.tokenPaymentAccounts(
+          params.paymentMint,
+          params.sponsor ?? this.client.wallet.publicKey,
+          escrow
+        ),
+        // Lamport prices follow the USD price feed when one is configured
+        priceFeed: storageConfig.priceFeed.equals(PublicKey.default)
+          ? null
+          : storageConfig.priceFeed,
+        sponsor: params.sponsor ?? null,
+      })
+      .instruction();
+  }
+
+  /**
+   * Data Ops: create assign upload instruction
+   *
+   * Creates a transaction instruction that draws an upload's nodes from the hash of its
+   * selection slot. Anyone may send it once that slot has passed; the hash stays in
+   * SlotHashes for about 512 slots. If it aged out first, the instruction sets a new
+   * selection slot instead, and must be sent again once that one passes.
+   * The fee of replicas that could not be assigned is refunded to the sponsor or owner.
+   *
+   * @param {AssignUploadRequest} params - The parameters for the assignment:
+   *  - dataHash: The hash of the uploaded data.
+   *  - uploader: The public key of the upload payer.
+   * Candidate nodes are loaded from the registry window the selection slot's hash fixes.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createAssignUploadIx(
+    params: AssignUploadRequest
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.programId);
+    const storageConfig = await new StateHelper(
+      this.client.programId
+    ).getStorageConfig(this.client.program);
+    const accounts = this.client.program.account as any;
+    const uploadPda = pdas.upload(params.dataHash, params.uploader);
+    const escrow = pdas.uploadEscrow(params.dataHash, params.uploader);
+    const upload = await accounts.upload.fetch(uploadPda);
+    const refundRecipient = upload.sponsor.equals(PublicKey.default)
+      ? upload.owner
+      : upload.sponsor;
+
+    // Load the candidate window the program will check; a selection slot that aged out is
+    // re-armed without one
+    const slotHashes = await this.client.connection.getAccountInfo(SYSVAR_SLOT_HASHES_PUBKEY);
+    let window: PublicKey[] = [];
+    let candidatePage = pdas.registryPage(0);
+    try {
+      const seed = uploadSeed(
+        slotSeed(slotHashes!.data, BigInt(upload.selectionSlot.toString())),
+        uploadPda
+      );
+      const registry = await accounts.nodeRegistry.fetch(pdas.nodeRegistry());
+      const { page, startSeed } = registryWindow(seed, registry.pageCount);
+      candidatePage = pdas.registryPage(page);
+      const { entries } = await accounts.registryPage.fetch(candidatePage);
+      window = candidateWindow(entries, startSeed, MAX_UPLOAD_CANDIDATES);
+    } catch (e) {
+      if (!(e instanceof Error) || !e.message.includes("aged out")) throw e;
+    }
+
+    const paymentMint: PublicKey = upload.paymentMint;
+    const paidInToken = !paymentMint.equals(PublicKey.default);
+    return this.client.program.methods
+      .assignUpload(params.dataHash)
+      .accounts({
+        upload: uploadPda,
+        escrow,
+        config: pdas.storageConfig(),
+        nodeRegistry: pdas.nodeRegistry(),
+        candidatePage,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY, // Entropy for node selection
+        treasury: storageConfig.treasury,
+        refundRecipient,
+        escrowToken: paidInToken ? associatedTokenAddress(escrow, paymentMint) : null,
+        treasuryToken: paidInToken
+          ? associatedTokenAddress(storageConfig.treasury, paymentMint)
+          : null,
+        refundToken: paidInToken
+          ? associatedTokenAddress(refundRecipient, paymentMint)
+          : null,
+        tokenProgram: paidInToken ? TOKEN_PROGRAM_ID : null,
       })
       .remainingAccounts(
-        params.nodes.map((pubkey) => ({
-          pubkey,
-          isWritable: true,
-          isSigner: false,
-        }))
+        window.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
       )
       .instruction();
   }
@@ -190,6 +576,122 @@ export class Core {
     };
   }
 
+  /**
+   * Data Ops: create issue challenge instruction
+   *
+   * Creates a transaction instruction that issues a PoS challenge for a shard in the
+   * current epoch. Anyone may send it; the signer pays rent for the challenge account.
+   * Its leaves are drawn later, by `createRevealChallengeIx`, once the seed slot passes.
+   * It is refused once less than a quarter of the epoch would be left after the seed slot.
+   * Sending it again re-arms a challenge whose seed slot aged out unrevealed.
+   *
+   * @param {IssueChallengeRequest} params - The parameters for issuing the challenge:
+   *  - dataHash: The hash of the uploaded data.
+   *  - uploader: The public key of the upload payer.
+   *  - shardId: The ID of the shard to challenge.
+   *  - epoch: The current epoch.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createIssueChallengeIx(
+    params: IssueChallengeRequest
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.program.programId);
+    const uploadPda = pdas.upload(params.dataHash, params.uploader);
+
+    return this.client.program.methods
+      .issueChallenge(
+        params.dataHash,
+        params.uploader,
+        params.shardId,
+        new anchor.BN(params.epoch)
+      )
+      .accounts({
+        upload: uploadPda,
+        challenge: pdas.challenge(uploadPda, params.shardId, params.epoch),
+        config: pdas.storageConfig(),
+        caller: this.client.wallet.publicKey,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+  }
+
+  /**
+   * Data Ops: create reveal challenge instruction
+   *
+   * Creates a transaction instruction that draws a challenge's leaf indices from the hash
+   * of its seed slot. Anyone may send it once the seed slot has passed and before the
+   * challenge's deadline; nodes usually put it just before their PoS submission in the
+   * same transaction.
+   *
+   * @param {IssueChallengeRequest} params - The challenge's upload, shard and epoch.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createRevealChallengeIx(
+    params: IssueChallengeRequest
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.program.programId);
+    const uploadPda = pdas.upload(params.dataHash, params.uploader);
+
+    return this.client.program.methods
+      .revealChallenge()
+      .accounts({
+        challenge: pdas.challenge(uploadPda, params.shardId, params.epoch),
+        upload: uploadPda,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+      })
+      .instruction();
+  }
+
+  /**
+   * Data Ops: create close challenge instruction
+   *
+   * Creates a transaction instruction that closes a challenge once it can no longer serve
+   * as inactivity evidence, more than 16 epochs after it was issued. Anyone may send it;
+   * the rent goes back to the challenge's issuer.
+   *
+   * @param {IssueChallengeRequest} params - The challenge's upload, shard and epoch.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createCloseChallengeIx(
+    params: IssueChallengeRequest
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.program.programId);
+    const uploadPda = pdas.upload(params.dataHash, params.uploader);
+    const challenge = pdas.challenge(uploadPda, params.shardId, params.epoch);
+    const { issuer } = await (this.client.program.account as any).challenge.fetch(challenge);
+
+    return this.client.program.methods
+      .closeChallenge()
+      .accounts({
+        challenge,
+        issuer,
+        config: pdas.storageConfig(),
+      })
+      .instruction();
+  }
+
+  /**
+   * Data Ops: create PoS attestation instruction
+   *
+   * Creates the Ed25519 program instruction carrying a challenger's attestation. It must be
+   * placed before the submit pos instruction in the same transaction; the program reads it
+   * through the instructions sysvar.
+   *
+   * @param {PoSAttestation} params - The attestation to verify:
+   *  - challenger: The owner key of the challenging node.
+   *  - signature: The challenger's Ed25519 signature over the message.
+   *  - message: The message built with `posAttestationMessage` (utils/attestation).
+   * @returns {TransactionInstruction} The Ed25519 program instruction.
+   */
+  createPosAttestationIx(params: PoSAttestation): TransactionInstruction {
+    return Ed25519Program.createInstructionWithPublicKey({
+      publicKey: params.challenger.toBytes(),
+      message: params.message,
+      signature: params.signature,
+    });
+  }
+
   /**
    * Data Ops: create submit pos instruction
    *
@@ -200,7 +702,9 @@ export class Core {
    * @param {PoSSubmissionRequest} params - The parameters required for creating the submit pos instruction:
    *  - submission: The PoSSubmission object containing the PoS details.
    *  - uploader: The public key of the node submitting the PoS.
+   *  - epoch: The epoch of the challenge being answered.
    *  - nodes: The public keys of the nodes to which the shards will be uploaded.
+   *  - exitingNode: (Optional) The node PDA being replaced, when this node is its successor.
    * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
    */
   async createSubmitPosIx(
@@ -210,19 +714,24 @@ export class Core {
       this.client.programId
     ).getStorageConfig(this.client.program);
     const pdas = new PDAHelper(this.client.program.programId);
-    const nodePda = pdas.nodeAccount(params.uploader);
-    const replacementPda = pdas.replacement(
-      params.submission.dataHash,
-      nodePda
+    const replacementPda = params.exitingNode
+      ? pdas.replacement(params.submission.dataHash, params.exitingNode)
+      : null;
+    const challengePda = pdas.challenge(
+      pdas.upload(params.submission.dataHash, params.uploader),
+      params.submission.shardId,
+      params.epoch
     );
 
     return this.client.program.methods
       .submitPos(params.submission, params.uploader)
       .accounts({
+        challenge: challengePda,
         replacement: replacementPda,
         owner: params.uploader,
         config: pdas.storageConfig,
         treasury: storageConfig.treasury,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
         systemProgram: SystemProgram.programId,
       })
       .remainingAccounts(
@@ -235,6 +744,48 @@ export class Core {
       .instruction();
   }
 
+  /**
+   * Data Ops: create submit pos batch instruction
+   *
+   * Creates a transaction instruction that submits several PoS responses at once, up to the
+   * configured `maxSubmissions`. Each item is verified independently and reported in a
+   * `PoSBatchItemEvent`. The Ed25519 attestation instructions for every item must precede it.
+   *
+   * @param {PoSBatchRequest} params - The parameters for the batch:
+   *  - items: The submissions, each with the upload payer and the challenge epoch.
+   *  - nodes: Node accounts of shards that the batch may fully verify.
+   * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
+   */
+  async createSubmitPosBatchIx(
+    params: PoSBatchRequest
+  ): Promise<TransactionInstruction> {
+    const pdas = new PDAHelper(this.client.program.programId);
+
+    const itemAccounts = params.items.flatMap((item) => {
+      const uploadPda = pdas.upload(item.submission.dataHash, item.uploader);
+      return [
+        uploadPda,
+        pdas.challenge(uploadPda, item.submission.shardId, item.epoch),
+      ];
+    });
+
+    return this.client.program.methods
+      .submitPosBatch(params.items.map((item) => item.submission))
+      .accounts({
+        owner: this.client.wallet.publicKey,
+        config: pdas.storageConfig(),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .remainingAccounts(
+        [...itemAccounts, ...params.nodes].map((pubkey) => ({
+          pubkey,
+          isWritable: true,
+          isSigner: false,
+        }))
+      )
+      .instruction();
+  }
+
   /**
    * Data Ops: create submit pos instruction
    *
@@ -246,6 +797,9 @@ export class Core {
    *  - shardId: The ID of the shard to be replaced.
    *  - owner: The public key of the current owner of the shard.
    *  - replacementNode: (Optional) The public key of the replacement node.
+   *  - uploader: (Optional) The payer of the upload; defaults to `owner`.
+   * Successor candidates are loaded from the registry windows the upload's selection seed
+   * fixes for this shard, in order, up to the first one holding an eligible node.
    * @returns {Promise<TransactionInstruction>} A promise that resolves to the transaction instruction.
    */
   async createRequestReplacementIx(
@@ -256,21 +810,92 @@ export class Core {
     ).getStorageConfig(this.client.program);
 
     const pdas = new PDAHelper(this.client.program.programId);
+    const accounts = this.client.program.account as any;
 
     // Determine replacement account PDA if a replacement node is specified
     const replacementAccount = params.replacementNode
       ? pdas.replacement(params.dataHash, params.replacementNode)
       : {};
 
+    // Load the candidate windows the program will search, up to the first usable one
+    const nodePda = pdas.nodeAccount(params.owner);
+    const node = await accounts.node.fetch(nodePda);
+    const uploader = params.uploader ?? params.owner;
+    const upload = await accounts.upload.fetch(pdas.upload(params.dataHash, uploader));
+    const shard = upload.shards[params.shardId];
+    // Replicas only exclude their own shard's nodes; erasure-coded shards exclude them all
+    const peers: PublicKey[] = ("replica" in shard.role ? [shard] : upload.shards).flatMap(
+      (s: any) => s.nodeKeys
+    );
+    const registry = await accounts.nodeRegistry.fetch(pdas.nodeRegistry());
+    const windows: { pubkey: PublicKey; isWritable: boolean; isSigner: boolean }[] = [];
+    for (let attempt = 0; attempt < MAX_REPLACEMENT_WINDOWS; attempt++) {
+      const seed = replacementSeed(
+        Buffer.from(upload.selectionSeed),
+        params.dataHash,
+        params.shardId,
+        nodePda,
+        attempt
+      );
+      const { page, startSeed } = registryWindow(seed, registry.pageCount);
+      const candidatePage = pdas.registryPage(page);
+      const { entries } = await accounts.registryPage.fetch(candidatePage);
+      const window = candidateWindow(entries, startSeed);
+      windows.push(
+        { pubkey: candidatePage, isWritable: false, isSigner: false },
+        ...window.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+      );
+
+      const eligible = await Promise.all(
+        window.map(async (candidate) => {
+          const entry = entries.find((e: any) => e.node.equals(candidate));
+          if (!entry.isActive || candidate.equals(nodePda)) return false;
+          if (peers.some((peer) => peer.equals(candidate))) return false;
+          const info = await accounts.node.fetch(candidate);
+          return (
+            info.isActive &&
+            info.stakeAmount.gte(storageConfig.minNodeStake) &&
+            info.capacityMb.sub(info.committedMb).gte(shard.sizeMb)
+          );
+        })
+      );
+      if (eligible.some(Boolean)) break;
+    }
+
     return this.client.program.methods
-      .requestReplacement(params.dataHash, params.shardId, params.owner)
+      .requestReplacement(params.dataHash, params.shardId, uploader)
       .accounts({
         replacement: replacementAccount,
+        nodeRegistry: pdas.nodeRegistry(),
+        nodePage: pdas.registryPage(node.registryPage),
         config: pdas.storageConfig(),
         treasury: storageConfig.treasury,
         owner: params.owner,
         systemProgram: SystemProgram.programId,
       })
+      .remainingAccounts(windows)
       .instruction();
   }
+
+  // Optional token payment accounts for `uploadData`; all null when paying in lamports.
+  private tokenPaymentAccounts(
+    paymentMint: PublicKey | undefined,
+    payer: PublicKey,
+    escrow: PublicKey
+  ) {
+    if (!paymentMint) {
+      return {
+        mintPricing: null,
+        payerToken: null,
+        escrowToken: null,
+        tokenProgram: null,
+      };
+    }
+    return {
+      mintPricing: new PDAHelper(this.client.programId).mintPricing(paymentMint),
+      payerToken: associatedTokenAddress(payer, paymentMint),
+      escrowToken: associatedTokenAddress(escrow, paymentMint),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+  }
 }
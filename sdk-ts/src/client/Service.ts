@@ -3,9 +3,18 @@ import { PublicKey, TransactionSignature } from "@solana/web3.js";
 import { StorageSDK } from "../client";
 import { Core } from "./Core";
 import { PDAHelper } from "../utils/pda-helper";
-import { createHash } from "crypto";
+import { commit } from "../utils/merkle";
 import axios from "axios";
-import { DataUploadRequest, DataUploadPayload, StorageConfig } from "../types";
+import { StateHelper } from "../utils/state-helper";
+import { createAssociatedTokenAccountIdempotentIx } from "../utils/token";
+import { contentHash } from "../utils/hash";
+import {
+  DataUploadRequest,
+  DataUploadPayload,
+  NodeMetadata,
+  NodeMetadataParams,
+  StorageConfig,
+} from "../types";
 
 // ==================================
 // Service Layer: Common Workflows.
@@ -37,14 +46,17 @@ export class Service {
    * This method registers a new node with the given stake amount using the
    * `registerNode` instruction. 
    *
-   * @param { { stakeAmount: number } } params - Parameters for registering a node 
+   * @param { { stakeAmount: number; metadata: NodeMetadataParams } } params - Parameters for registering a node 
    * @returns {Promise<{ dataHash: string; uploadPDA: PublicKey }>} - The promise resolves to an object containing the tx hash .
    */
   async registerNode(
-    params: { stakeAmount: number }
+    params: { stakeAmount: number; metadata: NodeMetadataParams }
   ): Promise<{ dataHash: string }> {
     // Register node
-    const registerIx = await this.core.createRegisterNodeIx(params.stakeAmount);
+    const registerIx = await this.core.createRegisterNodeIx(
+      params.stakeAmount,
+      params.metadata
+    );
 
     try {
       // Create custom transaction & ensure it's confirmed before proceeding
@@ -60,10 +72,32 @@ export class Service {
     }
   }
 
+  /**
+   * Lists the nodes that can take new uploads, as advertised in their on-chain metadata.
+   *
+   * Nodes advertising less than `minCapacityMb` are dropped and the rest are ordered by
+   * capacity, largest first. Pass the `node` keys as `nodes` when uploading.
+   *
+   * @param {number} minCapacityMb - Minimum advertised capacity in megabytes. Defaults to 0.
+   * @returns {Promise<NodeMetadata[]>} - The promise resolves to the metadata of each candidate node.
+   */
+  async findUploadTargets(minCapacityMb = 0): Promise<NodeMetadata[]> {
+    const state = new StateHelper(this.client.programId);
+    const targets = await state.getAllNodeMetadata(this.client.program);
+
+    return targets
+      .filter((metadata) => metadata.capacityMb >= minCapacityMb)
+      .sort((a, b) => b.capacityMb - a.capacityMb);
+  }
+
   /**
    * Uploads data to the Solad network. This method first calls the `uploadData` instruction
    * on the Solad program to create an upload instruction. After the instruction is confirmed,
-   * it then uploads the data to the specified endpoint.
+   * it waits for the upload's selection slot and calls `assignUpload` to draw its nodes,
+   * then uploads the data to the specified endpoint, or to the HTTP URL the first node
+   * advertises on chain when none is given. Passing `paymentMint` pays in that listed SPL
+   * token from the wallet's associated token account. The data hash uses `hashAlgo`
+   * (SHA-256 by default), which is recorded on the upload for nodes to verify against.
    *
    * @param {DataUploadRequest} params - Parameters for uploading data.
    * @returns {Promise<{ dataHash: string; uploadPDA: PublicKey }>} - The promise resolves to an object containing the data hash and the upload PDA.
@@ -73,7 +107,7 @@ export class Service {
   ): Promise<{ dataHash: string; uploadPDA: PublicKey }> {
     const pdas = new PDAHelper(this.client.programId);
 
-    const dataHash = createHash("sha256").update(params.data).digest("hex");
+    const dataHash = contentHash(params.data, params.hashAlgo);
     const shardCount = params.nodes.length;
 
     // Phase 1: On-chain contract call
@@ -82,17 +116,45 @@ export class Service {
       sizeBytes: params.data.length,
       shardCount,
       duration: params.duration,
+      commitment: commit(params.data),
       nodes: params.nodes,
+      paymentMint: params.paymentMint,
+      hashAlgo: params.hashAlgo,
+      replicationFactor: params.replicationFactor,
     });
 
     const uploadPDA = pdas.upload(dataHash, this.client.wallet.publicKey);
 
+    // Token payments need the escrow PDA's token account to exist before the upload
+    const ixs = params.paymentMint
+      ? [
+          createAssociatedTokenAccountIdempotentIx(
+            this.client.wallet.publicKey,
+            pdas.uploadEscrow(dataHash, this.client.wallet.publicKey),
+            params.paymentMint
+          ),
+          ix,
+        ]
+      : [ix];
+
     try {
       // Create custom transaction & ensure it's confirmed before proceeding
-      const txSig = await this.client.sendTransactions([ix]);
+      const txSig = await this.client.sendTransactions(ixs);
 
       // Confirm the transaction or use catch block
       await this.client.confirmTransaction(txSig);
+
+      // Nodes are drawn from the hash of a slot after the payment, once it has passed
+      const upload = await (this.client.program.account as any).upload.fetch(uploadPDA);
+      while ((await this.client.connection.getSlot()) <= upload.selectionSlot.toNumber()) {
+        await new Promise((resolve) => setTimeout(resolve, 400));
+      }
+      const assignIx = await this.core.createAssignUploadIx({
+        dataHash,
+        uploader: this.client.wallet.publicKey,
+      });
+      const assignSig = await this.client.sendTransactions([assignIx]);
+      await this.client.confirmTransaction(assignSig);
     } catch (err: any) {
       // TODO: use error type
       throw new Error(`On-chain upload failed: ${err.message}`);
@@ -103,11 +165,21 @@ export class Service {
       key: params.key,
       data: params.data.toString("base64"),
       hash: dataHash,
+      hash_algo: params.hashAlgo ?? "sha256",
       format: params.format,
       upload_pda: uploadPDA,
     };
 
-    await this.postWithRetry(`${params.endpoint}/set_value`, payload);
+    const endpoint =
+      params.endpoint ??
+      (
+        await new StateHelper(this.client.programId).getNodeMetadata(
+          this.client.program,
+          params.nodes[0]
+        )
+      ).httpUrl;
+
+    await this.postWithRetry(`${endpoint}/set_value`, payload);
 
     return { dataHash, uploadPDA };
   }
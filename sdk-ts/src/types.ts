@@ -10,28 +10,69 @@ export interface StorageConfig {
   slashPenaltyPercent: number;
   minShardCount: number;
   maxShardCount: number;
+  minReplicationFactor: number; // Fewest nodes a shard may be assigned, at least 2
+  maxReplicationFactor: number; // Most nodes an upload may request per shard, at most 8
   slotsPerEpoch: number;
   minNodeStake: number;
   replacementTimeoutEpochs: number;
+  inactivityEpochs: number; // Consecutive missed challenges before a node can be slashed, 1 to 16
   minLamportsPerUpload: number;
   maxUserUploads: number;
   userSlashPenaltyPercent: number;
   reportingWindow: number;
   oversizedReportThreshold: number;
+  disputeWindowEpochs: number; // Epochs an invalidated shard's oversized reports can be disputed
   maxSubmissions: number;
+  configTimelockEpochs: number;
+  unbondingEpochs: number;
+  usdMicrosPerGb: number; // USD per GB in millionths; 0 prices uploads from solPerGb only
+  priceFeed: PublicKey; // SOL/USD feed account in the program's PriceFeed layout
+  maxPriceAgeSecs: number;
+  maxPriceConfidenceBps: number;
+  priceFallbackToSol: boolean;
 }
 
+export interface MintPricing {
+  mint: PublicKey;
+  pricePerGb: number; // Mint base units per GB
+  pendingPricePerGb: number;
+  activationEpoch: number; // Epoch from which `pendingPricePerGb` applies
+  isEnabled: boolean;
+}
+
+export interface DataCommitment {
+  merkleRoot: number[];
+  chunkSize: number; // Bytes per leaf chunk
+  leafCount: number; // ceil(sizeBytes / chunkSize), at most 128
+}
+
+// Reed-Solomon layout for an erasure-coded upload; one root per fragment, data first
+export interface ErasureCoding {
+  dataShards: number;
+  parityShards: number;
+  chunkSize: number; // Bytes per leaf chunk of each fragment
+  fragmentRoots: number[][];
+}
+
+// Digest algorithm behind an upload's data hash; each yields 32 bytes
+export type HashAlgo = "sha256" | "blake3" | "keccak256";
+
 export interface UploadRequest {
   dataHash: string;
+  hashAlgo?: HashAlgo; // Algorithm that produced dataHash, SHA-256 by default
   sizeBytes: number;
   shardCount: number;
+  replicationFactor?: number; // Nodes to store each shard, 3 by default
   duration: number;
-  nodes: PublicKey[];
+  commitment: DataCommitment;
+  paymentMint?: PublicKey; // Pay in this listed SPL token instead of lamports
+  erasure?: ErasureCoding; // Store Reed-Solomon fragments instead of full replicas
+  sponsor?: PublicKey; // Co-signer that pays the fee and takes refunds
 }
 
 export interface OffChainMetadata {
   uploadUrl: string;
-  payload: Omit<UploadRequest, "duration" | "nodes">;
+  payload: Pick<UploadRequest, "dataHash" | "shardCount" | "sizeBytes">;
 }
 
 export interface PreparedUpload {
@@ -39,21 +80,55 @@ export interface PreparedUpload {
   offChainMetadata: OffChainMetadata;
 }
 
+export interface ChallengeProof {
+  leafIndex: number;
+  leaf: any[];
+  merkleProof: any[];
+}
+
 export interface PoSSubmission {
   dataHash: string;
   shardId: number;
-  merkleRoot: any[];
-  merkleProof: any[];
-  leaf: any[];
-  challengerSignature: any[];
-  challengerPubkey: PublicKey;
+  proofs: ChallengeProof[];
+  challengerPubkey: PublicKey; // Owner key of the challenging node
   actualSizeMb?: number;
 }
 
 export interface PoSSubmissionRequest {
   submission: PoSSubmission;
   uploader: PublicKey;
+  epoch: number; // Epoch of the challenge being answered
   nodes: PublicKey[];
+  exitingNode?: PublicKey; // Node PDA being replaced, when the submitter is its successor
+}
+
+export interface PoSBatchItem {
+  submission: PoSSubmission;
+  uploader: PublicKey; // Payer of the upload being proven
+  epoch: number; // Epoch of the challenge being answered
+}
+
+export interface PoSBatchRequest {
+  items: PoSBatchItem[];
+  nodes: PublicKey[]; // Node accounts of shards the batch may fully verify
+}
+
+export interface PoSAttestation {
+  challenger: PublicKey; // Owner key of the challenging node
+  signature: Uint8Array; // Ed25519 signature over posAttestationMessage(...)
+  message: Buffer;
+}
+
+export interface AssignUploadRequest {
+  dataHash: string;
+  uploader: PublicKey; // Payer of the upload
+}
+
+export interface IssueChallengeRequest {
+  dataHash: string;
+  uploader: PublicKey;
+  shardId: number;
+  epoch: number;
 }
 
 export interface RequestReplacement {
@@ -61,6 +136,26 @@ export interface RequestReplacement {
   shardId: number;
   owner: PublicKey;
   replacementNode?: PublicKey;
+  uploader?: PublicKey; // Payer of the upload; defaults to `owner`
+}
+
+export interface NodeMetadataParams {
+  httpUrl: string; // e.g. https://node.example.com
+  multiaddr: string; // e.g. /ip4/1.2.3.4/tcp/4001/p2p/<peer id>
+  region: string;
+  capacityMb: number;
+  version: string;
+}
+
+export interface NodeMetadata {
+  node: PublicKey;
+  owner: PublicKey;
+  httpUrl: string;
+  multiaddr: string;
+  region: string;
+  capacityMb: number;
+  version: string;
+  updatedAt: number;
 }
 
 export interface DataUploadRequest {
@@ -69,12 +164,16 @@ export interface DataUploadRequest {
   format: string;
   duration: number; // in days
   nodes: PublicKey[];
-  endpoint: string;
+  endpoint?: string; // defaults to the on-chain HTTP URL of the first node
+  paymentMint?: PublicKey; // Pay in this listed SPL token instead of lamports
+  hashAlgo?: HashAlgo; // Content hash algorithm, SHA-256 by default
+  replicationFactor?: number; // Nodes to store each shard, 3 by default
 }
 export interface DataUploadPayload {
   key: string;
   data: string;
   hash: string;
+  hash_algo: HashAlgo;
   format: string;
   upload_pda: PublicKey;
 }
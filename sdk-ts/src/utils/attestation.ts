@@ -0,0 +1,21 @@
+import { PublicKey } from "@solana/web3.js";
+
+// PoS attestation message signed by a challenging node's owner key. Mirrors
+// `pos_attestation_message` in the program:
+// domain || program id || challenge PDA || responding node PDA || committed Merkle root.
+export const POS_ATTESTATION_DOMAIN = Buffer.from("solad:pos-attestation:v1");
+
+export function posAttestationMessage(
+  programId: PublicKey,
+  challenge: PublicKey,
+  node: PublicKey,
+  merkleRoot: number[] | Uint8Array
+): Buffer {
+  return Buffer.concat([
+    POS_ATTESTATION_DOMAIN,
+    programId.toBuffer(),
+    challenge.toBuffer(),
+    node.toBuffer(),
+    Buffer.from(merkleRoot),
+  ]);
+}
@@ -0,0 +1,24 @@
+import { blake3 } from "@noble/hashes/blake3";
+import { sha256 } from "@noble/hashes/sha2";
+import { keccak_256 } from "@noble/hashes/sha3";
+import { HashAlgo } from "../types";
+
+// Content hashes for uploads. Every algorithm yields a 32-byte digest, hex encoded as the
+// upload's data hash. The algorithm is recorded on chain so nodes verify data with it.
+
+const DIGESTS: Record<HashAlgo, (data: Uint8Array) => Uint8Array> = {
+  sha256,
+  blake3: (data) => blake3(data),
+  keccak256: keccak_256,
+};
+
+/**
+ * Returns the hex digest of `data` under `algo` (SHA-256 by default).
+ */
+export const contentHash = (data: Uint8Array, algo: HashAlgo = "sha256"): string =>
+  Buffer.from(DIGESTS[algo](data)).toString("hex");
+
+/**
+ * Encodes `algo` as the program's `HashAlgo` enum argument.
+ */
+export const hashAlgoArg = (algo: HashAlgo = "sha256") => ({ [algo]: {} });
@@ -0,0 +1,69 @@
+import { createHash } from "crypto";
+import { DataCommitment } from "../types";
+
+// Chunk Merkle tree helpers matching the Solad program's Proof of Storage checks.
+// Leaves are sha256(chunk); parents are sha256(left || right), and the last node of an
+// odd-sized level is paired with itself. The root, chunk size, and leaf count are
+// committed on-chain by `uploadData`.
+
+export const MIN_CHUNK_SIZE = 1024;
+export const MAX_LEAF_COUNT = 128; // Mirrors the program's MAX_LEAF_COUNT
+
+const sha256 = (...parts: Buffer[]) => {
+  const hash = createHash("sha256");
+  parts.forEach((part) => hash.update(part));
+  return hash.digest();
+};
+
+const nextLevel = (level: Buffer[]) => {
+  const parents: Buffer[] = [];
+  for (let i = 0; i < level.length; i += 2) {
+    parents.push(sha256(level[i], level[i + 1] ?? level[i]));
+  }
+  return parents;
+};
+
+// Smallest chunk size that keeps the data within MAX_LEAF_COUNT chunks
+export function chunkSizeFor(sizeBytes: number): number {
+  return Math.max(MIN_CHUNK_SIZE, Math.ceil(sizeBytes / MAX_LEAF_COUNT));
+}
+
+export function leafHashes(data: Buffer, chunkSize: number): Buffer[] {
+  const leaves: Buffer[] = [];
+  for (let offset = 0; offset < data.length; offset += chunkSize) {
+    leaves.push(sha256(data.subarray(offset, offset + chunkSize)));
+  }
+  return leaves;
+}
+
+export function merkleRoot(leaves: Buffer[]): Buffer {
+  let level = leaves;
+  while (level.length > 1) {
+    level = nextLevel(level);
+  }
+  return level[0] ?? Buffer.alloc(32);
+}
+
+// Sibling path for the leaf at `index`, ordered from the leaf up to the root
+export function merkleProof(leaves: Buffer[], index: number): Buffer[] {
+  const proof: Buffer[] = [];
+  let level = leaves;
+  while (level.length > 1) {
+    const sibling =
+      index % 2 === 0 ? level[index + 1] ?? level[index] : level[index - 1];
+    proof.push(sibling);
+    level = nextLevel(level);
+    index = Math.floor(index / 2);
+  }
+  return proof;
+}
+
+export function commit(data: Buffer): DataCommitment {
+  const chunkSize = chunkSizeFor(data.length);
+  const leaves = leafHashes(data, chunkSize);
+  return {
+    merkleRoot: [...merkleRoot(leaves)],
+    chunkSize,
+    leafCount: leaves.length,
+  };
+}
@@ -11,6 +11,14 @@ export class PDAHelper {
     )[0];
   }
 
+  // config management: per-mint pricing
+  mintPricing(mint: PublicKey) {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_pricing"), mint.toBuffer()],
+      this.programId
+    )[0];
+  }
+
   // node management: registry
   nodeRegistry() {
     return PublicKey.findProgramAddressSync(
@@ -19,6 +27,16 @@ export class PDAHelper {
     )[0];
   }
 
+  // node management: registry page
+  registryPage(index: number) {
+    const indexBuf = Buffer.alloc(4);
+    indexBuf.writeUInt32LE(index);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("registry_page"), indexBuf],
+      this.programId
+    )[0];
+  }
+
   // node management: account
   nodeAccount(owner: PublicKey) {
     return PublicKey.findProgramAddressSync(
@@ -71,4 +89,48 @@ export class PDAHelper {
       this.programId
     );
   }
+
+  // Data Ops: PoS challenge for a shard in an epoch
+  challenge(upload: PublicKey, shardId: number, epoch: number) {
+    const epochBytes = Buffer.alloc(8);
+    epochBytes.writeBigUInt64LE(BigInt(epoch));
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("challenge"),
+        upload.toBuffer(),
+        Buffer.from([shardId]),
+        epochBytes,
+      ],
+      this.programId
+    )[0];
+  }
+
+  // Node Ops: per-shard reward claim ledger
+  shardClaim(upload: PublicKey, shardId: number, nodePda: PublicKey) {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("shard_claim"),
+        upload.toBuffer(),
+        Buffer.from([shardId]),
+        nodePda.toBuffer(),
+      ],
+      this.programId
+    )[0];
+  }
+
+  // Node Ops: endpoints, region and capacity a node advertises
+  nodeMetadata(nodePda: PublicKey) {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("node_metadata"), nodePda.toBuffer()],
+      this.programId
+    )[0];
+  }
+
+  // Node Ops: a delegator's position in a node's delegation pool
+  delegation(nodePda: PublicKey, delegator: PublicKey) {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("delegation"), nodePda.toBuffer(), delegator.toBuffer()],
+      this.programId
+    )[0];
+  }
 }
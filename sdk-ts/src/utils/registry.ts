@@ -0,0 +1,108 @@
+import { PublicKey } from "@solana/web3.js";
+import { createHash } from "crypto";
+
+// Paged node registry helpers matching the Solad program's candidate sampling.
+// `assignUpload` and `requestReplacement` draw nodes from a window of consecutive entries in
+// one registry page. The program fixes the page and start offset, so the candidate
+// accounts can be loaded before building the transaction. Replacements search up to
+// `MAX_REPLACEMENT_WINDOWS` windows in order, all fixed by the upload's selection seed.
+
+export const REGISTRY_PAGE_CAPACITY = 256; // Mirrors the program's REGISTRY_PAGE_CAPACITY
+export const MAX_REPLACEMENT_CANDIDATES = 8; // Mirrors the program's MAX_REPLACEMENT_CANDIDATES
+export const MAX_UPLOAD_CANDIDATES = 16; // Mirrors the program's MAX_UPLOAD_CANDIDATES
+export const MAX_REPLACEMENT_WINDOWS = 4; // Mirrors the program's MAX_REPLACEMENT_WINDOWS
+
+const SELECTION_DOMAIN = Buffer.from("solad:node_selection");
+const UPLOAD_WINDOW_DOMAIN = Buffer.from("solad:upload_window");
+const REPLACEMENT_WINDOW_DOMAIN = Buffer.from("solad:replacement_window");
+
+const u32le = (value: number) => {
+  const buf = Buffer.alloc(4);
+  buf.writeUInt32LE(value);
+  return buf;
+};
+
+// Mirrors the program's `derive_seed`: each part is length-prefixed.
+const deriveSeed = (entropy: Buffer, parts: Buffer[]): Buffer => {
+  const hash = createHash("sha256").update(SELECTION_DOMAIN).update(entropy);
+  for (const part of parts) {
+    hash.update(u32le(part.length)).update(part);
+  }
+  return hash.digest();
+};
+
+/**
+ * Returns the hash that draws from `seedSlot` in the raw SlotHashes sysvar data: that of the
+ * oldest listed slot at or after it. Mirrors the program's `challenge_seed`.
+ */
+export const slotSeed = (slotHashes: Buffer, seedSlot: bigint): Buffer => {
+  const count = Number(slotHashes.readBigUInt64LE(0));
+  if (count === 0) throw new Error("SlotHashes sysvar is empty");
+  if (slotHashes.readBigUInt64LE(8) < seedSlot) {
+    throw new Error("Seed slot has not passed yet");
+  }
+  if (slotHashes.readBigUInt64LE(8 + (count - 1) * 40) > seedSlot) {
+    throw new Error("Seed slot has aged out of SlotHashes");
+  }
+  for (let i = count - 1; i >= 0; i--) {
+    const offset = 8 + i * 40;
+    if (slotHashes.readBigUInt64LE(offset) >= seedSlot) {
+      return slotHashes.subarray(offset + 8, offset + 40);
+    }
+  }
+  throw new Error("unreachable");
+};
+
+/**
+ * Returns the selection seed the program derives for `upload` from the hash of its
+ * selection slot (see `slotSeed`).
+ */
+export const uploadSeed = (entropy: Buffer, upload: PublicKey): Buffer =>
+  deriveSeed(entropy, [UPLOAD_WINDOW_DOMAIN, upload.toBuffer()]);
+
+/**
+ * Returns the seed of the `attempt`th replacement window for a node leaving a shard, derived
+ * from the upload's stored `selectionSeed`. Pass it to `registryWindow`.
+ */
+export const replacementSeed = (
+  selectionSeed: Buffer,
+  dataHash: string,
+  shardId: number,
+  exitingNode: PublicKey,
+  attempt: number
+): Buffer =>
+  deriveSeed(selectionSeed, [
+    REPLACEMENT_WINDOW_DOMAIN,
+    Buffer.from(dataHash),
+    Buffer.from([shardId]),
+    exitingNode.toBuffer(),
+    Buffer.from([attempt]),
+  ]);
+
+/**
+ * Returns the registry page and start seed a selection seed fixes.
+ */
+export const registryWindow = (
+  seed: Buffer,
+  pageCount: number
+): { page: number; startSeed: bigint } => {
+  const page = seed.readBigUInt64LE(0) % BigInt(Math.max(pageCount, 1));
+  return { page: Number(page), startSeed: seed.readBigUInt64LE(8) };
+};
+
+/**
+ * Picks the candidate window out of a page's entries, in the order the program checks it.
+ */
+export const candidateWindow = (
+  entries: { node: PublicKey }[],
+  startSeed: bigint,
+  maxLength: number = MAX_REPLACEMENT_CANDIDATES
+): PublicKey[] => {
+  if (entries.length === 0) return [];
+  const start = Number(startSeed % BigInt(entries.length));
+  const size = Math.min(entries.length, maxLength);
+  return Array.from(
+    { length: size },
+    (_, i) => entries[(start + i) % entries.length].node
+  );
+};
@@ -1,7 +1,7 @@
 import { PublicKey } from "@solana/web3.js";
 import { PDAHelper } from "./pda-helper";
 import { Program } from "@coral-xyz/anchor";
-import { StorageConfig } from "../types";
+import { MintPricing, NodeMetadata, StorageConfig } from "../types";
 
 export class StateHelper {
   constructor(private programId: PublicKey) {}
@@ -11,4 +11,44 @@ export class StateHelper {
 
     return (program.account as any).storageConfig.fetch(pdas.storageConfig());
   }
+
+  async getMintPricing(program: Program, mint: PublicKey): Promise<MintPricing> {
+    const pdas = new PDAHelper(this.programId);
+    const pricing = await (program.account as any).mintPricing.fetch(
+      pdas.mintPricing(mint)
+    );
+
+    return {
+      ...pricing,
+      pricePerGb: pricing.pricePerGb.toNumber(),
+      pendingPricePerGb: pricing.pendingPricePerGb.toNumber(),
+      activationEpoch: pricing.activationEpoch.toNumber(),
+    };
+  }
+
+  async getNodeMetadata(
+    program: Program,
+    nodePda: PublicKey
+  ): Promise<NodeMetadata> {
+    const pdas = new PDAHelper(this.programId);
+    const metadata = await (program.account as any).nodeMetadata.fetch(
+      pdas.nodeMetadata(nodePda)
+    );
+
+    return {
+      ...metadata,
+      capacityMb: metadata.capacityMb.toNumber(),
+      updatedAt: metadata.updatedAt.toNumber(),
+    };
+  }
+
+  async getAllNodeMetadata(program: Program): Promise<NodeMetadata[]> {
+    const accounts = await (program.account as any).nodeMetadata.all();
+
+    return accounts.map(({ account }: any) => ({
+      ...account,
+      capacityMb: account.capacityMb.toNumber(),
+      updatedAt: account.updatedAt.toNumber(),
+    }));
+  }
 }
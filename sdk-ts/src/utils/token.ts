@@ -0,0 +1,49 @@
+import { PublicKey, SystemProgram, TransactionInstruction } from "@solana/web3.js";
+
+// SPL Token helpers for uploads paid in a token. Token escrows are the associated token
+// accounts of the upload's escrow PDA, so the payer creates them before `uploadData`.
+
+export const TOKEN_PROGRAM_ID = new PublicKey(
+  "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+);
+export const ASSOCIATED_TOKEN_PROGRAM_ID = new PublicKey(
+  "ATokenGPvbdGVxr1b2hvZbsiqW5xQSX6rsE3Xgd3BjA8f"
+);
+
+/**
+ * Returns the associated token account of `owner` for `mint`. Off-curve owners such as
+ * the escrow PDA are allowed.
+ */
+export const associatedTokenAddress = (
+  owner: PublicKey,
+  mint: PublicKey
+): PublicKey =>
+  PublicKey.findProgramAddressSync(
+    [owner.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  )[0];
+
+/**
+ * Creates the associated token account of `owner` for `mint` unless it already exists.
+ */
+export const createAssociatedTokenAccountIdempotentIx = (
+  payer: PublicKey,
+  owner: PublicKey,
+  mint: PublicKey
+): TransactionInstruction =>
+  new TransactionInstruction({
+    programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+    keys: [
+      { pubkey: payer, isSigner: true, isWritable: true },
+      {
+        pubkey: associatedTokenAddress(owner, mint),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: owner, isSigner: false, isWritable: false },
+      { pubkey: mint, isSigner: false, isWritable: false },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from([1]), // CreateIdempotent
+  });